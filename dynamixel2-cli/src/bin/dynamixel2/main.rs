@@ -144,6 +144,10 @@ fn do_main(options: Options) -> Result<(), ()> {
 			}
 			log::info!("{:?}: Ok", start.elapsed());
 		},
+		Command::Sniff { capture, registers } => {
+			let names = registers.map_or(&[][..], |registers| registers.names());
+			sniff(&options, capture.as_deref(), names)?;
+		},
 		Command::ShellCompletion { shell, output } => {
 			write_shell_completion(*shell, output.as_deref())?;
 		},
@@ -164,6 +168,70 @@ fn open_bus(options: &Options) -> Result<dynamixel2::Bus<Vec<u8>, Vec<u8>, Seria
 	Ok(bus)
 }
 
+fn sniff(options: &Options, capture: Option<&Path>, names: &[(u16, &str)]) -> Result<(), ()> {
+	let serial_port = SerialPort::open(&options.serial_port, options.baud_rate)
+		.map_err(|e| log::error!("Failed to open serial port: {}: {}", options.serial_port.display(), e))?;
+	log::debug!(
+		"Listening on serial port {} with baud rate {}",
+		options.serial_port.display(),
+		options.baud_rate
+	);
+
+	match capture {
+		None => log_sniffed_packets(dynamixel2::Sniffer::with_buffer(serial_port, vec![0; 1024]), names),
+		Some(path) => {
+			let file = std::fs::File::create(path).map_err(|e| log::error!("Failed to create {}: {}", path.display(), e))?;
+			let capture = dynamixel2::capture::CaptureWriter::new(file)
+				.map_err(|e| log::error!("Failed to write to {}: {}", path.display(), e))?;
+			let serial_port = dynamixel2::capture::CapturingSerialPort::new(serial_port, capture);
+			log::debug!("Recording packets to {}", path.display());
+			log_sniffed_packets(dynamixel2::Sniffer::with_buffer(serial_port, vec![0; 1024]), names)
+		},
+	}
+}
+
+fn log_sniffed_packets<T>(sniffer: dynamixel2::Sniffer<Vec<u8>, T>, names: &[(u16, &str)]) -> Result<(), ()>
+where
+	T: dynamixel2::SerialPort,
+	T::Error: std::fmt::Display,
+{
+	let mut last_instruction = None;
+	for packet in sniffer {
+		let packet = match packet {
+			Ok(packet) => packet,
+			Err(dynamixel2::ReadError::Io(e)) => {
+				log::error!("Failed to read from serial port: {}", e);
+				return Err(());
+			},
+			Err(e) => {
+				log::warn!("Invalid packet: {}", e);
+				continue;
+			},
+		};
+		let timestamp = packet.timestamp.as_secs_f64();
+		let pretty = dynamixel2::PrettyPacket::from_bytes(&packet.data).map(|pretty| pretty.with_names(names));
+		let Some(pretty) = pretty else {
+			continue;
+		};
+		match packet.content {
+			dynamixel2::SniffedContent::Instruction(_) => {
+				log::info!("[{:12.6}] #{} {}", timestamp, packet.sequence, pretty);
+				last_instruction = Some((packet.sequence, packet.timestamp));
+			},
+			dynamixel2::SniffedContent::Status(status) => {
+				let reply_to = match (status.reply_to, last_instruction) {
+					(Some(sequence), Some((instruction, sent))) if sequence == instruction => {
+						format!(" (reply to #{} after {:?})", sequence, packet.timestamp - sent)
+					},
+					_ => String::from(" (unsolicited)"),
+				};
+				log::info!("[{:12.6}] #{} {}{}", timestamp, packet.sequence, pretty, reply_to);
+			},
+		}
+	}
+	Ok(())
+}
+
 fn log_ping_response(response: &dynamixel2::Response<dynamixel2::instructions::Ping>, elapsed: Duration) {
 	log::info!("Motor ID: {}", response.motor_id);
 	log::info!(" ├─ Response time: {:?}", elapsed);
@@ -127,6 +127,19 @@ pub enum Command {
 		value: u32,
 	},
 
+	/// Listen to the bus without transmitting, and print every packet.
+	///
+	/// Status packets are matched with the instruction that they reply to.
+	Sniff {
+		/// Also record all packets in a pcapng capture file.
+		#[clap(long)]
+		capture: Option<PathBuf>,
+
+		/// Show register names from the control table of a motor model.
+		#[clap(long, value_enum)]
+		registers: Option<RegisterTable>,
+	},
+
 	/// Write shell completions to standard output or a file.
 	ShellCompletion {
 		/// The shell for which to generate completions.
@@ -138,6 +151,22 @@ pub enum Command {
 		output: Option<PathBuf>,
 	},
 }
+
+/// A table of register names.
+#[derive(Copy, Clone, clap::ValueEnum)]
+pub enum RegisterTable {
+	/// The control table of the XM430 series.
+	Xm430,
+}
+
+impl RegisterTable {
+	pub fn names(self) -> &'static [(u16, &'static str)] {
+		match self {
+			Self::Xm430 => dynamixel2::simulation::xm430::REGISTER_NAMES,
+		}
+	}
+}
+
 #[derive(Copy, Clone)]
 pub enum MotorId {
 	Id(u8),
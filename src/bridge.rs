@@ -0,0 +1,314 @@
+//! Bridge between two serial ports, to inspect, rewrite or block the traffic between them.
+
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use core::time::Duration;
+
+use crate::endian::read_u16_le;
+use crate::instructions::{instruction_id, packet_id};
+use crate::{Bus, Device, Packet, ReadError, SerialPort, TransferError};
+
+/// An instruction packet passing through a [`Bridge`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BridgedInstruction {
+	/// The packet ID: the ID of the addressed motor, or the broadcast ID.
+	pub packet_id: u8,
+
+	/// The instruction ID.
+	pub instruction_id: u8,
+
+	/// The parameters of the instruction.
+	pub parameters: Vec<u8>,
+}
+
+/// A status packet passing through a [`Bridge`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BridgedStatus {
+	/// The packet ID: the ID of the motor that sent the status packet.
+	pub packet_id: u8,
+
+	/// The error field of the status packet.
+	pub error: u8,
+
+	/// The parameters of the status packet.
+	pub parameters: Vec<u8>,
+}
+
+/// The decision of a [`BridgeHooks`] implementation about a packet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Verdict {
+	/// Forward the (possibly rewritten) packet.
+	Forward,
+
+	/// Drop the packet.
+	Block,
+}
+
+/// Hooks to inspect, rewrite or block the packets passing through a [`Bridge`].
+///
+/// All methods have a default implementation that forwards the packet unchanged.
+/// The unit type `()` can be used as hooks that do nothing.
+pub trait BridgeHooks {
+	/// Called for each instruction received from the upstream port, before it is forwarded to the downstream port.
+	///
+	/// The instruction can be modified in place, for example to remap motor IDs.
+	/// A blocked instruction is not forwarded, so the upstream side receives no status packet.
+	fn on_instruction(&mut self, instruction: &mut BridgedInstruction) -> Verdict {
+		let _ = instruction;
+		Verdict::Forward
+	}
+
+	/// Called for each status packet received from the downstream port, before it is relayed to the upstream port.
+	///
+	/// The `instruction` is the instruction as it was forwarded, after any modifications by [`Self::on_instruction()`].
+	/// The status packet can be modified in place.
+	fn on_status(&mut self, instruction: &BridgedInstruction, status: &mut BridgedStatus) -> Verdict {
+		let _ = (instruction, status);
+		Verdict::Forward
+	}
+}
+
+impl BridgeHooks for () {}
+
+impl<H: BridgeHooks + ?Sized> BridgeHooks for &mut H {
+	fn on_instruction(&mut self, instruction: &mut BridgedInstruction) -> Verdict {
+		(**self).on_instruction(instruction)
+	}
+
+	fn on_status(&mut self, instruction: &BridgedInstruction, status: &mut BridgedStatus) -> Verdict {
+		(**self).on_status(instruction, status)
+	}
+}
+
+/// An error that occurred while forwarding packets through a [`Bridge`].
+#[derive(Debug)]
+pub enum BridgeError<UpstreamError, DownstreamError> {
+	/// Receiving an instruction or sending a status packet on the upstream port failed.
+	Upstream(TransferError<UpstreamError>),
+
+	/// Sending an instruction or receiving a status packet on the downstream port failed.
+	Downstream(TransferError<DownstreamError>),
+}
+
+/// A bridge that forwards instructions from one serial port to another, and relays the status packets back.
+///
+/// The upstream side is a [`Device`], facing a controller such as the ROBOTIS Dynamixel Wizard.
+/// The downstream side is a [`Bus`], facing the real motors.
+/// Every instruction packet is parsed, passed to the [`BridgeHooks`], and written to the downstream bus.
+/// The status packets received in response are passed to the hooks as well, and written back to the upstream port.
+///
+/// The number of status packets to wait for is derived from the forwarded instruction:
+/// one for a unicast instruction, one per motor for a sync read or bulk read,
+/// and as many as arrive before the timeout for a broadcast ping.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use dynamixel2::{Bridge, BridgeError, Bus, Device, ReadError, SerialPort, TransferError};
+/// use std::time::Duration;
+///
+/// let upstream = Device::open("/dev/ttyUSB0", 57600)?;
+/// let downstream = Bus::open("/dev/ttyUSB1", 57600)?;
+/// let mut bridge = Bridge::new(upstream, downstream, ());
+/// loop {
+///   match bridge.forward_one(Duration::from_secs(1)) {
+///     Ok(()) => (),
+///     Err(BridgeError::Upstream(TransferError::ReadError(ReadError::Io(e)))) if e.kind() == std::io::ErrorKind::TimedOut => (),
+///     Err(e) => eprintln!("{e}"),
+///   }
+/// }
+/// # }
+/// ```
+pub struct Bridge<ReadBuffer, WriteBuffer, Upstream: SerialPort, Downstream: SerialPort, H> {
+	upstream: Device<ReadBuffer, WriteBuffer, Upstream>,
+	downstream: Bus<ReadBuffer, WriteBuffer, Downstream>,
+	hooks: H,
+}
+
+impl<ReadBuffer, WriteBuffer, Upstream, Downstream, H> Debug for Bridge<ReadBuffer, WriteBuffer, Upstream, Downstream, H>
+where
+	Upstream: SerialPort + Debug,
+	Downstream: SerialPort + Debug,
+	H: Debug,
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Bridge")
+			.field("upstream", &self.upstream)
+			.field("downstream", &self.downstream)
+			.field("hooks", &self.hooks)
+			.finish()
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, Upstream, Downstream, H> Bridge<ReadBuffer, WriteBuffer, Upstream, Downstream, H>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	Upstream: SerialPort,
+	Downstream: SerialPort,
+	H: BridgeHooks,
+{
+	/// Create a new bridge between an upstream device and a downstream bus.
+	pub fn new(
+		upstream: Device<ReadBuffer, WriteBuffer, Upstream>,
+		downstream: Bus<ReadBuffer, WriteBuffer, Downstream>,
+		hooks: H,
+	) -> Self {
+		Self {
+			upstream,
+			downstream,
+			hooks,
+		}
+	}
+
+	/// Get a reference to the upstream device.
+	pub fn upstream(&self) -> &Device<ReadBuffer, WriteBuffer, Upstream> {
+		&self.upstream
+	}
+
+	/// Get a mutable reference to the upstream device.
+	pub fn upstream_mut(&mut self) -> &mut Device<ReadBuffer, WriteBuffer, Upstream> {
+		&mut self.upstream
+	}
+
+	/// Get a reference to the downstream bus.
+	pub fn downstream(&self) -> &Bus<ReadBuffer, WriteBuffer, Downstream> {
+		&self.downstream
+	}
+
+	/// Get a mutable reference to the downstream bus.
+	pub fn downstream_mut(&mut self) -> &mut Bus<ReadBuffer, WriteBuffer, Downstream> {
+		&mut self.downstream
+	}
+
+	/// Get a reference to the hooks.
+	pub fn hooks(&self) -> &H {
+		&self.hooks
+	}
+
+	/// Get a mutable reference to the hooks.
+	pub fn hooks_mut(&mut self) -> &mut H {
+		&mut self.hooks
+	}
+
+	/// Consume the bridge to get back the upstream device, the downstream bus and the hooks.
+	pub fn into_parts(
+		self,
+	) -> (
+		Device<ReadBuffer, WriteBuffer, Upstream>,
+		Bus<ReadBuffer, WriteBuffer, Downstream>,
+		H,
+	) {
+		(self.upstream, self.downstream, self.hooks)
+	}
+
+	/// Wait for a single instruction on the upstream port, forward it and relay the status packets back.
+	///
+	/// If no instruction is received before the timeout, the timeout error of the upstream port is returned.
+	/// Missing status packets on the downstream port are not an error: the upstream side will simply time out as well.
+	pub fn forward_one(&mut self, timeout: Duration) -> Result<(), BridgeError<Upstream::Error, Downstream::Error>> {
+		let packet = self
+			.upstream
+			.read_instruction_packet_timeout(timeout)
+			.map_err(|e| BridgeError::Upstream(e.into()))?;
+		let mut instruction = BridgedInstruction {
+			packet_id: packet.packet_id(),
+			instruction_id: packet.instruction_id(),
+			parameters: packet.parameters().to_vec(),
+		};
+
+		if self.hooks.on_instruction(&mut instruction) == Verdict::Block {
+			debug!(
+				"bridge: blocked instruction 0x{:02X} for packet ID {}",
+				instruction.instruction_id, instruction.packet_id
+			);
+			return Ok(());
+		}
+
+		self.downstream
+			.write_instruction(
+				instruction.packet_id,
+				instruction.instruction_id,
+				instruction.parameters.len(),
+				|buffer| buffer.copy_from_slice(&instruction.parameters),
+			)
+			.map_err(|e| BridgeError::Downstream(e.into()))?;
+
+		let (expected_responses, expected_parameters) = expected_responses(&instruction);
+		let timeout = if instruction.packet_id == packet_id::BROADCAST && instruction.instruction_id == instruction_id::PING {
+			self.downstream.scan_timeout()
+		} else if instruction.packet_id == packet_id::BROADCAST {
+			self.downstream.status_response_timeout(None, expected_parameters)
+		} else {
+			self.downstream
+				.status_response_timeout(Some(instruction.packet_id), expected_parameters)
+		};
+
+		let mut received = 0;
+		while expected_responses.is_none_or(|expected| received < expected) {
+			let response = match self.downstream.read_raw_status_packet(timeout) {
+				Ok(response) => response,
+				Err(ReadError::Io(e)) if Downstream::is_timeout_error(&e) => break,
+				Err(e) => return Err(BridgeError::Downstream(e.into())),
+			};
+			if response.instruction_id() != instruction_id::STATUS {
+				trace!("bridge: ignoring packet with instruction 0x{:02X}", response.instruction_id());
+				continue;
+			}
+			received += 1;
+
+			let mut status = BridgedStatus {
+				packet_id: response.packet_id(),
+				error: response.error(),
+				parameters: response.parameters().to_vec(),
+			};
+			if self.hooks.on_status(&instruction, &mut status) == Verdict::Block {
+				debug!("bridge: blocked status packet from packet ID {}", status.packet_id);
+				continue;
+			}
+			self.upstream
+				.write_status(status.packet_id, status.error, status.parameters.len(), |buffer| {
+					buffer.copy_from_slice(&status.parameters)
+				})
+				.map_err(|e| BridgeError::Upstream(e.into()))?;
+		}
+		Ok(())
+	}
+}
+
+/// Determine the number of status packets and the expected parameter count of each status packet for an instruction.
+///
+/// The number of status packets is `None` if it is unknown.
+fn expected_responses(instruction: &BridgedInstruction) -> (Option<usize>, u16) {
+	let parameters = &instruction.parameters;
+	let read_length = || match parameters.get(2..4) {
+		Some(length) => read_u16_le(length),
+		None => 0,
+	};
+	let broadcast = instruction.packet_id == packet_id::BROADCAST;
+	match instruction.instruction_id {
+		instruction_id::PING if broadcast => (None, 3),
+		instruction_id::PING => (Some(1), 3),
+		instruction_id::READ => (Some(1), read_length()),
+		instruction_id::SYNC_READ => (Some(parameters.len().saturating_sub(4)), read_length()),
+		instruction_id::BULK_READ => {
+			let blocks = parameters.chunks_exact(5);
+			let length = blocks.clone().map(|block| read_u16_le(&block[3..5])).max().unwrap_or(0);
+			(Some(blocks.len()), length)
+		},
+		_ if broadcast => (Some(0), 0),
+		_ => (Some(1), 0),
+	}
+}
+
+impl<U: Display, D: Display> Display for BridgeError<U, D> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::Upstream(e) => write!(f, "upstream: {}", e),
+			Self::Downstream(e) => write!(f, "downstream: {}", e),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl<U: Debug + Display, D: Debug + Display> std::error::Error for BridgeError<U, D> {}
@@ -10,11 +10,27 @@ use std::path::Path;
 use alloc::{borrow::ToOwned, vec::Vec};
 use crate::instructions::instruction_id;
 use crate::messaging::Messenger;
+use crate::motor_settings::MotorSettings;
 use crate::packet::{Packet, STATUS_HEADER_SIZE};
+use crate::span::TransactionSpan;
+use crate::timing::{TimingRecorder, TransactionTiming};
+use crate::timeout::TimeoutSettings;
 
 /// Dynamixel Protocol 2 communication bus.
 pub struct Bus<ReadBuffer, WriteBuffer, T: SerialPort> {
 	messenger: Messenger<ReadBuffer, WriteBuffer, T>,
+
+	/// The known response settings of the motors on the bus.
+	pub(crate) motor_settings: MotorSettings,
+
+	/// The timeout model, overrides and latency measurements.
+	pub(crate) timeouts: TimeoutSettings,
+
+	/// The span of the current transaction, for the `tracing` feature.
+	span: TransactionSpan,
+
+	/// The timing of the current transaction and the summary of all previous transactions.
+	pub(crate) timing: TimingRecorder,
 }
 //
 impl<ReadBuffer, WriteBuffer, T> core::fmt::Debug for Bus<ReadBuffer, WriteBuffer, T>
@@ -38,7 +54,7 @@ impl Bus<Vec<u8>, Vec<u8>, serial2::SerialPort> {
 	pub fn open(path: impl AsRef<Path>, baud_rate: u32) -> std::io::Result<Self> {
 		let port = serial2::SerialPort::open(path, baud_rate)?;
 		let messenger = Messenger::with_buffers_and_baud_rate(port, vec![0; 128], vec![0; 128], baud_rate);
-		Ok(Self { messenger })
+		Ok(Self::from_messenger(messenger))
 	}
 
 	/// Create a new bus for an open serial port.
@@ -50,7 +66,7 @@ impl Bus<Vec<u8>, Vec<u8>, serial2::SerialPort> {
 	/// Use [`Self::with_buffers()`] if you want to use a custom buffers.
 	pub fn new(serial_port: serial2::SerialPort) -> std::io::Result<Self> {
 		let messenger = Messenger::with_buffers(serial_port, vec![0; 128], vec![0; 128])?;
-		Ok(Self { messenger })
+		Ok(Self::from_messenger(messenger))
 	}
 }
 
@@ -71,7 +87,7 @@ where
 	) -> std::io::Result<Self> {
 		let port = serial2::SerialPort::open(path, baud_rate)?;
 		let messenger = Messenger::with_buffers_and_baud_rate(port, read_buffer, write_buffer, baud_rate);
-		Ok(Self { messenger })
+		Ok(Self::from_messenger(messenger))
 	}
 }
 
@@ -91,7 +107,18 @@ where
 		write_buffer: WriteBuffer,
 	) -> Result<Self, T::Error> {
 		let messenger = Messenger::with_buffers(serial_port, read_buffer, write_buffer)?;
-		Ok(Self { messenger })
+		Ok(Self::from_messenger(messenger))
+	}
+
+	/// Wrap a messenger in a new bus.
+	fn from_messenger(messenger: Messenger<ReadBuffer, WriteBuffer, T>) -> Self {
+		Self {
+			messenger,
+			motor_settings: MotorSettings::new(),
+			timeouts: TimeoutSettings::new(),
+			span: TransactionSpan::default(),
+			timing: TimingRecorder::default(),
+		}
 	}
 
 	/// Get a reference to the underlying [`Transport`].
@@ -141,7 +168,7 @@ where
 		F: FnOnce(&mut [u8]),
 	{
 		self.write_instruction(packet_id, instruction_id, parameter_count, encode_parameters)?;
-		let response = self.read_status_response(expected_response_parameters)?;
+		let response = self.read_motor_status_response(packet_id, expected_response_parameters)?;
 		crate::error::InvalidPacketId::check(response.packet_id(), packet_id).map_err(crate::ReadError::from)?;
 		Ok(response)
 	}
@@ -154,31 +181,188 @@ where
 		parameter_count: usize,
 		encode_parameters: F,
 	) -> Result<(), WriteError<T::Error>>
+	where
+		F: FnOnce(&mut [u8]),
+	{
+		self.timeouts.set_current_instruction(instruction_id);
+		let mut parameter_head = [0; 4];
+		let message_len = self.messenger.encode_instruction(0, packet_id, instruction_id, parameter_count, |buffer| {
+			encode_parameters(buffer);
+			let head_len = buffer.len().min(parameter_head.len());
+			parameter_head[..head_len].copy_from_slice(&buffer[..head_len]);
+		})?;
+		let head_len = parameter_count.min(parameter_head.len());
+		self.send_instruction(Some(packet_id), instruction_id, &parameter_head[..head_len], parameter_count, message_len)
+	}
+
+	/// Encode an instruction message into the write buffer at the given offset, without sending it.
+	///
+	/// Returns the length of the encoded message.
+	pub(crate) fn encode_instruction_at<F>(
+		&mut self,
+		offset: usize,
+		packet_id: u8,
+		instruction_id: u8,
+		parameter_count: usize,
+		encode_parameters: F,
+	) -> Result<usize, crate::error::BufferTooSmallError>
 	where
 		F: FnOnce(&mut [u8]),
 	{
 		self.messenger
-			.write_instruction(packet_id, instruction_id, parameter_count, encode_parameters)
+			.encode_instruction(offset, packet_id, instruction_id, parameter_count, encode_parameters)
+	}
+
+	/// Send the first `len` bytes of the write buffer in a single write.
+	///
+	/// The `last_instruction_id` is used to select the read timeout for any responses.
+	pub(crate) fn send_write_buffer(&mut self, len: usize, last_instruction_id: u8) -> Result<(), WriteError<T::Error>> {
+		self.timeouts.set_current_instruction(last_instruction_id);
+		self.send_instruction(None, last_instruction_id, &[], 0, len)
+	}
+
+	/// Send the first `len` bytes of the write buffer, and start the span and timing of a new transaction.
+	///
+	/// The `motor_id` is `None` for a batch of instructions.
+	/// See [`TransactionSpan::start()`] for the meaning of the other parameters.
+	fn send_instruction(
+		&mut self,
+		motor_id: Option<u8>,
+		instruction_id: u8,
+		parameter_head: &[u8],
+		parameter_count: usize,
+		len: usize,
+	) -> Result<(), WriteError<T::Error>> {
+		self.span.start(motor_id, instruction_id, parameter_head, parameter_count, len);
+		let result = self.messenger.send_write_buffer(len);
+		self.timing.start(TransactionTiming {
+			instruction_id,
+			motor_id,
+			bytes_sent: len,
+			bytes_received: 0,
+			responses: 0,
+			transmit_time: message_transfer_time(len as u32, self.messenger.baud_rate),
+			receive_time: Duration::ZERO,
+			return_delay: motor_id.and_then(|motor_id| self.motor_settings.return_delay_time(motor_id)),
+			first_byte: None,
+			duration: self.messenger.elapsed(),
+		});
+		result.inspect_err(|_| self.span.record_write_error())
 	}
 
 	/// Read a raw status response from the bus with the given deadline.
-	pub fn read_status_response_timeout(&mut self, timeout: Duration) -> Result<StatusPacket, ReadError<T::Error>> {
-		let response: StatusPacket = self.messenger.read_packet_response_timeout(timeout)?;
+	pub fn read_status_response_timeout(&mut self, timeout: Duration) -> Result<StatusPacket<'_>, ReadError<T::Error>> {
+		let packet_len = self.receive_status_packet(None, timeout)?;
+		self.check_status_packet(packet_len)
+	}
 
-		crate::InvalidInstruction::check(response.instruction_id(), instruction_id::STATUS)?;
-		crate::MotorError::check(response.error())?;
+	/// Read a raw status packet from the bus without checking the instruction ID or the error field.
+	#[cfg(any(feature = "alloc", feature = "std"))]
+	pub(crate) fn read_raw_status_packet(&mut self, timeout: Duration) -> Result<StatusPacket<'_>, ReadError<T::Error>> {
+		let packet_len = self.receive_status_packet(None, timeout)?;
+		let response: StatusPacket = self.messenger.packet(packet_len);
+		self.span.record_response(response.packet_id(), self.messenger.parser.last_packet_size(), self.messenger.response_time);
 		Ok(response)
 	}
 
+	/// Receive a status packet into the read buffer, recording the timing and any error of the transaction.
+	///
+	/// Returns the length of the packet, which can be passed to [`Messenger::packet()`].
+	fn receive_status_packet(&mut self, motor_id: Option<u8>, timeout: Duration) -> Result<usize, ReadError<T::Error>> {
+		let result = self.messenger.receive_packet::<StatusPacket>(timeout);
+		let bytes_received = match result {
+			Ok(_) => self.messenger.parser.last_packet_size(),
+			Err(_) => 0,
+		};
+		self.timing.record_read(
+			bytes_received,
+			self.messenger.baud_rate,
+			self.messenger.first_byte_time,
+			self.messenger.elapsed(),
+		);
+		result.inspect_err(|e| self.span.record_read_error::<T>(motor_id, e))
+	}
+
+	/// Wrap a received status packet, and check the instruction ID and the error field.
+	///
+	/// The response and any error are recorded in the transaction span.
+	fn check_status_packet(&mut self, packet_len: usize) -> Result<StatusPacket<'_>, ReadError<T::Error>> {
+		let response: StatusPacket = self.messenger.packet(packet_len);
+		self.span.record_response(response.packet_id(), self.messenger.parser.last_packet_size(), self.messenger.response_time);
+		let result = crate::InvalidInstruction::check(response.instruction_id(), instruction_id::STATUS)
+			.map_err(ReadError::from)
+			.and_then(|()| crate::MotorError::check(response.error()).map_err(ReadError::from));
+		match result {
+			Ok(()) => Ok(response),
+			Err(e) => {
+				self.span.record_read_error::<T>(Some(response.packet_id()), &e);
+				Err(e)
+			},
+		}
+	}
+
 	/// Read a raw status response with an automatically calculated timeout.
 	///
-	/// The read timeout is determined by the expected number of response parameters and the baud rate of the bus.
-	pub fn read_status_response(&mut self, expected_parameters: u16) -> Result<StatusPacket, ReadError<T::Error>> {
-		// Official SDK adds a flat 34 milliseconds, so lets just mimick that.
-		let message_size = STATUS_HEADER_SIZE as u32 + u32::from(expected_parameters) + 2;
-		let timeout = message_transfer_time(message_size, self.messenger.baud_rate) + Duration::from_millis(34);
+	/// The read timeout is determined by the expected number of response parameters, the baud rate of the bus and the [`TimeoutModel`][crate::TimeoutModel] of the bus.
+	pub fn read_status_response(&mut self, expected_parameters: u16) -> Result<StatusPacket<'_>, ReadError<T::Error>> {
+		let timeout = self.status_response_timeout(None, expected_parameters);
 		self.read_status_response_timeout(timeout)
 	}
+
+	/// Read a raw status response from a specific motor with an automatically calculated timeout.
+	///
+	/// The read timeout also takes the known Return Delay Time and measured latency of the motor into account.
+	/// The latency of the response is recorded for the adaptive timeout model.
+	/// This does not check the packet ID of the response.
+	pub(crate) fn read_motor_status_response(&mut self, motor_id: u8, expected_parameters: u16) -> Result<StatusPacket<'_>, ReadError<T::Error>> {
+		self.read_motor_status_response_after(motor_id, expected_parameters, Duration::ZERO)
+	}
+
+	/// Read a raw status response from a specific motor, allowing extra time for outgoing data that may still be in transit.
+	///
+	/// The `transmit_time` is added to the read timeout and subtracted from the measured latency.
+	pub(crate) fn read_motor_status_response_after(
+		&mut self,
+		motor_id: u8,
+		expected_parameters: u16,
+		transmit_time: Duration,
+	) -> Result<StatusPacket<'_>, ReadError<T::Error>> {
+		let timeout = self.status_response_timeout(Some(motor_id), expected_parameters) + transmit_time;
+		let packet_len = match self.receive_status_packet(Some(motor_id), timeout) {
+			Ok(packet_len) => packet_len,
+			Err(ReadError::Io(e)) if T::is_timeout_error(&e) => {
+				self.timeouts.record_timeout(motor_id);
+				return Err(ReadError::Io(e));
+			},
+			Err(e) => return Err(e),
+		};
+
+		if let Some(response_time) = self.messenger.response_time {
+			let response: StatusPacket = self.messenger.packet(packet_len);
+			if response.packet_id() == motor_id {
+				let message_size = STATUS_HEADER_SIZE as u32 + response.parameters().len() as u32 + 2;
+				let latency = response_time
+					.saturating_sub(transmit_time)
+					.saturating_sub(message_transfer_time(message_size, self.messenger.baud_rate));
+				self.timeouts.record_latency(motor_id, latency);
+			}
+		}
+
+		self.check_status_packet(packet_len)
+	}
+
+	/// Compute the read timeout for a status response with the given number of parameters.
+	///
+	/// If there is a timeout override for the last instruction, it is returned as-is.
+	/// Otherwise, the [`TimeoutModel`][crate::TimeoutModel] of the bus determines the margin on top of the transfer time.
+	pub(crate) fn status_response_timeout(&self, motor_id: Option<u8>, expected_parameters: u16) -> Duration {
+		if let Some(timeout) = self.timeouts.current_override() {
+			return timeout;
+		}
+		let message_size = STATUS_HEADER_SIZE as u32 + u32::from(expected_parameters) + 2;
+		let return_delay = motor_id.and_then(|motor_id| self.motor_settings.return_delay_time(motor_id));
+		message_transfer_time(message_size, self.messenger.baud_rate) + self.timeouts.margin(motor_id, return_delay)
+	}
 }
 
 /// Calculate the required time to transfer a message of a given size.
@@ -231,6 +415,8 @@ impl<'a> StatusPacket<'a> {
 /// Note that the `Eq` and `PartialEq` compare all fields of the struct,
 /// including the `motor_id` and `alert`.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response<T> {
 	/// The motor that sent the response.
 	pub motor_id: u8,
@@ -268,6 +454,19 @@ impl<'a, 'b> From<&'b StatusPacket<'a>> for Response<&'b [u8]> {
 	}
 }
 
+impl<'a> From<StatusPacket<'a>> for Response<&'a [u8]> {
+	fn from(status_packet: StatusPacket<'a>) -> Self {
+		let motor_id = status_packet.packet_id();
+		let alert = status_packet.alert();
+		let StatusPacket { data } = status_packet;
+		Self {
+			motor_id,
+			alert,
+			data: &data[StatusPacket::HEADER_SIZE..],
+		}
+	}
+}
+
 #[cfg(any(feature = "alloc", feature = "std"))]
 impl<'a> From<StatusPacket<'a>> for Response<Vec<u8>> {
 	fn from(status_packet: StatusPacket<'a>) -> Self {
@@ -279,6 +478,21 @@ impl<'a> From<StatusPacket<'a>> for Response<Vec<u8>> {
 	}
 }
 
+impl<'a, const N: usize> TryFrom<StatusPacket<'a>> for Response<[u8; N]> {
+	type Error = crate::InvalidParameterCount;
+
+	fn try_from(status_packet: StatusPacket<'a>) -> Result<Self, Self::Error> {
+		crate::InvalidParameterCount::check(status_packet.parameters().len(), N)?;
+		let mut data = [0; N];
+		data.copy_from_slice(status_packet.parameters());
+		Ok(Self {
+			motor_id: status_packet.packet_id(),
+			alert: status_packet.alert(),
+			data,
+		})
+	}
+}
+
 impl<'a> TryFrom<StatusPacket<'a>> for Response<u8> {
 	type Error = crate::InvalidParameterCount;
 
@@ -0,0 +1,685 @@
+//! Packet capture files in the pcapng format.
+//!
+//! Captures can be written with a [`CaptureWriter`] and read back with a [`CaptureReader`].
+//! Every packet is stored as raw bytes, exactly as they appeared on the bus (including byte-stuffing and CRC),
+//! together with a timestamp and the direction of the packet.
+//!
+//! The interface in the capture file uses [`LINKTYPE_DYNAMIXEL2`] as link type by default.
+//! This is the first "user defined" link type, so Wireshark needs a dissector for it.
+//! A Lua dissector is available in the `wireshark` directory of the repository.
+//!
+//! To record the traffic of a live [`Bus`][crate::Bus], [`Device`][crate::Device] or [`Sniffer`][crate::Sniffer],
+//! wrap the serial port in a [`CapturingSerialPort`].
+//!
+//! # Example
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use dynamixel2::Bus;
+//! use dynamixel2::capture::{CaptureReader, CaptureWriter, CapturingSerialPort};
+//!
+//! let serial_port = serial2::SerialPort::open("/dev/ttyUSB0", 57600)?;
+//! let capture = CaptureWriter::new(std::fs::File::create("capture.pcapng")?)?;
+//! let mut bus = Bus::with_buffers(CapturingSerialPort::new(serial_port, capture), vec![0; 128], vec![0; 128])?;
+//! bus.ping(1)?;
+//! drop(bus);
+//!
+//! for packet in CaptureReader::new(std::fs::File::open("capture.pcapng")?)? {
+//!   let packet = packet?;
+//!   println!("{:?} {:?}: {:02X?}", packet.timestamp, packet.direction, packet.data);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::endian::read_u16_le;
+use crate::log::DisplayFmt;
+use crate::SerialPort;
+
+/// The link type used for captured Dynamixel Protocol 2.0 packets: `LINKTYPE_USER0`.
+pub const LINKTYPE_DYNAMIXEL2: u16 = 147;
+
+/// The block type of a section header block.
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+
+/// The block type of an interface description block.
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+
+/// The block type of an enhanced packet block.
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+
+/// The byte-order magic of a section header block.
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+/// The option code of the timestamp resolution of an interface.
+const OPTION_IF_TSRESOL: u16 = 9;
+
+/// The option code of the flags of an enhanced packet block.
+const OPTION_EPB_FLAGS: u16 = 2;
+
+/// The largest block accepted by the reader, to avoid huge allocations for corrupted files.
+const MAX_BLOCK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// The largest packet accepted by the [`Framer`], including the header and CRC.
+///
+/// This matches the maximum packet size of the official SDK.
+const MAX_PACKET_SIZE: usize = 1024;
+
+/// The direction of a captured packet, as seen from the capturing serial port.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Direction {
+	/// The direction is not known.
+	Unknown,
+
+	/// The packet was received by the capturing serial port.
+	Inbound,
+
+	/// The packet was transmitted by the capturing serial port.
+	Outbound,
+}
+
+/// A packet in a capture file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CapturedPacket {
+	/// The time at which the packet was captured.
+	pub timestamp: SystemTime,
+
+	/// The direction of the packet.
+	pub direction: Direction,
+
+	/// The raw bytes of the packet.
+	pub data: Vec<u8>,
+}
+
+/// Writer for pcapng capture files.
+///
+/// The section header and the interface description are written when the writer is created.
+/// Timestamps are stored with microsecond resolution.
+#[derive(Debug)]
+pub struct CaptureWriter<W: Write> {
+	writer: W,
+}
+
+impl<W: Write> CaptureWriter<W> {
+	/// Create a new capture writer with [`LINKTYPE_DYNAMIXEL2`] as link type.
+	pub fn new(writer: W) -> std::io::Result<Self> {
+		Self::with_link_type(writer, LINKTYPE_DYNAMIXEL2)
+	}
+
+	/// Create a new capture writer with a custom link type.
+	pub fn with_link_type(mut writer: W, link_type: u16) -> std::io::Result<Self> {
+		let mut block = Vec::with_capacity(32);
+		block.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+		block.extend_from_slice(&1u16.to_le_bytes()); // major version
+		block.extend_from_slice(&0u16.to_le_bytes()); // minor version
+		block.extend_from_slice(&(-1i64).to_le_bytes()); // section length: unknown
+		write_block(&mut writer, SECTION_HEADER_BLOCK, &block)?;
+
+		block.clear();
+		block.extend_from_slice(&link_type.to_le_bytes());
+		block.extend_from_slice(&0u16.to_le_bytes()); // reserved
+		block.extend_from_slice(&0u32.to_le_bytes()); // snap length: unlimited
+		write_option(&mut block, OPTION_IF_TSRESOL, &[6]);
+		write_option(&mut block, 0, &[]);
+		write_block(&mut writer, INTERFACE_DESCRIPTION_BLOCK, &block)?;
+
+		Ok(Self { writer })
+	}
+
+	/// Write a packet to the capture file.
+	pub fn write_packet(&mut self, packet: &CapturedPacket) -> std::io::Result<()> {
+		let timestamp = packet.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+		let flags: u32 = match packet.direction {
+			Direction::Unknown => 0,
+			Direction::Inbound => 1,
+			Direction::Outbound => 2,
+		};
+
+		let mut block = Vec::with_capacity(packet.data.len() + 40);
+		block.extend_from_slice(&0u32.to_le_bytes()); // interface ID
+		block.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+		block.extend_from_slice(&(timestamp as u32).to_le_bytes());
+		block.extend_from_slice(&(packet.data.len() as u32).to_le_bytes()); // captured length
+		block.extend_from_slice(&(packet.data.len() as u32).to_le_bytes()); // original length
+		block.extend_from_slice(&packet.data);
+		pad(&mut block);
+		write_option(&mut block, OPTION_EPB_FLAGS, &flags.to_le_bytes());
+		write_option(&mut block, 0, &[]);
+		write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &block)
+	}
+
+	/// Flush the underlying writer.
+	pub fn flush(&mut self) -> std::io::Result<()> {
+		self.writer.flush()
+	}
+
+	/// Get a reference to the underlying writer.
+	pub fn get_ref(&self) -> &W {
+		&self.writer
+	}
+
+	/// Consume the capture writer to get ownership of the underlying writer.
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+
+/// Write a block with the given type and body, adding the block length fields.
+fn write_block(writer: &mut impl Write, block_type: u32, body: &[u8]) -> std::io::Result<()> {
+	let total_length = (body.len() + 12) as u32;
+	writer.write_all(&block_type.to_le_bytes())?;
+	writer.write_all(&total_length.to_le_bytes())?;
+	writer.write_all(body)?;
+	writer.write_all(&total_length.to_le_bytes())
+}
+
+/// Append an option to a block body, padded to 32 bits.
+fn write_option(block: &mut Vec<u8>, code: u16, value: &[u8]) {
+	block.extend_from_slice(&code.to_le_bytes());
+	block.extend_from_slice(&(value.len() as u16).to_le_bytes());
+	block.extend_from_slice(value);
+	pad(block);
+}
+
+/// Pad a block body to a multiple of 32 bits.
+fn pad(block: &mut Vec<u8>) {
+	block.resize(block.len().next_multiple_of(4), 0);
+}
+
+/// Reader for pcapng capture files.
+///
+/// Both little endian and big endian sections are supported.
+/// Blocks other than section headers, interface descriptions and enhanced packet blocks are skipped.
+/// Packets are returned regardless of the link type of their interface.
+///
+/// The reader can also be used as an [`Iterator`] over the packets in the file.
+#[derive(Debug)]
+pub struct CaptureReader<R: Read> {
+	reader: R,
+	big_endian: bool,
+	interfaces: Vec<Interface>,
+}
+
+/// An interface described in a capture file.
+#[derive(Debug, Copy, Clone)]
+struct Interface {
+	link_type: u16,
+	units_per_second: u64,
+}
+
+impl<R: Read> CaptureReader<R> {
+	/// Create a new capture reader.
+	///
+	/// This reads the first block of the file, which must be a section header.
+	pub fn new(reader: R) -> std::io::Result<Self> {
+		let mut reader = Self {
+			reader,
+			big_endian: false,
+			interfaces: Vec::new(),
+		};
+		match reader.read_block()? {
+			Some((SECTION_HEADER_BLOCK, _)) => Ok(reader),
+			_ => Err(invalid_data("capture file does not start with a section header block")),
+		}
+	}
+
+	/// Get the link type of the first interface in the current section, if any.
+	pub fn link_type(&self) -> Option<u16> {
+		self.interfaces.first().map(|interface| interface.link_type)
+	}
+
+	/// Read the next packet from the capture file.
+	///
+	/// Returns `Ok(None)` at the end of the file.
+	pub fn read_packet(&mut self) -> std::io::Result<Option<CapturedPacket>> {
+		loop {
+			let (block_type, body) = match self.read_block()? {
+				Some(x) => x,
+				None => return Ok(None),
+			};
+			match block_type {
+				INTERFACE_DESCRIPTION_BLOCK => {
+					let interface = self.parse_interface(&body)?;
+					self.interfaces.push(interface);
+				},
+				ENHANCED_PACKET_BLOCK => return self.parse_packet(&body).map(Some),
+				_ => continue,
+			}
+		}
+	}
+
+	/// Consume the capture reader to get ownership of the underlying reader.
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+
+	/// Read the next block, returning the block type and the body without the length fields.
+	///
+	/// A section header block changes the byte order for all following blocks.
+	fn read_block(&mut self) -> std::io::Result<Option<(u32, Vec<u8>)>> {
+		let mut header = [0; 8];
+		if !read_exact_or_eof(&mut self.reader, &mut header)? {
+			return Ok(None);
+		}
+
+		// The section header block type is a palindrome, so it can be recognized before the byte order is known.
+		let mut body = Vec::new();
+		if header[..4] == SECTION_HEADER_BLOCK.to_le_bytes() {
+			let mut magic = [0; 4];
+			self.reader.read_exact(&mut magic)?;
+			self.big_endian = match magic {
+				x if x == BYTE_ORDER_MAGIC.to_be_bytes() => true,
+				x if x == BYTE_ORDER_MAGIC.to_le_bytes() => false,
+				_ => return Err(invalid_data("invalid byte-order magic in section header block")),
+			};
+			self.interfaces.clear();
+			body.extend_from_slice(&magic);
+		}
+
+		let block_type = self.u32(&header[0..4]);
+		let total_length = self.u32(&header[4..8]);
+		if total_length < 12 + body.len() as u32 || !total_length.is_multiple_of(4) || total_length > MAX_BLOCK_SIZE {
+			return Err(invalid_data(format!("invalid block length: {}", total_length)));
+		}
+
+		let already_read = body.len();
+		body.resize(total_length as usize - 12, 0);
+		self.reader.read_exact(&mut body[already_read..])?;
+		let mut trailer = [0; 4];
+		self.reader.read_exact(&mut trailer)?;
+		if self.u32(&trailer) != total_length {
+			return Err(invalid_data("block length fields do not match"));
+		}
+		Ok(Some((block_type, body)))
+	}
+
+	/// Parse the body of an interface description block.
+	fn parse_interface(&self, body: &[u8]) -> std::io::Result<Interface> {
+		if body.len() < 8 {
+			return Err(invalid_data("interface description block too short"));
+		}
+		let mut interface = Interface {
+			link_type: self.u16(&body[0..2]),
+			units_per_second: 1_000_000,
+		};
+		for (code, value) in self.options(&body[8..]) {
+			if code == OPTION_IF_TSRESOL && value.len() == 1 {
+				let exponent = u32::from(value[0] & 0x7F);
+				let base: u64 = if value[0] & 0x80 == 0 { 10 } else { 2 };
+				interface.units_per_second = base
+					.checked_pow(exponent)
+					.ok_or_else(|| invalid_data(format!("unsupported timestamp resolution: 0x{:02X}", value[0])))?;
+			}
+		}
+		Ok(interface)
+	}
+
+	/// Parse the body of an enhanced packet block.
+	fn parse_packet(&self, body: &[u8]) -> std::io::Result<CapturedPacket> {
+		if body.len() < 20 {
+			return Err(invalid_data("enhanced packet block too short"));
+		}
+		let interface_id = self.u32(&body[0..4]);
+		let interface = self
+			.interfaces
+			.get(interface_id as usize)
+			.ok_or_else(|| invalid_data(format!("packet refers to unknown interface {}", interface_id)))?;
+		let timestamp = u64::from(self.u32(&body[4..8])) << 32 | u64::from(self.u32(&body[8..12]));
+		let captured_length = self.u32(&body[12..16]) as usize;
+		let data = body
+			.get(20..20 + captured_length)
+			.ok_or_else(|| invalid_data("captured packet length exceeds block length"))?;
+
+		let mut direction = Direction::Unknown;
+		let options_start = (20 + captured_length).next_multiple_of(4).min(body.len());
+		for (code, value) in self.options(&body[options_start..]) {
+			if code == OPTION_EPB_FLAGS && value.len() == 4 {
+				direction = match self.u32(value) & 0b11 {
+					1 => Direction::Inbound,
+					2 => Direction::Outbound,
+					_ => Direction::Unknown,
+				};
+			}
+		}
+
+		let units_per_second = interface.units_per_second;
+		let nanos = u128::from(timestamp % units_per_second) * 1_000_000_000 / u128::from(units_per_second);
+		let timestamp = Duration::new(timestamp / units_per_second, nanos as u32);
+		Ok(CapturedPacket {
+			timestamp: UNIX_EPOCH + timestamp,
+			direction,
+			data: data.to_vec(),
+		})
+	}
+
+	/// Iterate over the options in a block, until the end-of-options marker or the end of the data.
+	fn options<'a>(&self, mut data: &'a [u8]) -> impl Iterator<Item = (u16, &'a [u8])> + 'a {
+		let big_endian = self.big_endian;
+		core::iter::from_fn(move || {
+			if data.len() < 4 {
+				return None;
+			}
+			let (code, length) = if big_endian {
+				(u16::from_be_bytes([data[0], data[1]]), u16::from_be_bytes([data[2], data[3]]))
+			} else {
+				(u16::from_le_bytes([data[0], data[1]]), u16::from_le_bytes([data[2], data[3]]))
+			};
+			let value = data.get(4..4 + length as usize)?;
+			if code == 0 {
+				return None;
+			}
+			data = data.get((4 + length as usize).next_multiple_of(4)..).unwrap_or_default();
+			Some((code, value))
+		})
+	}
+
+	fn u16(&self, data: &[u8]) -> u16 {
+		let data = [data[0], data[1]];
+		if self.big_endian {
+			u16::from_be_bytes(data)
+		} else {
+			u16::from_le_bytes(data)
+		}
+	}
+
+	fn u32(&self, data: &[u8]) -> u32 {
+		let data = [data[0], data[1], data[2], data[3]];
+		if self.big_endian {
+			u32::from_be_bytes(data)
+		} else {
+			u32::from_le_bytes(data)
+		}
+	}
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+	type Item = std::io::Result<CapturedPacket>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.read_packet().transpose()
+	}
+}
+
+/// Fill the buffer completely, or return `false` if the reader is at the end of the file.
+fn read_exact_or_eof(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<bool> {
+	let mut filled = 0;
+	while filled < buffer.len() {
+		match reader.read(&mut buffer[filled..]) {
+			Ok(0) if filled == 0 => return Ok(false),
+			Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+			Ok(n) => filled += n,
+			Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(true)
+}
+
+fn invalid_data(message: impl Into<String>) -> std::io::Error {
+	std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+/// A serial port wrapper that records all packets passing through it in a capture file.
+///
+/// Written packets are recorded as [`Direction::Outbound`] and received packets as [`Direction::Inbound`].
+/// The data is split into packets by looking for the packet header and the length field.
+/// Packets with an invalid length or CRC are not recorded, and neither are bytes outside of packets.
+///
+/// Errors writing the capture file do not interrupt the communication.
+/// Instead, the first error is stored and can be retrieved with [`Self::take_capture_error()`].
+#[derive(Debug)]
+pub struct CapturingSerialPort<T, W: Write> {
+	inner: T,
+	capture: CaptureWriter<W>,
+	read_framer: Framer,
+	write_framer: Framer,
+	error: Option<std::io::Error>,
+}
+
+impl<T, W: Write> CapturingSerialPort<T, W> {
+	/// Wrap a serial port to record all packets with the given capture writer.
+	pub fn new(inner: T, capture: CaptureWriter<W>) -> Self {
+		Self {
+			inner,
+			capture,
+			read_framer: Framer::default(),
+			write_framer: Framer::default(),
+			error: None,
+		}
+	}
+
+	/// Get a reference to the wrapped serial port.
+	pub fn inner(&self) -> &T {
+		&self.inner
+	}
+
+	/// Get a mutable reference to the wrapped serial port.
+	pub fn inner_mut(&mut self) -> &mut T {
+		&mut self.inner
+	}
+
+	/// Get a reference to the capture writer.
+	pub fn capture(&self) -> &CaptureWriter<W> {
+		&self.capture
+	}
+
+	/// Get a mutable reference to the capture writer.
+	pub fn capture_mut(&mut self) -> &mut CaptureWriter<W> {
+		&mut self.capture
+	}
+
+	/// Take the first error that occurred while writing the capture file, if any.
+	pub fn take_capture_error(&mut self) -> Option<std::io::Error> {
+		self.error.take()
+	}
+
+	/// Consume the wrapper to get back the wrapped serial port and the capture writer.
+	pub fn into_parts(self) -> (T, CaptureWriter<W>) {
+		(self.inner, self.capture)
+	}
+
+	/// Record all complete packets in a framer.
+	fn record(&mut self, direction: Direction) {
+		let framer = match direction {
+			Direction::Outbound => &mut self.write_framer,
+			_ => &mut self.read_framer,
+		};
+		while let Some(data) = framer.next_packet() {
+			let packet = CapturedPacket {
+				timestamp: SystemTime::now(),
+				direction,
+				data,
+			};
+			if let Err(e) = self.capture.write_packet(&packet) {
+				debug!("failed to write packet to capture file: {}", DisplayFmt(&e));
+				self.error.get_or_insert(e);
+			}
+		}
+	}
+}
+
+/// Splits a stream of bytes into packets.
+#[derive(Debug, Default)]
+pub(crate) struct Framer {
+	pub(crate) buffer: Vec<u8>,
+}
+
+impl Framer {
+	/// Take the next complete packet from the buffer, discarding any bytes before the header.
+	///
+	/// If the length field or the CRC of a packet is invalid, the header is discarded and the search continues with the next header.
+	pub(crate) fn next_packet(&mut self) -> Option<Vec<u8>> {
+		loop {
+			let start = crate::parser::find_header(&self.buffer);
+			self.buffer.drain(..start);
+			if self.buffer.len() < 7 {
+				return None;
+			}
+			// The length field covers at least the instruction ID and the CRC.
+			let length = 7 + read_u16_le(&self.buffer[5..7]) as usize;
+			if !(10..=MAX_PACKET_SIZE).contains(&length) {
+				self.skip_header();
+				continue;
+			}
+			if self.buffer.len() < length {
+				return None;
+			}
+			let checksum = read_u16_le(&self.buffer[length - 2..length]);
+			if checksum != crate::checksum::calculate_checksum(0, &self.buffer[..length - 2]) {
+				self.skip_header();
+				continue;
+			}
+			return Some(self.buffer.drain(..length).collect());
+		}
+	}
+
+	/// Discard the header at the start of the buffer, to search for the next one.
+	fn skip_header(&mut self) {
+		trace!("skipping packet header with invalid length or CRC: {:?}", crate::log::HexBytes(&self.buffer[..7]));
+		self.buffer.drain(..crate::packet::HEADER_PREFIX.len());
+	}
+}
+
+impl<T: SerialPort, W: Write> SerialPort for CapturingSerialPort<T, W> {
+	type Error = T::Error;
+
+	type Instant = T::Instant;
+
+	fn baud_rate(&self) -> Result<u32, Self::Error> {
+		self.inner.baud_rate()
+	}
+
+	fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> {
+		self.inner.set_baud_rate(baud_rate)
+	}
+
+	fn discard_input_buffer(&mut self) -> Result<(), Self::Error> {
+		self.read_framer.buffer.clear();
+		self.inner.discard_input_buffer()
+	}
+
+	fn read(&mut self, buffer: &mut [u8], deadline: &Self::Instant) -> Result<usize, Self::Error> {
+		let read = self.inner.read(buffer, deadline)?;
+		self.read_framer.buffer.extend_from_slice(&buffer[..read]);
+		self.record(Direction::Inbound);
+		Ok(read)
+	}
+
+	fn write_all(&mut self, buffer: &[u8]) -> Result<(), Self::Error> {
+		self.write_framer.buffer.extend_from_slice(buffer);
+		self.record(Direction::Outbound);
+		self.inner.write_all(buffer)
+	}
+
+	fn make_deadline(&self, timeout: Duration) -> Self::Instant {
+		self.inner.make_deadline(timeout)
+	}
+
+	fn is_timeout_error(error: &Self::Error) -> bool {
+		T::is_timeout_error(error)
+	}
+
+	fn remaining_time(&self, deadline: &Self::Instant) -> Option<Duration> {
+		self.inner.remaining_time(deadline)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_round_trip() {
+		let packets = [
+			CapturedPacket {
+				timestamp: UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_000),
+				direction: Direction::Outbound,
+				data: vec![0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E],
+			},
+			CapturedPacket {
+				timestamp: UNIX_EPOCH + Duration::new(1_700_000_001, 0),
+				direction: Direction::Inbound,
+				data: vec![1, 2, 3, 4, 5],
+			},
+		];
+
+		let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+		for packet in &packets {
+			writer.write_packet(packet).unwrap();
+		}
+		let data = writer.into_inner();
+		assert!(data.len() % 4 == 0);
+
+		let_assert!(Ok(mut reader) = CaptureReader::new(data.as_slice()));
+		let_assert!(Ok(Some(first)) = reader.read_packet());
+		assert!(reader.link_type() == Some(LINKTYPE_DYNAMIXEL2));
+		assert!(first == packets[0]);
+		let_assert!(Ok(Some(second)) = reader.read_packet());
+		assert!(second == packets[1]);
+		assert!(let Ok(None) = reader.read_packet());
+	}
+
+	#[test]
+	fn test_read_big_endian() {
+		#[rustfmt::skip]
+		let data = [
+			// Section header block.
+			0x0A, 0x0D, 0x0D, 0x0A, 0, 0, 0, 28, 0x1A, 0x2B, 0x3C, 0x4D, 0, 1, 0, 0,
+			0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 28,
+			// Interface description block with millisecond timestamps.
+			0, 0, 0, 1, 0, 0, 0, 32, 0, 147, 0, 0, 0, 0, 0, 0,
+			0, 9, 0, 1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 32,
+			// Enhanced packet block with a single byte, received.
+			0, 0, 0, 6, 0, 0, 0, 44, 0, 0, 0, 0, 0, 0, 0, 0,
+			0, 0, 0x03, 0xE9, 0, 0, 0, 1, 0, 0, 0, 1, 0xAB, 0, 0, 0,
+			0, 2, 0, 4, 0, 0, 0, 1, 0, 0, 0, 44,
+		];
+		let_assert!(Ok(mut reader) = CaptureReader::new(data.as_slice()));
+		let_assert!(Ok(Some(packet)) = reader.read_packet());
+		assert!(packet.timestamp == UNIX_EPOCH + Duration::from_millis(1001));
+		assert!(packet.direction == Direction::Inbound);
+		assert!(packet.data == [0xAB]);
+		assert!(let Ok(None) = reader.read_packet());
+	}
+
+	#[test]
+	fn test_framer() {
+		let mut framer = Framer::default();
+		framer
+			.buffer
+			.extend_from_slice(&[0x00, 0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19]);
+		assert!(framer.next_packet() == None);
+		framer.buffer.extend_from_slice(&[0x4E, 0xFF, 0xFF]);
+		assert!(framer.next_packet() == Some(vec![0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E]));
+		assert!(framer.next_packet() == None);
+		assert!(framer.buffer == [0xFF, 0xFF]);
+	}
+
+	#[test]
+	fn test_framer_resync() {
+		let ping = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E];
+		let mut framer = Framer::default();
+
+		// A corrupted length field must not swallow the following packet.
+		framer.buffer.extend_from_slice(&[0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x0A, 0x00, 0x01]);
+		framer.buffer.extend_from_slice(&ping);
+		framer.buffer.extend_from_slice(&[0; 4]);
+		assert!(framer.next_packet().as_deref() == Some(&ping[..]));
+
+		// A length beyond the maximum packet size is rejected without waiting for more data.
+		framer.buffer.clear();
+		framer.buffer.extend_from_slice(&[0xFF, 0xFF, 0xFD, 0x00, 0x01, 0xFF, 0xFF, 0x01]);
+		framer.buffer.extend_from_slice(&ping);
+		assert!(framer.next_packet().as_deref() == Some(&ping[..]));
+
+		// A length that is too short for the instruction ID and CRC is rejected too.
+		framer.buffer.clear();
+		framer.buffer.extend_from_slice(&[0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x01, 0x00]);
+		framer.buffer.extend_from_slice(&ping);
+		assert!(framer.next_packet().as_deref() == Some(&ping[..]));
+		assert!(framer.buffer.is_empty());
+	}
+}
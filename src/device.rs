@@ -1,15 +1,31 @@
 use crate::endian::read_u16_le;
-use crate::instructions::instruction_id;
+use crate::instructions::{instruction_id, BulkWriteData, SyncWriteData};
 use crate::messaging::Messenger;
-use crate::{InvalidParameterCount, Packet, ReadError, SerialPort, WriteError};
+use crate::packet::STATUS_HEADER_SIZE;
+use crate::{InvalidParameterCount, Packet, ReadError, SerialPort, StatusPacket, StatusReturnLevel, WriteError};
 use core::time::Duration;
 
 #[cfg(feature = "alloc")]
 use alloc::{borrow::ToOwned, vec::Vec};
 
 /// Dynamixel [`Device`] for communicating with a [`Bus`].
+///
+/// Like a real motor, the device waits for the [Return Delay Time][Self::set_return_delay_time] before sending a status packet,
+/// and it only sends status packets that are allowed by the [Status Return Level][Self::set_status_return_level].
 pub struct Device<ReadBuffer, WriteBuffer, T: SerialPort> {
 	messenger: Messenger<ReadBuffer, WriteBuffer, T>,
+
+	/// The time to wait before sending a status packet.
+	return_delay_time: Duration,
+
+	/// The instructions that are answered with a status packet.
+	status_return_level: StatusReturnLevel,
+
+	/// The packet ID and instruction ID of the last received instruction.
+	last_instruction: Option<(u8, u8)>,
+
+	/// The length of the last received instruction packet, while it is still in the read buffer.
+	last_instruction_len: Option<usize>,
 }
 
 impl<ReadBuffer, WriteBuffer, T> core::fmt::Debug for Device<ReadBuffer, WriteBuffer, T>
@@ -20,10 +36,25 @@ where
 		f.debug_struct("Device")
 			.field("serial_port", &self.messenger.serial_port)
 			.field("baud_rate", &self.messenger.baud_rate)
+			.field("return_delay_time", &self.return_delay_time)
+			.field("status_return_level", &self.status_return_level)
 			.finish_non_exhaustive()
 	}
 }
 
+impl<ReadBuffer, WriteBuffer, T: SerialPort> Device<ReadBuffer, WriteBuffer, T> {
+	/// Create a new device with the default Return Delay Time and Status Return Level.
+	fn from_messenger(messenger: Messenger<ReadBuffer, WriteBuffer, T>) -> Self {
+		Self {
+			messenger,
+			return_delay_time: Duration::ZERO,
+			status_return_level: StatusReturnLevel::All,
+			last_instruction: None,
+			last_instruction_len: None,
+		}
+	}
+}
+
 #[cfg(feature = "serial2")]
 impl Device<Vec<u8>, Vec<u8>, serial2::SerialPort> {
 	/// Open a serial port with the given baud rate.
@@ -33,7 +64,7 @@ impl Device<Vec<u8>, Vec<u8>, serial2::SerialPort> {
 	pub fn open(path: impl AsRef<std::path::Path>, baud_rate: u32) -> std::io::Result<Self> {
 		let port = serial2::SerialPort::open(path, baud_rate)?;
 		let messenger = Messenger::with_buffers_and_baud_rate(port, vec![0; 128], vec![0; 128], baud_rate);
-		Ok(Self { messenger })
+		Ok(Self::from_messenger(messenger))
 	}
 
 	/// Create a new device for an open serial port.
@@ -45,7 +76,7 @@ impl Device<Vec<u8>, Vec<u8>, serial2::SerialPort> {
 	/// Use [`Self::with_buffers()`] if you want to use a custom buffers.
 	pub fn new(serial_port: serial2::SerialPort) -> std::io::Result<Self> {
 		let messenger = Messenger::with_buffers(serial_port, vec![0; 128], vec![0; 128])?;
-		Ok(Self { messenger })
+		Ok(Self::from_messenger(messenger))
 	}
 }
 
@@ -66,7 +97,7 @@ where
 	) -> std::io::Result<Self> {
 		let port = serial2::SerialPort::open(path, baud_rate)?;
 		let messenger = Messenger::with_buffers_and_baud_rate(port, read_buffer, write_buffer, baud_rate);
-		Ok(Self { messenger })
+		Ok(Self::from_messenger(messenger))
 	}
 }
 impl<ReadBuffer, WriteBuffer, T> Device<ReadBuffer, WriteBuffer, T>
@@ -82,7 +113,7 @@ where
 		write_buffer: WriteBuffer,
 	) -> Result<Self, T::Error> {
 		let messenger = Messenger::with_buffers(serial_port, read_buffer, write_buffer)?;
-		Ok(Device { messenger })
+		Ok(Self::from_messenger(messenger))
 	}
 
 	/// Get a reference to the underlying [`Transport`].
@@ -114,6 +145,38 @@ where
 		Ok(())
 	}
 
+	/// Get the time the device waits before sending a status packet.
+	pub fn return_delay_time(&self) -> Duration {
+		self.return_delay_time
+	}
+
+	/// Set the time the device waits before sending a status packet.
+	///
+	/// This emulates the Return Delay Time of a real motor.
+	/// The default is zero, so status packets are sent immediately.
+	pub fn set_return_delay_time(&mut self, delay: Duration) {
+		self.return_delay_time = delay;
+	}
+
+	/// Get the Status Return Level of the device.
+	pub fn status_return_level(&self) -> StatusReturnLevel {
+		self.status_return_level
+	}
+
+	/// Set the Status Return Level of the device.
+	///
+	/// The status packet functions silently skip status packets for instructions that the Status Return Level does not allow.
+	/// Regardless of the Status Return Level, status packets are never sent for broadcast instructions,
+	/// except for ping, sync read and bulk read.
+	///
+	/// The decision is based on the last instruction received with [`Self::read()`], [`Self::read_owned()`] or [`Self::read_instruction_packet_timeout()`].
+	/// If no instruction has been received yet, status packets are always sent.
+	///
+	/// The default is [`StatusReturnLevel::All`].
+	pub fn set_status_return_level(&mut self, level: StatusReturnLevel) {
+		self.status_return_level = level;
+	}
+
 	/// Read a single [`Instruction`] with borrowed data
 	///
 	/// Use [`Device::read_owned`] to received owned data
@@ -142,10 +205,67 @@ where
 	where
 		F: FnOnce(&mut [u8]),
 	{
+		if !self.prepare_status()? {
+			return Ok(());
+		}
 		self.messenger
 			.write_status(packet_id, instruction_id::STATUS, error, parameter_count, encode_parameters)
 	}
 
+	/// Write a status message with parameters that may fail to encode.
+	///
+	/// If `encode_parameters` returns an error code, an empty status message with that error code is sent instead.
+	pub fn write_status_with<F>(
+		&mut self,
+		packet_id: u8,
+		error: u8,
+		parameter_count: usize,
+		encode_parameters: F,
+	) -> Result<(), WriteError<T::Error>>
+	where
+		F: FnOnce(&mut [u8]) -> Result<(), u8>,
+	{
+		crate::error::BufferTooSmallError::check(STATUS_HEADER_SIZE + parameter_count + 2, self.messenger.write_buffer.as_ref().len())?;
+		if !self.prepare_status()? {
+			return Ok(());
+		}
+		let mut result = Ok(());
+		let message_len = self
+			.messenger
+			.encode_instruction(0, packet_id, instruction_id::STATUS, parameter_count + 1, |buffer| {
+				buffer[0] = error;
+				result = encode_parameters(&mut buffer[1..]);
+			})?;
+		match result {
+			Ok(()) => self.messenger.send_write_buffer(message_len),
+			Err(error) => self
+				.messenger
+				.write_status(packet_id, instruction_id::STATUS, error, 0, |_| {}),
+		}
+	}
+
+	/// Check if a status packet should be sent for the last instruction, and wait for the Return Delay Time if so.
+	fn prepare_status(&mut self) -> Result<bool, WriteError<T::Error>> {
+		if let Some((packet_id, instruction_id)) = self.last_instruction {
+			let responds_to_broadcast = matches!(
+				instruction_id,
+				instruction_id::PING | instruction_id::SYNC_READ | instruction_id::BULK_READ
+			);
+			if packet_id == crate::instructions::packet_id::BROADCAST && !responds_to_broadcast {
+				return Ok(false);
+			}
+			if !self.status_return_level.responds_to(instruction_id) {
+				return Ok(false);
+			}
+		}
+		// Waiting and writing discard the last instruction from the read buffer.
+		self.last_instruction_len = None;
+		self.messenger
+			.delay(self.return_delay_time)
+			.map_err(WriteError::Read)?;
+		Ok(true)
+	}
+
 	/// Write an empty status message with an error code.
 	pub fn write_status_error(&mut self, packet_id: u8, error: u8) -> Result<(), WriteError<T::Error>> {
 		self.write_status(packet_id, error, 0, |_| {})
@@ -156,9 +276,63 @@ where
 		self.write_status(packet_id, 0, 0, |_| {})
 	}
 
+	/// Wait for a status packet from another motor on the bus.
+	///
+	/// Motors respond to a sync read or bulk read one after another, in the order of the motor IDs in the instruction.
+	/// A device that takes part in such a read must wait for the status packets of all motors before it.
+	///
+	/// This returns [`StatusWait::Received`] when a status packet from the motor is received,
+	/// or when a corrupted packet is received in its place.
+	/// Status packets from other motors are skipped, without extending the timeout.
+	/// If nothing is received before the timeout, this returns [`StatusWait::TimedOut`] and the motor is assumed to be absent.
+	///
+	/// If an instruction packet is received instead, the bus stopped waiting for status packets and sent a new instruction.
+	/// In that case, this returns [`StatusWait::Interrupted`] and the instruction is returned by the next read.
+	pub fn wait_for_status_packet(&mut self, motor_id: u8, timeout: Duration) -> Result<StatusWait, ReadError<T::Error>> {
+		self.last_instruction_len = None;
+		let deadline = self.messenger.serial_port.make_deadline(timeout);
+		loop {
+			match self.messenger.receive_status_packet_before(&deadline) {
+				Ok(Some(packet_len)) => {
+					let packet: StatusPacket = self.messenger.packet(packet_len);
+					if packet.packet_id() == motor_id {
+						return Ok(StatusWait::Received);
+					}
+				},
+				Ok(None) => return Ok(StatusWait::Interrupted),
+				Err(ReadError::Io(e)) if T::is_timeout_error(&e) => return Ok(StatusWait::TimedOut),
+				Err(ReadError::InvalidMessage(_)) => return Ok(StatusWait::Received),
+				Err(e) => return Err(e),
+			}
+		}
+	}
+
 	/// Read a single [`InstructionPacket`].
-	pub fn read_instruction_packet_timeout(&mut self, timeout: Duration) -> Result<InstructionPacket, ReadError<T::Error>> {
-		self.messenger.read_packet_response_timeout(timeout)
+	pub fn read_instruction_packet_timeout(&mut self, timeout: Duration) -> Result<InstructionPacket<'_>, ReadError<T::Error>> {
+		self.last_instruction_len = None;
+		let packet_len = self.messenger.receive_packet::<InstructionPacket>(timeout)?;
+		let packet: InstructionPacket = self.messenger.packet(packet_len);
+		self.last_instruction = Some((packet.packet_id(), packet.instruction_id()));
+		self.last_instruction_len = Some(packet_len);
+		Ok(packet)
+	}
+
+	/// Get the data addressed to a specific motor in the last received instruction, if it is a sync write or bulk write.
+	///
+	/// Returns `Ok(None)` if the last instruction is not a sync write or bulk write, or if it has no data for the motor.
+	/// Malformed instructions are rejected with an error, even if the data for the motor could be extracted.
+	///
+	/// The last instruction is only available until a status packet is written or [`Self::wait_for_status_packet()`] is called.
+	/// After that, this also returns `Ok(None)`.
+	pub fn write_data_for(&self, motor_id: u8) -> Result<Option<BulkWriteData<&[u8]>>, InvalidParameterCount> {
+		let Some(packet_len) = self.last_instruction_len else {
+			return Ok(None);
+		};
+		let packet: InstructionPacket = self.messenger.packet(packet_len);
+		if !matches!(packet.instruction_id(), instruction_id::SYNC_WRITE | instruction_id::BULK_WRITE) {
+			return Ok(None);
+		}
+		Instruction::try_from(packet)?.instruction.write_data_for(motor_id)
 	}
 }
 
@@ -168,8 +342,25 @@ pub struct InstructionPacket<'a> {
 	pub(crate) data: &'a [u8],
 }
 
+/// The outcome of [`Device::wait_for_status_packet()`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum StatusWait {
+	/// The status packet of the motor was received, or a corrupted packet was received in its place.
+	Received,
+	/// Nothing was received from the motor before the timeout.
+	TimedOut,
+	/// An instruction packet was received, so the bus is no longer waiting for status packets.
+	///
+	/// The instruction is kept in the read buffer of the device.
+	Interrupted,
+}
+
 /// The options for the [Factory Reset](https://emanual.robotis.com/docs/en/dxl/protocol2/#factory-reset-0x06) instruction.
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum FactoryReset {
 	/// Reset all values to their factory defaults.
 	All,
@@ -182,7 +373,9 @@ pub enum FactoryReset {
 }
 
 /// The options for the [Clear](https://emanual.robotis.com/docs/en/dxl/protocol2/#clear-0x10) instruction.
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum Clear {
 	/// Reset the Present Position value to an absolute value within one rotation (0-4095).
 	MultiTurns,
@@ -198,7 +391,9 @@ pub enum Clear {
 /// It contains the ID and parameters.
 /// The owned data variant requires the `alloc` feature.
 ///
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Instruction<T> {
 	/// The ID of the packet
 	pub id: u8,
@@ -209,7 +404,9 @@ pub struct Instruction<T> {
 /// Instructions as defined in the [Dynamixel Protocol 2.0](https://emanual.robotis.com/docs/en/dxl/protocol2/#instruction-details).
 /// The parameters are stored as a &[u8] slice or a Vec<u8>.
 #[allow(missing_docs)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum Instructions<T> {
 	Ping,
 	Read { address: u16, length: u16 },
@@ -226,6 +423,194 @@ pub enum Instructions<T> {
 	Unknown { instruction: u8, parameters: T },
 }
 
+impl<T: AsRef<[u8]>> Instructions<T> {
+	/// Get the instruction with borrowed parameters.
+	///
+	/// This gives access to the functions that are only available for borrowed parameters,
+	/// like [`Self::sync_write_iter()`], for instructions with owned parameters.
+	pub fn as_deref(&self) -> Instructions<&[u8]> {
+		match self {
+			Self::Ping => Instructions::Ping,
+			Self::Read { address, length } => Instructions::Read {
+				address: *address,
+				length: *length,
+			},
+			Self::Write { address, parameters } => Instructions::Write {
+				address: *address,
+				parameters: parameters.as_ref(),
+			},
+			Self::RegWrite { address, parameters } => Instructions::RegWrite {
+				address: *address,
+				parameters: parameters.as_ref(),
+			},
+			Self::Action => Instructions::Action,
+			Self::FactoryReset(kind) => Instructions::FactoryReset(*kind),
+			Self::Reboot => Instructions::Reboot,
+			Self::Clear(kind) => Instructions::Clear(*kind),
+			Self::SyncRead { address, length, ids } => Instructions::SyncRead {
+				address: *address,
+				length: *length,
+				ids: ids.as_ref(),
+			},
+			Self::SyncWrite { address, length, parameters } => Instructions::SyncWrite {
+				address: *address,
+				length: *length,
+				parameters: parameters.as_ref(),
+			},
+			Self::BulkRead { parameters } => Instructions::BulkRead {
+				parameters: parameters.as_ref(),
+			},
+			Self::BulkWrite { parameters } => Instructions::BulkWrite {
+				parameters: parameters.as_ref(),
+			},
+			Self::Unknown { instruction, parameters } => Instructions::Unknown {
+				instruction: *instruction,
+				parameters: parameters.as_ref(),
+			},
+		}
+	}
+}
+
+impl<'a> Instructions<&'a [u8]> {
+	/// Iterate over the data for each motor in a sync write instruction.
+	///
+	/// Returns `None` if the instruction is not a sync write.
+	/// Returns an error if the parameters can not be divided into blocks of the data length plus the motor ID.
+	///
+	/// For instructions with owned parameters, use [`Instructions::as_deref()`] first.
+	pub fn sync_write_iter(&self) -> Option<Result<SyncWriteIter<'a>, InvalidParameterCount>> {
+		match *self {
+			Self::SyncWrite { address, length, parameters } => Some(SyncWriteIter::new(address, length, parameters)),
+			_ => None,
+		}
+	}
+
+	/// Iterate over the data for each motor in a bulk write instruction.
+	///
+	/// Returns `None` if the instruction is not a bulk write.
+	/// Returns an error if the parameters are truncated in the middle of a block.
+	///
+	/// For instructions with owned parameters, use [`Instructions::as_deref()`] first.
+	pub fn bulk_write_iter(&self) -> Option<Result<BulkWriteIter<'a>, InvalidParameterCount>> {
+		match *self {
+			Self::BulkWrite { parameters } => Some(BulkWriteIter::new(parameters)),
+			_ => None,
+		}
+	}
+
+	/// Get the data of a sync write or bulk write instruction that is addressed to a specific motor.
+	///
+	/// Returns `Ok(None)` if the instruction is not a sync write or bulk write, or if it has no data for the motor.
+	/// Malformed instructions are rejected with an error, even if the data for the motor could be extracted.
+	///
+	/// For instructions with owned parameters, use [`Instructions::as_deref()`] first.
+	pub fn write_data_for(&self, motor_id: u8) -> Result<Option<BulkWriteData<&'a [u8]>>, InvalidParameterCount> {
+		if let Some(iter) = self.sync_write_iter() {
+			let mut iter = iter?;
+			let address = iter.address();
+			let data = iter.find(|write| write.motor_id == motor_id);
+			return Ok(data.map(|write| BulkWriteData {
+				motor_id,
+				address,
+				data: write.data,
+			}));
+		}
+		if let Some(iter) = self.bulk_write_iter() {
+			return Ok(iter?.find(|write| write.motor_id == motor_id));
+		}
+		Ok(None)
+	}
+}
+
+/// Iterator over the data for each motor in a sync write instruction.
+///
+/// Created with [`Instructions::sync_write_iter()`] or [`SyncWriteIter::new()`].
+#[derive(Debug, Clone)]
+pub struct SyncWriteIter<'a> {
+	address: u16,
+	blocks: core::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> SyncWriteIter<'a> {
+	/// Create a new iterator over the parameters of a sync write instruction, after the address and length fields.
+	///
+	/// Returns an error if the parameters can not be divided into blocks of `length` data bytes plus the motor ID.
+	pub fn new(address: u16, length: u16, parameters: &'a [u8]) -> Result<Self, InvalidParameterCount> {
+		let stride = usize::from(length) + 1;
+		InvalidParameterCount::check(parameters.len(), parameters.len() - parameters.len() % stride)?;
+		Ok(Self {
+			address,
+			blocks: parameters.chunks_exact(stride),
+		})
+	}
+
+	/// The address that all motors write to.
+	pub fn address(&self) -> u16 {
+		self.address
+	}
+}
+
+impl<'a> Iterator for SyncWriteIter<'a> {
+	type Item = SyncWriteData<&'a [u8]>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let block = self.blocks.next()?;
+		Some(SyncWriteData {
+			motor_id: block[0],
+			data: &block[1..],
+		})
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.blocks.size_hint()
+	}
+}
+
+impl ExactSizeIterator for SyncWriteIter<'_> {}
+
+/// Iterator over the data for each motor in a bulk write instruction.
+///
+/// Created with [`Instructions::bulk_write_iter()`] or [`BulkWriteIter::new()`].
+#[derive(Debug, Clone)]
+pub struct BulkWriteIter<'a> {
+	parameters: &'a [u8],
+}
+
+impl<'a> BulkWriteIter<'a> {
+	/// Create a new iterator over the parameters of a bulk write instruction.
+	///
+	/// All blocks are validated up front.
+	/// Returns an error if the parameters are truncated in the middle of a block.
+	pub fn new(parameters: &'a [u8]) -> Result<Self, InvalidParameterCount> {
+		let mut offset = 0;
+		while offset < parameters.len() {
+			InvalidParameterCount::check_min(parameters.len(), offset + 5)?;
+			let length = usize::from(read_u16_le(&parameters[offset + 3..]));
+			InvalidParameterCount::check_min(parameters.len(), offset + 5 + length)?;
+			offset += 5 + length;
+		}
+		Ok(Self { parameters })
+	}
+}
+
+impl<'a> Iterator for BulkWriteIter<'a> {
+	type Item = BulkWriteData<&'a [u8]>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.parameters.is_empty() {
+			return None;
+		}
+		let length = usize::from(read_u16_le(&self.parameters[3..]));
+		let (block, rest) = self.parameters.split_at(5 + length);
+		self.parameters = rest;
+		Some(BulkWriteData {
+			motor_id: block[0],
+			address: read_u16_le(&block[1..]),
+			data: &block[5..],
+		})
+	}
+}
+
 impl<'a> TryFrom<InstructionPacket<'a>> for Instruction<&'a [u8]> {
 	type Error = InvalidParameterCount;
 
@@ -342,7 +727,7 @@ impl<'a> TryFrom<InstructionPacket<'a>> for Instruction<Vec<u8>> {
 			Instructions::BulkRead { parameters } => Instructions::BulkRead {
 				parameters: parameters.to_owned(),
 			},
-			Instructions::BulkWrite { parameters } => Instructions::BulkRead {
+			Instructions::BulkWrite { parameters } => Instructions::BulkWrite {
 				parameters: parameters.to_owned(),
 			},
 			Instructions::Unknown { instruction, parameters } => Instructions::Unknown {
@@ -353,3 +738,65 @@ impl<'a> TryFrom<InstructionPacket<'a>> for Instruction<Vec<u8>> {
 		Ok(Instruction { id, instruction })
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_sync_write_iter() {
+		let instruction = Instructions::SyncWrite {
+			address: 10,
+			length: 2,
+			parameters: &[1, 0x11, 0x12, 2, 0x21, 0x22][..],
+		};
+		let_assert!(Some(Ok(iter)) = instruction.sync_write_iter());
+		assert!(iter.address() == 10);
+		let writes: Vec<_> = iter.collect();
+		assert!(writes == [SyncWriteData { motor_id: 1, data: &[0x11, 0x12][..] }, SyncWriteData { motor_id: 2, data: &[0x21, 0x22][..] }]);
+
+		let_assert!(Ok(Some(write)) = instruction.write_data_for(2));
+		assert!(write == BulkWriteData { motor_id: 2, address: 10, data: &[0x21, 0x22][..] });
+		assert!(let Ok(None) = instruction.write_data_for(3));
+
+		let owned = Instructions::SyncWrite {
+			address: 10,
+			length: 2,
+			parameters: vec![1, 0x11, 0x12, 2, 0x21, 0x22],
+		};
+		assert!(owned.as_deref() == instruction);
+		let_assert!(Ok(Some(write)) = owned.as_deref().write_data_for(1));
+		assert!(write.data == [0x11, 0x12]);
+
+		let truncated = Instructions::SyncWrite {
+			address: 10,
+			length: 2,
+			parameters: &[1, 0x11, 0x12, 2, 0x21][..],
+		};
+		assert!(let Some(Err(InvalidParameterCount { actual: 5, .. })) = truncated.sync_write_iter());
+		assert!(let Err(_) = truncated.write_data_for(1));
+	}
+
+	#[test]
+	fn test_bulk_write_iter() {
+		let instruction = Instructions::BulkWrite {
+			parameters: &[1, 10, 0, 1, 0, 0x11, 2, 20, 0, 2, 0, 0x21, 0x22][..],
+		};
+		let_assert!(Some(Ok(iter)) = instruction.bulk_write_iter());
+		let writes: Vec<_> = iter.collect();
+		assert!(writes == [
+			BulkWriteData { motor_id: 1, address: 10, data: &[0x11][..] },
+			BulkWriteData { motor_id: 2, address: 20, data: &[0x21, 0x22][..] },
+		]);
+		let_assert!(Ok(Some(write)) = instruction.write_data_for(2));
+		assert!(write.address == 20);
+
+		let truncated = Instructions::BulkWrite {
+			parameters: &[1, 10, 0, 1, 0, 0x11, 2, 20, 0, 2, 0, 0x21][..],
+		};
+		assert!(let Some(Err(InvalidParameterCount { actual: 12, .. })) = truncated.bulk_write_iter());
+		assert!(let Err(_) = truncated.write_data_for(1));
+		assert!(let Ok(None) = Instructions::<&[u8]>::Ping.write_data_for(1));
+	}
+}
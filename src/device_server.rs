@@ -0,0 +1,901 @@
+use core::time::Duration;
+
+use crate::endian::{read_u16_le, write_u16_le};
+use crate::instructions::{instruction_id, packet_id};
+use crate::timeout::default_status_timeout;
+use crate::{
+	Clear, Device, FactoryReset, Instruction, Instructions, InstructionPacket, Packet, SerialPort, StatusReturnLevel, StatusWait, TransferError,
+};
+
+/// The maximum number of data bytes of a registered write that a [`DeviceServer`] can store.
+pub const MAX_REGISTERED_WRITE: usize = 128;
+
+/// The bit in the error field of a status packet that signals a hardware error.
+const ALERT_BIT: u8 = 0x80;
+
+/// The maximum number of motors that can take part in a sync read or bulk read.
+const MAX_CHAINED_MOTORS: usize = 253;
+
+/// An error code that a device reports in the error field of a status packet.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum StatusError {
+	/// The device failed to process the instruction.
+	ResultFail = 0x01,
+
+	/// The instruction is undefined, or an action was received without a registered write.
+	Instruction = 0x02,
+
+	/// The checksum of the instruction packet does not match.
+	Crc = 0x03,
+
+	/// The data to be written is outside of the control table.
+	DataRange = 0x04,
+
+	/// The length of the data is shorter or longer than required.
+	DataLength = 0x05,
+
+	/// The data to be written is outside of the allowed range.
+	DataLimit = 0x06,
+
+	/// The register is read-only, write-only or locked.
+	Access = 0x07,
+}
+
+impl From<StatusError> for u8 {
+	fn from(error: StatusError) -> Self {
+		error as u8
+	}
+}
+
+/// The control table of a device, served by a [`DeviceServer`].
+///
+/// The control table holds the registers of the device.
+/// Addresses are byte offsets into the control table, and multi-byte registers are little-endian.
+pub trait ControlTable {
+	/// The model number of the device, reported in response to a ping.
+	fn model_number(&self) -> u16;
+
+	/// The firmware version of the device, reported in response to a ping.
+	fn firmware_version(&self) -> u8;
+
+	/// Read a range of registers into the buffer.
+	///
+	/// The length of the buffer is the number of bytes to read.
+	fn read(&mut self, address: u16, buffer: &mut [u8]) -> Result<(), StatusError>;
+
+	/// Check if a write to a range of registers is allowed.
+	///
+	/// This is called before [`Self::write()`], and when a registered write is received.
+	/// The default implementation allows all writes.
+	fn validate_write(&self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		let _ = (address, data);
+		Ok(())
+	}
+
+	/// Write data to a range of registers.
+	///
+	/// The write has already been accepted by [`Self::validate_write()`].
+	fn write(&mut self, address: u16, data: &[u8]) -> Result<(), StatusError>;
+
+	/// Reset the control table to the factory defaults.
+	///
+	/// The default implementation rejects the instruction with [`StatusError::Instruction`].
+	fn factory_reset(&mut self, kind: FactoryReset) -> Result<(), StatusError> {
+		let _ = kind;
+		Err(StatusError::Instruction)
+	}
+
+	/// Reboot the device.
+	///
+	/// The status packet is sent before this function is called.
+	/// The default implementation does nothing.
+	fn reboot(&mut self) {}
+
+	/// Clear the multi-turn position or errors of the device.
+	///
+	/// The default implementation rejects the instruction with [`StatusError::Instruction`].
+	fn clear(&mut self, kind: Clear) -> Result<(), StatusError> {
+		let _ = kind;
+		Err(StatusError::Instruction)
+	}
+
+	/// The time to wait before sending a status packet.
+	///
+	/// This corresponds to the Return Delay Time register of a real motor.
+	/// The server uses it instead of the [Return Delay Time][Device::set_return_delay_time] of the [`Device`]
+	/// when replying for this control table.
+	/// The setting of the [`Device`] itself is left unchanged.
+	/// The default implementation returns zero.
+	fn return_delay_time(&self) -> Duration {
+		Duration::ZERO
+	}
+
+	/// The instructions that are answered with a status packet.
+	///
+	/// This corresponds to the Status Return Level register of a real motor.
+	/// The server uses it instead of the [Status Return Level][Device::set_status_return_level] of the [`Device`]
+	/// when replying for this control table.
+	/// The setting of the [`Device`] itself is left unchanged.
+	/// The default implementation returns [`StatusReturnLevel::All`].
+	fn status_return_level(&self) -> StatusReturnLevel {
+		StatusReturnLevel::All
+	}
+
+	/// Check if the alert bit should be set in status packets.
+	///
+	/// A real motor sets the alert bit when a hardware error occurred.
+	/// The default implementation returns `false`.
+	fn alert(&self) -> bool {
+		false
+	}
+}
+
+impl<C: ControlTable + ?Sized> ControlTable for &mut C {
+	fn model_number(&self) -> u16 {
+		(**self).model_number()
+	}
+
+	fn firmware_version(&self) -> u8 {
+		(**self).firmware_version()
+	}
+
+	fn read(&mut self, address: u16, buffer: &mut [u8]) -> Result<(), StatusError> {
+		(**self).read(address, buffer)
+	}
+
+	fn validate_write(&self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		(**self).validate_write(address, data)
+	}
+
+	fn write(&mut self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		(**self).write(address, data)
+	}
+
+	fn factory_reset(&mut self, kind: FactoryReset) -> Result<(), StatusError> {
+		(**self).factory_reset(kind)
+	}
+
+	fn reboot(&mut self) {
+		(**self).reboot()
+	}
+
+	fn clear(&mut self, kind: Clear) -> Result<(), StatusError> {
+		(**self).clear(kind)
+	}
+
+	fn return_delay_time(&self) -> Duration {
+		(**self).return_delay_time()
+	}
+
+	fn status_return_level(&self) -> StatusReturnLevel {
+		(**self).status_return_level()
+	}
+
+	fn alert(&self) -> bool {
+		(**self).alert()
+	}
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<C: ControlTable + ?Sized> ControlTable for alloc::boxed::Box<C> {
+	fn model_number(&self) -> u16 {
+		(**self).model_number()
+	}
+
+	fn firmware_version(&self) -> u8 {
+		(**self).firmware_version()
+	}
+
+	fn read(&mut self, address: u16, buffer: &mut [u8]) -> Result<(), StatusError> {
+		(**self).read(address, buffer)
+	}
+
+	fn validate_write(&self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		(**self).validate_write(address, data)
+	}
+
+	fn write(&mut self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		(**self).write(address, data)
+	}
+
+	fn factory_reset(&mut self, kind: FactoryReset) -> Result<(), StatusError> {
+		(**self).factory_reset(kind)
+	}
+
+	fn reboot(&mut self) {
+		(**self).reboot()
+	}
+
+	fn clear(&mut self, kind: Clear) -> Result<(), StatusError> {
+		(**self).clear(kind)
+	}
+
+	fn return_delay_time(&self) -> Duration {
+		(**self).return_delay_time()
+	}
+
+	fn status_return_level(&self) -> StatusReturnLevel {
+		(**self).status_return_level()
+	}
+
+	fn alert(&self) -> bool {
+		(**self).alert()
+	}
+}
+
+/// A write that is registered with the reg write instruction, waiting for an action instruction.
+#[derive(Debug, Clone)]
+struct RegisteredWrite {
+	/// The start address of the write.
+	address: u16,
+
+	/// The number of valid bytes in `data`.
+	len: usize,
+
+	/// The data to write.
+	data: [u8; MAX_REGISTERED_WRITE],
+}
+
+/// A logical device with its own ID and control table.
+struct Endpoint<C> {
+	/// The ID of the device.
+	id: u8,
+
+	/// The control table of the device.
+	control_table: C,
+
+	/// The write registered with the reg write instruction, if any.
+	registered_write: Option<RegisteredWrite>,
+}
+
+/// The reply of a single logical device to an instruction.
+enum Reply {
+	/// Do not send a status packet.
+	None,
+
+	/// Send an empty status packet with the given error code.
+	Status(u8),
+
+	/// Send the response to a ping.
+	Ping,
+
+	/// Send the data of a range of registers.
+	Read { address: u16, length: u16 },
+
+	/// Reboot the device, after sending an empty status packet if the instruction was not broadcast.
+	Reboot,
+}
+
+/// The status packets that a server sends after handling an instruction.
+enum Plan {
+	/// Do not send any status packet.
+	None,
+
+	/// Send the reply of a single logical device.
+	Unicast { index: usize, reply: Reply },
+
+	/// Send the response to a ping for each logical device, in order of their IDs.
+	BroadcastPing,
+
+	/// Take part in a sync read or bulk read.
+	///
+	/// The motors that take part are stored in the `chain` field of the [`MultiDeviceServer`].
+	ChainedRead,
+}
+
+/// A single read of a sync read or bulk read.
+#[derive(Debug, Copy, Clone)]
+struct ChainedRead {
+	/// The ID of the motor that responds.
+	motor_id: u8,
+
+	/// The start address of the read.
+	address: u16,
+
+	/// The number of bytes to read.
+	length: u16,
+}
+
+/// The motors that respond to a sync read or bulk read,
+/// up to and including the last one that is served by this server.
+#[derive(Debug, Clone)]
+struct ChainedReads {
+	/// The number of valid entries in `reads`.
+	len: usize,
+
+	/// The reads, in the order of the instruction.
+	reads: [ChainedRead; MAX_CHAINED_MOTORS],
+}
+
+impl ChainedReads {
+	/// Create an empty list of reads.
+	const fn new() -> Self {
+		Self {
+			len: 0,
+			reads: [ChainedRead { motor_id: 0, address: 0, length: 0 }; MAX_CHAINED_MOTORS],
+		}
+	}
+
+	/// Fill the list with the reads of an instruction.
+	///
+	/// Reads after the last one for any of the given logical devices are dropped.
+	/// Returns `false` if no read is for one of the logical devices, or if there are too many reads.
+	fn fill<C>(&mut self, endpoints: &[Endpoint<C>], reads: impl Iterator<Item = ChainedRead>) -> bool {
+		self.len = 0;
+		let mut served_len = 0;
+		for read in reads {
+			let Some(entry) = self.reads.get_mut(self.len) else {
+				return false;
+			};
+			*entry = read;
+			self.len += 1;
+			if endpoints.iter().any(|endpoint| endpoint.id == read.motor_id) {
+				served_len = self.len;
+			}
+		}
+		self.len = served_len;
+		served_len > 0
+	}
+
+	/// Get the reads in the list.
+	fn as_slice(&self) -> &[ChainedRead] {
+		&self.reads[..self.len]
+	}
+}
+
+/// Server for multiple logical Dynamixel devices that share a single serial port.
+///
+/// Each logical device has its own ID and [`ControlTable`].
+/// The server reads instructions from a [`Device`] and answers them on behalf of the addressed logical device,
+/// just like a [`DeviceServer`] would for a single device.
+///
+/// A broadcast ping is answered for every logical device, in order of their IDs.
+/// For sync read and bulk read instructions, the status packets are sent in the order of the motor IDs in the instruction.
+/// The server waits for the status packets of other motors on the bus in between, like a real motor would.
+/// Before each status packet, the server waits for the [Return Delay Time][ControlTable::return_delay_time] of the responding logical device,
+/// and status packets are suppressed according to its [Status Return Level][ControlTable::status_return_level].
+///
+/// To serve logical devices with different control table types, use `&mut dyn ControlTable` or `Box<dyn ControlTable>`.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # struct Imu;
+/// # struct FootSensors;
+/// # impl ControlTable for Imu {
+/// #   fn model_number(&self) -> u16 { 1 }
+/// #   fn firmware_version(&self) -> u8 { 1 }
+/// #   fn read(&mut self, _: u16, _: &mut [u8]) -> Result<(), StatusError> { Ok(()) }
+/// #   fn write(&mut self, _: u16, _: &[u8]) -> Result<(), StatusError> { Ok(()) }
+/// # }
+/// # impl ControlTable for FootSensors {
+/// #   fn model_number(&self) -> u16 { 2 }
+/// #   fn firmware_version(&self) -> u8 { 1 }
+/// #   fn read(&mut self, _: u16, _: &mut [u8]) -> Result<(), StatusError> { Ok(()) }
+/// #   fn write(&mut self, _: u16, _: &[u8]) -> Result<(), StatusError> { Ok(()) }
+/// # }
+/// use dynamixel2::{ControlTable, Device, MultiDeviceServer, StatusError};
+/// use std::time::Duration;
+///
+/// let mut imu = Imu;
+/// let mut foot_sensors = FootSensors;
+/// let device = Device::open("/dev/ttyUSB0", 57600)?;
+/// let mut server = MultiDeviceServer::new(device, [
+///   (100, &mut imu as &mut dyn ControlTable),
+///   (101, &mut foot_sensors),
+/// ]);
+/// loop {
+///   if let Err(e) = server.serve_one(Duration::from_millis(100)) {
+///     eprintln!("{}", e);
+///   }
+/// }
+/// # }
+/// ```
+pub struct MultiDeviceServer<ReadBuffer, WriteBuffer, T: SerialPort, C, const N: usize> {
+	device: Device<ReadBuffer, WriteBuffer, T>,
+	endpoints: [Endpoint<C>; N],
+	preceding_response_timeout: Option<Duration>,
+	chain: ChainedReads,
+}
+
+impl<ReadBuffer, WriteBuffer, T, C, const N: usize> core::fmt::Debug for MultiDeviceServer<ReadBuffer, WriteBuffer, T, C, N>
+where
+	T: SerialPort + core::fmt::Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		struct Ids<'a, C>(&'a [Endpoint<C>]);
+		impl<C> core::fmt::Debug for Ids<'_, C> {
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				f.debug_list().entries(self.0.iter().map(|endpoint| endpoint.id)).finish()
+			}
+		}
+
+		f.debug_struct("MultiDeviceServer")
+			.field("device", &self.device)
+			.field("ids", &Ids(&self.endpoints))
+			.finish_non_exhaustive()
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T, C, const N: usize> MultiDeviceServer<ReadBuffer, WriteBuffer, T, C, N>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+	C: ControlTable,
+{
+	/// Create a new server for logical devices with the given IDs and control tables.
+	///
+	/// # Panics
+	/// This function panics if an ID is used more than once, or if an ID is not a valid motor ID (0 to 252).
+	pub fn new(device: Device<ReadBuffer, WriteBuffer, T>, devices: [(u8, C); N]) -> Self {
+		for (i, (id, _)) in devices.iter().enumerate() {
+			assert!(*id < packet_id::BROADCAST - 1, "invalid motor ID: {id}");
+			assert!(devices[..i].iter().all(|(other, _)| other != id), "duplicate motor ID: {id}");
+		}
+		Self {
+			device,
+			endpoints: devices.map(|(id, control_table)| Endpoint {
+				id,
+				control_table,
+				registered_write: None,
+			}),
+			preceding_response_timeout: None,
+			chain: ChainedReads::new(),
+		}
+	}
+
+	/// Get the IDs of the logical devices.
+	pub fn ids(&self) -> impl Iterator<Item = u8> + '_ {
+		self.endpoints.iter().map(|endpoint| endpoint.id)
+	}
+
+	/// Get a reference to the control table of a logical device.
+	///
+	/// Returns `None` if the server does not serve a device with the given ID.
+	pub fn control_table(&self, id: u8) -> Option<&C> {
+		let index = self.index_of(id)?;
+		Some(&self.endpoints[index].control_table)
+	}
+
+	/// Get a mutable reference to the control table of a logical device.
+	///
+	/// Returns `None` if the server does not serve a device with the given ID.
+	pub fn control_table_mut(&mut self, id: u8) -> Option<&mut C> {
+		let index = self.index_of(id)?;
+		Some(&mut self.endpoints[index].control_table)
+	}
+
+	/// Get the timeout for the status packet of each motor that responds before a logical device to a sync read or bulk read.
+	///
+	/// Returns `None` if the default timeout is used.
+	pub fn preceding_response_timeout(&self) -> Option<Duration> {
+		self.preceding_response_timeout
+	}
+
+	/// Set the timeout for the status packet of each motor that responds before a logical device to a sync read or bulk read.
+	///
+	/// See [`DeviceServer::set_preceding_response_timeout()`] for details.
+	pub fn set_preceding_response_timeout(&mut self, timeout: Option<Duration>) {
+		self.preceding_response_timeout = timeout;
+	}
+
+	/// Get a reference to the underlying [`Device`].
+	pub fn device(&self) -> &Device<ReadBuffer, WriteBuffer, T> {
+		&self.device
+	}
+
+	/// Get a mutable reference to the underlying [`Device`].
+	pub fn device_mut(&mut self) -> &mut Device<ReadBuffer, WriteBuffer, T> {
+		&mut self.device
+	}
+
+	/// Consume the server to get back the [`Device`] and the IDs and control tables of the logical devices.
+	pub fn into_parts(self) -> (Device<ReadBuffer, WriteBuffer, T>, [(u8, C); N]) {
+		(self.device, self.endpoints.map(|endpoint| (endpoint.id, endpoint.control_table)))
+	}
+
+	/// Wait for a single instruction and handle it.
+	///
+	/// Instructions for other devices are ignored.
+	/// If no instruction is received before the timeout, a timeout error is returned.
+	pub fn serve_one(&mut self, timeout: Duration) -> Result<(), TransferError<T::Error>> {
+		let packet = self.device.read_instruction_packet_timeout(timeout)?;
+		let plan = handle_packet(&mut self.endpoints, &mut self.chain, packet);
+		self.send_plan(plan)
+	}
+
+	/// Find the index of the logical device with the given ID.
+	fn index_of(&self, id: u8) -> Option<usize> {
+		self.endpoints.iter().position(|endpoint| endpoint.id == id)
+	}
+
+	/// Send the status packets for a handled instruction.
+	fn send_plan(&mut self, plan: Plan) -> Result<(), TransferError<T::Error>> {
+		match plan {
+			Plan::None => (),
+			Plan::Unicast { index, reply } => self.send_reply(index, reply)?,
+			Plan::BroadcastPing => {
+				for id in 0..packet_id::BROADCAST {
+					if let Some(index) = self.index_of(id) {
+						self.send_reply(index, Reply::Ping)?;
+					}
+				}
+			},
+			Plan::ChainedRead => {
+				let baud_rate = self.device.baud_rate();
+				for i in 0..self.chain.as_slice().len() {
+					let read = self.chain.as_slice()[i];
+					if let Some(index) = self.index_of(read.motor_id) {
+						self.send_reply(index, Reply::Read {
+							address: read.address,
+							length: read.length,
+						})?;
+						continue;
+					}
+					let timeout = self.preceding_response_timeout.unwrap_or_else(|| {
+						// The bus stops waiting for an absent motor after the same timeout, and then waits for the next status packet.
+						// If that is ours, wait for half of its read timeout on top, so we don't race the bus when the motor is absent.
+						let timeout = default_status_timeout(read.length, baud_rate);
+						match self.chain.as_slice().get(i + 1) {
+							Some(next) if self.index_of(next.motor_id).is_some() => {
+								timeout + default_status_timeout(next.length, baud_rate) / 2
+							},
+							_ => timeout,
+						}
+					});
+					match self.device.wait_for_status_packet(read.motor_id, timeout)? {
+						StatusWait::Received => (),
+						StatusWait::TimedOut => trace!("Motor {} did not respond in time, assuming it is absent.", read.motor_id),
+						StatusWait::Interrupted => {
+							debug!("Received a new instruction while waiting for motor {}, aborting.", read.motor_id);
+							break;
+						},
+					}
+				}
+			},
+		}
+		Ok(())
+	}
+
+	/// Send the reply of a single logical device.
+	fn send_reply(&mut self, index: usize, reply: Reply) -> Result<(), TransferError<T::Error>> {
+		let endpoint = &mut self.endpoints[index];
+		if let Reply::None = reply {
+			return Ok(());
+		}
+		// Use the settings of the logical device for this reply only, and restore the settings of the device afterwards.
+		let return_delay_time = self.device.return_delay_time();
+		let status_return_level = self.device.status_return_level();
+		self.device.set_return_delay_time(endpoint.control_table.return_delay_time());
+		self.device.set_status_return_level(endpoint.control_table.status_return_level());
+		let result = Self::write_reply(&mut self.device, endpoint, reply);
+		self.device.set_return_delay_time(return_delay_time);
+		self.device.set_status_return_level(status_return_level);
+		result
+	}
+
+	/// Write the status packet for the reply of a single logical device.
+	fn write_reply(
+		device: &mut Device<ReadBuffer, WriteBuffer, T>,
+		endpoint: &mut Endpoint<C>,
+		reply: Reply,
+	) -> Result<(), TransferError<T::Error>> {
+		let id = endpoint.id;
+		let control_table = &mut endpoint.control_table;
+		let alert = if control_table.alert() { ALERT_BIT } else { 0 };
+		match reply {
+			Reply::None => (),
+			Reply::Status(error) => device.write_status_error(id, error | alert)?,
+			Reply::Ping => {
+				let model = control_table.model_number();
+				let firmware = control_table.firmware_version();
+				device.write_status(id, alert, 3, |buffer| {
+					write_u16_le(&mut buffer[0..], model);
+					buffer[2] = firmware;
+				})?
+			},
+			Reply::Read { address, length } => device.write_status_with(id, alert, length.into(), |buffer| {
+				control_table.read(address, buffer).map_err(|e| u8::from(e) | alert)
+			})?,
+			Reply::Reboot => {
+				device.write_status_error(id, alert)?;
+				control_table.reboot();
+			},
+		}
+		Ok(())
+	}
+}
+
+/// Server for a Dynamixel compatible device.
+///
+/// The server reads instructions from a [`Device`] and automatically answers them using a [`ControlTable`].
+/// It handles the ping, read, write, reg write, action, factory reset, reboot, clear, sync write and bulk write instructions.
+///
+/// The server also takes part in sync read and bulk read instructions that include its ID.
+/// Like a real motor, it waits for the status packets of all motors that come before it in the instruction,
+/// or for their timeout if a motor does not respond, before sending its own status packet.
+///
+/// Status packets are sent for unicast instructions only, except for ping which is also answered when it is broadcast.
+/// Failed instructions are answered with an empty status packet containing a [`StatusError`].
+/// The Return Delay Time and Status Return Level used for the replies are taken from the [`ControlTable`],
+/// without changing the settings of the [`Device`] itself.
+///
+/// To serve multiple logical devices on the same serial port, use a [`MultiDeviceServer`].
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use dynamixel2::{ControlTable, Device, DeviceServer, ReadError, SerialPort, StatusError, TransferError};
+/// use std::time::Duration;
+///
+/// struct Registers([u8; 64]);
+///
+/// impl ControlTable for Registers {
+///   fn model_number(&self) -> u16 { 0x1234 }
+///   fn firmware_version(&self) -> u8 { 1 }
+///   fn read(&mut self, address: u16, buffer: &mut [u8]) -> Result<(), StatusError> {
+///     let data = self.0.get(address.into()..).and_then(|x| x.get(..buffer.len())).ok_or(StatusError::DataRange)?;
+///     buffer.copy_from_slice(data);
+///     Ok(())
+///   }
+///   fn write(&mut self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+///     let registers = self.0.get_mut(address.into()..).and_then(|x| x.get_mut(..data.len())).ok_or(StatusError::DataRange)?;
+///     registers.copy_from_slice(data);
+///     Ok(())
+///   }
+/// }
+///
+/// let device = Device::open("/dev/ttyUSB0", 57600)?;
+/// let mut server = DeviceServer::new(device, 1, Registers([0; 64]));
+/// loop {
+///   match server.serve_one(Duration::from_millis(100)) {
+///     Ok(()) => (),
+///     Err(TransferError::ReadError(ReadError::Io(e))) if serial2::SerialPort::is_timeout_error(&e) => (),
+///     Err(e) => eprintln!("{}", e),
+///   }
+/// }
+/// # }
+/// ```
+pub struct DeviceServer<ReadBuffer, WriteBuffer, T: SerialPort, C> {
+	inner: MultiDeviceServer<ReadBuffer, WriteBuffer, T, C, 1>,
+}
+
+impl<ReadBuffer, WriteBuffer, T, C> core::fmt::Debug for DeviceServer<ReadBuffer, WriteBuffer, T, C>
+where
+	T: SerialPort + core::fmt::Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("DeviceServer")
+			.field("device", &self.inner.device)
+			.field("id", &self.inner.endpoints[0].id)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T, C> DeviceServer<ReadBuffer, WriteBuffer, T, C>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+	C: ControlTable,
+{
+	/// Create a new server for a device with the given ID.
+	pub fn new(device: Device<ReadBuffer, WriteBuffer, T>, id: u8, control_table: C) -> Self {
+		Self {
+			inner: MultiDeviceServer::new(device, [(id, control_table)]),
+		}
+	}
+
+	/// Get the ID of the device.
+	pub fn id(&self) -> u8 {
+		self.inner.endpoints[0].id
+	}
+
+	/// Change the ID of the device.
+	///
+	/// # Panics
+	/// This function panics if the ID is not a valid motor ID (0 to 252).
+	pub fn set_id(&mut self, id: u8) {
+		assert!(id < packet_id::BROADCAST - 1, "invalid motor ID: {id}");
+		self.inner.endpoints[0].id = id;
+	}
+
+	/// Get the timeout for the status packet of each motor that responds before this device to a sync read or bulk read.
+	///
+	/// Returns `None` if the default timeout is used.
+	pub fn preceding_response_timeout(&self) -> Option<Duration> {
+		self.inner.preceding_response_timeout
+	}
+
+	/// Set the timeout for the status packet of each motor that responds before this device to a sync read or bulk read.
+	///
+	/// If a motor does not respond within the timeout, it is assumed to be absent.
+	/// The timeout should be longer than the read timeout of the bus for the absent motor,
+	/// or the bus may receive the status packet of this device while it is still waiting for the absent motor.
+	/// It should also be clearly shorter than the read timeout of the bus for the absent motor and this device together,
+	/// or the bus may give up on this device just before it responds.
+	/// If the bus stops waiting and sends a new instruction, the server stops waiting too and does not respond.
+	///
+	/// Pass `None` to use the default timeout,
+	/// which matches the read timeout of a [`Bus`][crate::Bus] with the default [`TimeoutModel`][crate::TimeoutModel].
+	/// For the motor right before this device, the default timeout is extended by half the read timeout of the bus for this device,
+	/// so that the status packet of this device arrives well within that read timeout.
+	pub fn set_preceding_response_timeout(&mut self, timeout: Option<Duration>) {
+		self.inner.preceding_response_timeout = timeout;
+	}
+
+	/// Get a reference to the underlying [`Device`].
+	pub fn device(&self) -> &Device<ReadBuffer, WriteBuffer, T> {
+		&self.inner.device
+	}
+
+	/// Get a mutable reference to the underlying [`Device`].
+	pub fn device_mut(&mut self) -> &mut Device<ReadBuffer, WriteBuffer, T> {
+		&mut self.inner.device
+	}
+
+	/// Get a reference to the control table.
+	pub fn control_table(&self) -> &C {
+		&self.inner.endpoints[0].control_table
+	}
+
+	/// Get a mutable reference to the control table.
+	pub fn control_table_mut(&mut self) -> &mut C {
+		&mut self.inner.endpoints[0].control_table
+	}
+
+	/// Consume the server to get back the [`Device`] and the control table.
+	pub fn into_parts(self) -> (Device<ReadBuffer, WriteBuffer, T>, C) {
+		let (device, [(_id, control_table)]) = self.inner.into_parts();
+		(device, control_table)
+	}
+
+	/// Wait for a single instruction and handle it.
+	///
+	/// Instructions for other devices are ignored.
+	/// If no instruction is received before the timeout, a timeout error is returned.
+	pub fn serve_one(&mut self, timeout: Duration) -> Result<(), TransferError<T::Error>> {
+		self.inner.serve_one(timeout)
+	}
+}
+
+/// Handle an instruction packet and determine which status packets to send.
+fn handle_packet<C: ControlTable>(endpoints: &mut [Endpoint<C>], chain: &mut ChainedReads, packet: InstructionPacket<'_>) -> Plan {
+	let packet_id = packet.packet_id();
+	let broadcast = packet_id == packet_id::BROADCAST;
+	let index = endpoints.iter().position(|endpoint| endpoint.id == packet_id);
+	if index.is_none() && !broadcast {
+		return Plan::None;
+	}
+	if packet.instruction_id() == instruction_id::STATUS {
+		// Status packets from other devices on the bus are not meant for us.
+		return Plan::None;
+	}
+
+	let instruction: Instruction<&[u8]> = match packet.try_into() {
+		Ok(instruction) => instruction,
+		Err(_) => {
+			return match index {
+				Some(index) => Plan::Unicast {
+					index,
+					reply: Reply::Status(StatusError::DataLength.into()),
+				},
+				None => Plan::None,
+			}
+		},
+	};
+	let instruction = instruction.instruction;
+
+	if let Some(index) = index {
+		let reply = endpoints[index].handle(&instruction, false);
+		return Plan::Unicast { index, reply };
+	}
+
+	match instruction {
+		Instructions::Ping => Plan::BroadcastPing,
+		Instructions::SyncRead { address, length, ids } => {
+			let reads = ids.iter().map(|&motor_id| ChainedRead { motor_id, address, length });
+			if chain.fill(endpoints, reads) {
+				Plan::ChainedRead
+			} else {
+				Plan::None
+			}
+		},
+		Instructions::BulkRead { parameters } => {
+			if parameters.len() % 5 != 0 {
+				return Plan::None;
+			}
+			let reads = parameters.chunks_exact(5).map(|block| ChainedRead {
+				motor_id: block[0],
+				address: read_u16_le(&block[1..]),
+				length: read_u16_le(&block[3..]),
+			});
+			if chain.fill(endpoints, reads) {
+				Plan::ChainedRead
+			} else {
+				Plan::None
+			}
+		},
+		_ => {
+			for endpoint in endpoints {
+				if let Reply::Reboot = endpoint.handle(&instruction, true) {
+					endpoint.control_table.reboot();
+				}
+			}
+			Plan::None
+		},
+	}
+}
+
+impl<C: ControlTable> Endpoint<C> {
+	/// Handle an instruction for this logical device and determine the reply.
+	///
+	/// Sync read and bulk read instructions are handled by the server, since they involve other motors too.
+	fn handle(&mut self, instruction: &Instructions<&[u8]>, broadcast: bool) -> Reply {
+		let control_table = &mut self.control_table;
+		let result = match *instruction {
+			Instructions::Ping => return Reply::Ping,
+			Instructions::Read { address, length } => {
+				if broadcast {
+					return Reply::None;
+				}
+				return Reply::Read { address, length };
+			},
+			Instructions::Write { address, parameters } => control_table
+				.validate_write(address, parameters)
+				.and_then(|()| control_table.write(address, parameters)),
+			Instructions::RegWrite { address, parameters } => control_table.validate_write(address, parameters).and_then(|()| {
+				if parameters.len() > MAX_REGISTERED_WRITE {
+					return Err(StatusError::DataLength);
+				}
+				let mut data = [0; MAX_REGISTERED_WRITE];
+				data[..parameters.len()].copy_from_slice(parameters);
+				self.registered_write = Some(RegisteredWrite {
+					address,
+					len: parameters.len(),
+					data,
+				});
+				Ok(())
+			}),
+			Instructions::Action => match self.registered_write.take() {
+				Some(write) => control_table.write(write.address, &write.data[..write.len]),
+				None => Err(StatusError::Instruction),
+			},
+			Instructions::FactoryReset(kind) => control_table.factory_reset(kind),
+			Instructions::Reboot => return Reply::Reboot,
+			Instructions::Clear(kind) => control_table.clear(kind),
+			Instructions::SyncRead { .. } | Instructions::BulkRead { .. } => return Reply::None,
+			Instructions::SyncWrite { .. } | Instructions::BulkWrite { .. } => {
+				// Sync and bulk writes are always broadcast, so they never get a status packet.
+				if let Ok(Some(write)) = instruction.write_data_for(self.id) {
+					let result = control_table
+						.validate_write(write.address, write.data)
+						.and_then(|()| control_table.write(write.address, write.data));
+					if let Err(e) = result {
+						debug!("Rejected write of {} bytes to address {}: {:?}", write.data.len(), write.address, e);
+					}
+				}
+				return Reply::None;
+			},
+			Instructions::Unknown { .. } => Err(StatusError::Instruction),
+		};
+
+		match result {
+			Ok(()) => unicast_reply(broadcast, 0),
+			Err(e) => unicast_reply(broadcast, e.into()),
+		}
+	}
+}
+
+/// Reply with a status packet, unless the instruction was broadcast.
+fn unicast_reply(broadcast: bool, error: u8) -> Reply {
+	if broadcast {
+		Reply::None
+	} else {
+		Reply::Status(error)
+	}
+}
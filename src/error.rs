@@ -2,7 +2,12 @@ use crate::instructions::packet_id::BROADCAST;
 use core::fmt::{Debug, Display, Formatter, Result as FmtResult};
 
 /// An error that can occur during a read/write transfer.
+///
+/// With the `serde` feature, the error can be serialized if the I/O error type `E` can be serialized.
+/// To serialize the errors of a bus on a real serial port, convert the [`std::io::Error`] with `error.map_io(IoError::from)` first.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum TransferError<E> {
 	/// The write of failed.
 	WriteError(WriteError<E>),
@@ -13,6 +18,8 @@ pub enum TransferError<E> {
 
 /// An error that can occur during a write transfer.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum WriteError<E> {
 	/// The write buffer is too small to contain the whole stuffed message.
 	BufferTooSmall(BufferTooSmallError),
@@ -22,6 +29,12 @@ pub enum WriteError<E> {
 
 	/// Failed to write the instruction.
 	Write(E),
+
+	/// Failed to read incoming data while waiting for the Return Delay Time before writing a status packet.
+	Read(E),
+
+	/// The motor would respond to an instruction that must not get a response.
+	WouldRespond(WouldRespondError),
 }
 
 /// The buffer is too small to hold the entire message.
@@ -29,6 +42,8 @@ pub enum WriteError<E> {
 /// Consider increasing the size of the buffer.
 /// Keep in mind that the write buffer needs to be large enough to account for byte stuffing.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BufferTooSmallError {
 	/// The required size of the buffer.
 	pub required_size: usize,
@@ -37,8 +52,27 @@ pub struct BufferTooSmallError {
 	pub total_size: usize,
 }
 
+/// A motor would respond to an instruction that must not get a response.
+///
+/// Instructions in an [`InstructionBatch`][crate::instructions::InstructionBatch] are not allowed to get a response,
+/// since the response would collide with the rest of the batch on the bus.
+/// If the Status Return Level of a motor is not known, the bus assumes that it responds to all instructions.
+/// Configure it with [`Bus::set_status_return_level()`][crate::Bus::set_status_return_level] or use the broadcast ID.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WouldRespondError {
+	/// The ID of the motor.
+	pub motor_id: u8,
+
+	/// The instruction the motor would respond to.
+	pub instruction_id: u8,
+}
+
 /// An error that can occur during a read transfer.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum ReadError<E> {
 	/// The read buffer is too small to contain the whole stuffed message.
 	BufferFull(BufferTooSmallError),
@@ -60,6 +94,8 @@ pub enum ReadError<E> {
 
 /// The received message is not valid.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum InvalidMessage {
 	/// The header does not start with the proper prefix.
 	InvalidHeaderPrefix(InvalidHeaderPrefix),
@@ -79,6 +115,7 @@ pub enum InvalidMessage {
 
 /// An error reported by the motor.
 #[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MotorError {
 	/// The raw error as returned by the motor.
 	pub raw: u8,
@@ -112,8 +149,17 @@ impl Debug for MotorError {
 	}
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for MotorError {
+	fn format(&self, f: defmt::Formatter<'_>) {
+		defmt::write!(f, "MotorError {{ error_number: {=u8}, alert: {=bool} }}", self.error_number(), self.alert())
+	}
+}
+
 /// The received message has an invalid header prefix.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InvalidHeaderPrefix {
 	/// The actual prefix.
 	pub actual: [u8; 4],
@@ -124,6 +170,8 @@ pub struct InvalidHeaderPrefix {
 
 /// The received message has an invalid checksum value.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InvalidChecksum {
 	/// The checksum from the messsage.
 	pub message: u16,
@@ -134,6 +182,8 @@ pub struct InvalidChecksum {
 
 /// The received message has an invalid or unexpected packet ID.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InvalidPacketId {
 	/// The actual packet ID.
 	pub actual: u8,
@@ -144,6 +194,8 @@ pub struct InvalidPacketId {
 
 /// The received message has an invalid or unexpected instruction value.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InvalidInstruction {
 	/// The actual instruction ID.
 	pub actual: u8,
@@ -154,6 +206,8 @@ pub struct InvalidInstruction {
 
 /// The expected number of parameters.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum ExpectedCount {
 	/// The exact number of expected parameters.
 	Exact(usize),
@@ -167,6 +221,8 @@ pub enum ExpectedCount {
 
 /// The received message has an invalid or unexpected parameter count.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InvalidParameterCount {
 	/// The actual parameter count.
 	pub actual: usize,
@@ -175,6 +231,38 @@ pub struct InvalidParameterCount {
 	pub expected: ExpectedCount,
 }
 
+/// A serializable representation of a [`std::io::Error`].
+///
+/// [`std::io::Error`] does not implement `Serialize`,
+/// so use [`TransferError::map_io()`], [`WriteError::map_io()`] or [`ReadError::map_io()`] with [`IoError::from`]
+/// to get an error that can be stored in logs.
+///
+/// Only the kind and the message of the original error are preserved.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IoError {
+	/// The kind of the error, as formatted by the `Debug` implementation of [`std::io::ErrorKind`], like `"TimedOut"`.
+	pub kind: String,
+
+	/// The message of the error, as formatted by its `Display` implementation.
+	pub message: String,
+}
+
+/// The timeout of too many different instructions was overridden.
+///
+/// Remove the override of another instruction first.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TooManyTimeoutOverrides {
+	/// The instruction that could not be given a timeout override.
+	pub instruction_id: u8,
+
+	/// The maximum number of instructions that can have a timeout override at the same time.
+	pub max_overrides: usize,
+}
+
 impl BufferTooSmallError {
 	/// Check if a buffer is large enough for the required total size.
 	pub fn check(required_size: usize, total_size: usize) -> Result<(), Self> {
@@ -186,6 +274,63 @@ impl BufferTooSmallError {
 	}
 }
 
+impl<E> TransferError<E> {
+	/// Convert the I/O error in the transfer error, if any, with the given function.
+	pub fn map_io<F>(self, f: impl FnOnce(E) -> F) -> TransferError<F> {
+		match self {
+			Self::WriteError(e) => TransferError::WriteError(e.map_io(f)),
+			Self::ReadError(e) => TransferError::ReadError(e.map_io(f)),
+		}
+	}
+}
+
+impl<E> WriteError<E> {
+	/// Convert the I/O error in the write error, if any, with the given function.
+	pub fn map_io<F>(self, f: impl FnOnce(E) -> F) -> WriteError<F> {
+		match self {
+			Self::BufferTooSmall(e) => WriteError::BufferTooSmall(e),
+			Self::DiscardBuffer(e) => WriteError::DiscardBuffer(f(e)),
+			Self::Write(e) => WriteError::Write(f(e)),
+			Self::Read(e) => WriteError::Read(f(e)),
+			Self::WouldRespond(e) => WriteError::WouldRespond(e),
+		}
+	}
+}
+
+impl<E> ReadError<E> {
+	/// Convert the I/O error in the read error, if any, with the given function.
+	pub fn map_io<F>(self, f: impl FnOnce(E) -> F) -> ReadError<F> {
+		match self {
+			Self::BufferFull(e) => ReadError::BufferFull(e),
+			Self::Io(e) => ReadError::Io(f(e)),
+			Self::InvalidMessage(e) => ReadError::InvalidMessage(e),
+			Self::MotorError(e) => ReadError::MotorError(e),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for IoError {
+	fn from(other: std::io::Error) -> Self {
+		Self {
+			kind: format!("{:?}", other.kind()),
+			message: other.to_string(),
+		}
+	}
+}
+
+impl ReadError<core::convert::Infallible> {
+	/// Convert an error that can not contain an I/O error into a [`ReadError`] for any I/O error type.
+	pub fn into_io_error<E>(self) -> ReadError<E> {
+		match self {
+			Self::BufferFull(e) => ReadError::BufferFull(e),
+			Self::Io(e) => match e {},
+			Self::InvalidMessage(e) => ReadError::InvalidMessage(e),
+			Self::MotorError(e) => ReadError::MotorError(e),
+		}
+	}
+}
+
 impl MotorError {
 	/// Check for a motor error in the response.
 	///
@@ -309,6 +454,15 @@ impl std::error::Error for InvalidInstruction {}
 #[cfg(feature = "std")]
 impl std::error::Error for InvalidParameterCount {}
 
+#[cfg(feature = "std")]
+impl std::error::Error for TooManyTimeoutOverrides {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WouldRespondError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IoError {}
+
 impl<E> From<WriteError<E>> for TransferError<E>
 {
 	fn from(other: WriteError<E>) -> Self {
@@ -359,6 +513,12 @@ impl<E> From<InvalidParameterCount> for TransferError<E> {
 	}
 }
 
+impl<E> From<WouldRespondError> for WriteError<E> {
+	fn from(other: WouldRespondError) -> Self {
+		Self::WouldRespond(other)
+	}
+}
+
 impl<E> From<BufferTooSmallError> for WriteError<E> {
 	fn from(other: BufferTooSmallError) -> Self {
 		Self::BufferTooSmall(other)
@@ -468,10 +628,29 @@ where
 			),
 			Self::DiscardBuffer(e) => write!(f, "failed to discard input buffer: {}", e),
 			Self::Write(e) => write!(f, "failed to write to serial port: {}", e),
+			Self::Read(e) => write!(f, "failed to read from serial port before writing: {}", e),
+			Self::WouldRespond(e) => write!(f, "{}", e),
 		}
 	}
 }
 
+#[cfg(feature = "std")]
+impl Display for IoError {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.write_str(&self.message)
+	}
+}
+
+impl Display for WouldRespondError {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(
+			f,
+			"motor {} would respond to instruction 0x{:02X}, set a lower status return level or use the broadcast ID",
+			self.motor_id, self.instruction_id
+		)
+	}
+}
+
 impl Display for BufferTooSmallError {
 	fn fmt(&self, f: &mut Formatter) -> FmtResult {
 		write!(
@@ -573,3 +752,13 @@ impl Display for InvalidParameterCount {
 		write!(f, "invalid parameter count, expected {}, got {}", self.expected, self.actual)
 	}
 }
+
+impl Display for TooManyTimeoutOverrides {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(
+			f,
+			"can not override the timeout of instruction 0x{:02X}: already overriding the maximum of {} instructions",
+			self.instruction_id, self.max_overrides
+		)
+	}
+}
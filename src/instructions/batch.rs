@@ -0,0 +1,318 @@
+use core::time::Duration;
+
+use super::{instruction_id, packet_id, BulkWriteData, SyncWriteData};
+use crate::bus::message_transfer_time;
+use crate::endian::write_u16_le;
+use crate::serial_port::SerialPort;
+use crate::{Bus, ReadError, Response, WouldRespondError, WriteError};
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::ToOwned, vec::Vec};
+
+/// A batch of instructions that is sent to the bus with a single write.
+///
+/// Created with [`Bus::batch()`].
+///
+/// The instructions are encoded back-to-back in the write buffer of the bus,
+/// so the write buffer must be large enough to hold all of them.
+/// Nothing is transmitted until the batch is sent with [`Self::send()`] or [`Self::sync_read_cb()`].
+/// Dropping the batch discards all encoded instructions.
+///
+/// No status packets are read for the instructions in a batch.
+/// The typed functions to add an instruction return a [`WriteError::WouldRespond`] error if the target motor would respond to it,
+/// according to the [`StatusReturnLevel`][crate::StatusReturnLevel] known by the bus.
+/// If the Status Return Level of a motor is not known, the bus assumes that it responds to all instructions,
+/// so configure it with [`Bus::set_status_return_level()`] first.
+/// Instructions sent to [`packet_id::BROADCAST`] never get a response.
+pub struct InstructionBatch<'a, ReadBuffer, WriteBuffer, T: SerialPort> {
+	bus: &'a mut Bus<ReadBuffer, WriteBuffer, T>,
+
+	/// The total length of the encoded instructions in the write buffer.
+	len: usize,
+
+	/// The number of encoded instructions.
+	instructions: usize,
+
+	/// The ID of the last encoded instruction.
+	last_instruction_id: Option<u8>,
+}
+
+impl<ReadBuffer, WriteBuffer, T> core::fmt::Debug for InstructionBatch<'_, ReadBuffer, WriteBuffer, T>
+where
+	T: SerialPort + core::fmt::Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("InstructionBatch")
+			.field("len", &self.len)
+			.field("instructions", &self.instructions)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T> Bus<ReadBuffer, WriteBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// Start a batch of instructions that will be sent to the bus with a single write.
+	///
+	/// Batching avoids the turnaround between separate writes,
+	/// which allows write-only traffic to use the full bandwidth of the bus.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+	/// use dynamixel2::Bus;
+	/// use dynamixel2::instructions::SyncWriteData;
+	///
+	/// let mut bus = Bus::open_with_buffers("/dev/ttyUSB0", 57600, vec![0; 128], vec![0; 128])?;
+	/// let mut batch = bus.batch();
+	/// // Write the goal position of motor 1 and 2.
+	/// batch.sync_write(116, 4, &[
+	///   SyncWriteData { motor_id: 1, data: 2000u32.to_le_bytes() },
+	///   SyncWriteData { motor_id: 2, data: 1600u32.to_le_bytes() },
+	/// ])?;
+	/// // And read back the present position in the same write.
+	/// batch.sync_read_cb(&[1, 2], 132, 4, |response| {
+	///   println!("{:?}", response);
+	/// })?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn batch(&mut self) -> InstructionBatch<'_, ReadBuffer, WriteBuffer, T> {
+		InstructionBatch {
+			bus: self,
+			len: 0,
+			instructions: 0,
+			last_instruction_id: None,
+		}
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T> InstructionBatch<'_, ReadBuffer, WriteBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// Get the total number of bytes of the encoded instructions.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Check if the batch is empty.
+	pub fn is_empty(&self) -> bool {
+		self.instructions == 0
+	}
+
+	/// Get the number of instructions in the batch.
+	pub fn instruction_count(&self) -> usize {
+		self.instructions
+	}
+
+	/// Get the time it takes to transmit the encoded instructions at the current baud rate.
+	pub fn transfer_time(&self) -> Duration {
+		message_transfer_time(self.len as u32, self.bus.baud_rate())
+	}
+
+	/// Add a raw instruction to the batch.
+	///
+	/// Unlike the other functions to add an instruction, this does not check if the target motor would respond.
+	/// Any response is discarded when the next instruction is written to the bus,
+	/// but it may collide with the responses to a trailing [`Self::sync_read_cb()`].
+	pub fn instruction<F>(
+		&mut self,
+		packet_id: u8,
+		instruction_id: u8,
+		parameter_count: usize,
+		encode_parameters: F,
+	) -> Result<(), WriteError<T::Error>>
+	where
+		F: FnOnce(&mut [u8]),
+	{
+		let len = self
+			.bus
+			.encode_instruction_at(self.len, packet_id, instruction_id, parameter_count, encode_parameters)?;
+		self.len += len;
+		self.instructions += 1;
+		self.last_instruction_id = Some(instruction_id);
+		Ok(())
+	}
+
+	/// Add a write of an arbitrary number of bytes to the batch.
+	///
+	/// Returns an error if the motor would respond to the instruction, without adding it to the batch.
+	pub fn write(&mut self, motor_id: u8, address: u16, data: &[u8]) -> Result<(), WriteError<T::Error>> {
+		self.check_no_response(motor_id, instruction_id::WRITE)?;
+		self.instruction(motor_id, instruction_id::WRITE, 2 + data.len(), |buffer| {
+			write_u16_le(&mut buffer[0..], address);
+			buffer[2..].copy_from_slice(data)
+		})
+	}
+
+	/// Add a registered write of an arbitrary number of bytes to the batch.
+	///
+	/// Returns an error if the motor would respond to the instruction, without adding it to the batch.
+	pub fn reg_write(&mut self, motor_id: u8, address: u16, data: &[u8]) -> Result<(), WriteError<T::Error>> {
+		self.check_no_response(motor_id, instruction_id::REG_WRITE)?;
+		self.instruction(motor_id, instruction_id::REG_WRITE, 2 + data.len(), |buffer| {
+			write_u16_le(&mut buffer[0..], address);
+			buffer[2..].copy_from_slice(data)
+		})
+	}
+
+	/// Add an action instruction to the batch.
+	///
+	/// Returns an error if the motor would respond to the instruction, without adding it to the batch.
+	pub fn action(&mut self, motor_id: u8) -> Result<(), WriteError<T::Error>> {
+		self.check_no_response(motor_id, instruction_id::ACTION)?;
+		self.instruction(motor_id, instruction_id::ACTION, 0, |_| ())
+	}
+
+	/// Add a broadcast action instruction to the batch.
+	pub fn broadcast_action(&mut self) -> Result<(), WriteError<T::Error>> {
+		self.instruction(packet_id::BROADCAST, instruction_id::ACTION, 0, |_| ())
+	}
+
+	/// Add a sync write of an arbitrary number of bytes to the batch.
+	///
+	/// See [`Bus::sync_write()`] for more details.
+	///
+	/// # Panics
+	/// The amount of data to write for each motor must be exactly `count` bytes.
+	/// This function panics if that is not the case.
+	pub fn sync_write<'a, Iter, Data, Buf>(&mut self, address: u16, count: u16, data: Iter) -> Result<(), WriteError<T::Error>>
+	where
+		Iter: IntoIterator<Item = Data>,
+		Iter::IntoIter: ExactSizeIterator,
+		Data: AsRef<SyncWriteData<Buf>>,
+		Buf: AsRef<[u8]> + 'a,
+	{
+		let data = data.into_iter();
+		let stride = 1 + usize::from(count);
+		let parameter_count = 4 + data.len() * stride;
+		self.instruction(packet_id::BROADCAST, instruction_id::SYNC_WRITE, parameter_count, |buffer| {
+			write_u16_le(&mut buffer[0..], address);
+			write_u16_le(&mut buffer[2..], count);
+			for (i, command) in data.enumerate() {
+				let command = command.as_ref();
+				assert_eq!(command.data.as_ref().len(), count as usize);
+				let buffer = &mut buffer[4 + i * stride..][..stride];
+				buffer[0] = command.motor_id;
+				buffer[1..].copy_from_slice(command.data.as_ref());
+			}
+		})
+	}
+
+	/// Add a bulk write to the batch.
+	///
+	/// See [`Bus::bulk_write()`] for more details.
+	///
+	/// # Panics
+	/// This function panics if the data length for a motor exceeds the capacity of a `u16`.
+	pub fn bulk_write<'a, I, D>(&mut self, writes: &'a I) -> Result<(), WriteError<T::Error>>
+	where
+		&'a I: IntoIterator,
+		<&'a I as IntoIterator>::IntoIter: Clone,
+		<&'a I as IntoIterator>::Item: core::borrow::Borrow<BulkWriteData<D>>,
+		D: AsRef<[u8]>,
+	{
+		use core::borrow::Borrow;
+
+		let writes = writes.into_iter();
+		let mut parameter_count = 0;
+		for write in writes.clone() {
+			let write = write.borrow();
+			let data = write.data.as_ref();
+			if data.len() > u16::MAX.into() {
+				panic!(
+					"bulk_write: data length ({}) for motor {} exceeds maximum size of {}",
+					data.len(),
+					write.motor_id,
+					u16::MAX
+				);
+			}
+			parameter_count += 5 + data.len();
+		}
+
+		self.instruction(packet_id::BROADCAST, instruction_id::BULK_WRITE, parameter_count, |buffer| {
+			let mut offset = 0;
+			for write in writes {
+				let write = write.borrow();
+				let data = write.data.as_ref();
+				let buffer = &mut buffer[offset..];
+				offset += 5 + data.len();
+				buffer[0] = write.motor_id;
+				write_u16_le(&mut buffer[1..], write.address);
+				write_u16_le(&mut buffer[3..], data.len() as u16);
+				buffer[5..][..data.len()].copy_from_slice(data);
+			}
+		})
+	}
+
+	/// Send all instructions in the batch with a single write.
+	///
+	/// Sending an empty batch does nothing.
+	pub fn send(self) -> Result<(), WriteError<T::Error>> {
+		match self.last_instruction_id {
+			None => Ok(()),
+			Some(last_instruction_id) => self.bus.send_write_buffer(self.len, last_instruction_id),
+		}
+	}
+
+	/// Send all instructions in the batch followed by a sync read, with a single write.
+	///
+	/// This schedules a complete read-after-write cycle without any turnaround between the instructions.
+	/// The read timeout for the first response includes the time to transmit the whole batch,
+	/// so long batches do not cause spurious timeouts.
+	///
+	/// The `on_response` function is called for the reply from each motor.
+	/// If the function fails to write the instructions, an error is returned and the function is not called.
+	pub fn sync_read_cb<F>(mut self, motor_ids: &[u8], address: u16, count: u16, on_response: F) -> Result<(), WriteError<T::Error>>
+	where
+		F: FnMut(Result<Response<&[u8]>, ReadError<T::Error>>),
+	{
+		self.instruction(packet_id::BROADCAST, instruction_id::SYNC_READ, 4 + motor_ids.len(), |buffer| {
+			write_u16_le(&mut buffer[0..], address);
+			write_u16_le(&mut buffer[2..], count);
+			buffer[4..].copy_from_slice(motor_ids);
+		})?;
+		let transmit_time = self.transfer_time();
+		self.bus.send_write_buffer(self.len, instruction_id::SYNC_READ)?;
+		self.bus.read_sync_read_responses(motor_ids, count, transmit_time, on_response);
+		Ok(())
+	}
+
+	/// Send all instructions in the batch followed by a sync read, with a single write.
+	///
+	/// If this function fails to get the data from any of the motors, the entire function returns an error.
+	/// See [`Self::sync_read_cb()`] for more details.
+	#[cfg(any(feature = "alloc", feature = "std"))]
+	pub fn sync_read(self, motor_ids: &[u8], address: u16, count: u16) -> Result<Vec<Response<Vec<u8>>>, crate::TransferError<T::Error>> {
+		let mut result = Vec::with_capacity(motor_ids.len());
+		let mut read_error = None;
+		self.sync_read_cb(motor_ids, address, count, |data| match data {
+			Err(e) if read_error.is_none() => read_error = Some(e),
+			Err(_) => (),
+			Ok(response) => result.push(Response {
+				motor_id: response.motor_id,
+				alert: response.alert,
+				data: response.data.to_owned(),
+			}),
+		})?;
+		match read_error {
+			Some(e) => Err(e.into()),
+			None => Ok(result),
+		}
+	}
+
+	/// Return an error if the motor would respond to the instruction.
+	fn check_no_response(&self, motor_id: u8, instruction_id: u8) -> Result<(), WouldRespondError> {
+		if self.bus.motor_settings.expects_response(motor_id, instruction_id) {
+			Err(WouldRespondError { motor_id, instruction_id })
+		} else {
+			Ok(())
+		}
+	}
+}
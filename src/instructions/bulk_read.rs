@@ -29,12 +29,40 @@ where
 	where
 		Read: AsRef<BulkReadData>,
 		F: FnMut(&BulkReadData, Result<Response<&[u8]>, ReadError<T::Error>>),
+	{
+		let mut responses = self.bulk_read_iter(reads)?;
+		while let Some((read, response)) = responses.next() {
+			on_response(read, response);
+		}
+		Ok(())
+	}
+
+	/// Synchronously read arbitrary data ranges from multiple motors in one command, and iterate over the responses as they arrive.
+	///
+	/// Unlike the sync read instruction, a bulk read can be used to read a different amount of data from a different address for each motor.
+	///
+	/// Each response is read from the serial port when [`BulkReadIter::next()`] is called,
+	/// and the data borrows the read buffer of the bus until the next call.
+	/// This allows you to process the responses without a callback or allocation, and to stop early.
+	/// Responses that have not been read yet are discarded when the next instruction is sent.
+	///
+	/// If the function fails to write the instruction, an error is returned.
+	///
+	/// # Panics
+	/// The protocol forbids specifying the same motor ID multiple times.
+	/// This function panics if the same motor ID is used for more than one read.
+	pub fn bulk_read_iter<'a, Read>(
+		&'a mut self,
+		reads: &'a [Read],
+	) -> Result<BulkReadIter<'a, Read, ReadBuffer, WriteBuffer, T>, WriteError<T::Error>>
+	where
+		Read: AsRef<BulkReadData>,
 	{
 		for i in 0..reads.len() {
 			for j in i + 1..reads.len() {
 				if reads[i].as_ref().motor_id == reads[j].as_ref().motor_id {
 					panic!(
-						"bulk_read_cb: motor ID {} used multiple at index {} and {}",
+						"bulk_read: motor ID {} used multiple at index {} and {}",
 						reads[i].as_ref().motor_id,
 						i,
 						j
@@ -46,26 +74,13 @@ where
 		self.write_instruction(packet_id::BROADCAST, instruction_id::BULK_READ, 5 * reads.len(), |buffer| {
 			for (i, read) in reads.iter().enumerate() {
 				let read = read.as_ref();
-				let buffer = &mut buffer[i..][..5];
+				let buffer = &mut buffer[i * 5..][..5];
 				write_u8_le(&mut buffer[0..], read.motor_id);
 				write_u16_le(&mut buffer[1..], read.address);
 				write_u16_le(&mut buffer[3..], read.count);
 			}
 		})?;
-		for read in reads {
-			let read = read.as_ref();
-			let response = self.read_status_response(read.count).and_then(|response| {
-				crate::InvalidPacketId::check(response.packet_id(), read.motor_id)?;
-				crate::InvalidParameterCount::check(response.parameters().len(), read.count.into())?;
-				Ok(response)
-			});
-
-			match response {
-				Ok(response) => on_response(read, Ok((&response).into())),
-				Err(e) => on_response(read, Err(e)),
-			}
-		}
-		Ok(())
+		Ok(BulkReadIter { bus: self, reads })
 	}
 
 	/// Synchronously read arbitrary data ranges from multiple motors in one command.
@@ -105,4 +120,100 @@ where
 			Ok(responses)
 		}
 	}
+
+	/// Synchronously read `LEN` bytes from a different address for each of `N` motors in one command, without allocating.
+	///
+	/// The result holds the response of each motor in the same order as `reads`,
+	/// or `None` if a motor failed to respond with valid data.
+	/// If you need access to the error for each motor, see [`Self::bulk_read_cb`].
+	///
+	/// # Panics
+	/// This function panics if the `count` of any read is not equal to `LEN`.
+	///
+	/// The protocol forbids specifying the same motor ID multiple times.
+	/// This function panics if the same motor ID is used for more than one read.
+	#[allow(clippy::type_complexity)]
+	pub fn bulk_read_array<Read, const N: usize, const LEN: usize>(
+		&mut self,
+		reads: &[Read; N],
+	) -> Result<[Option<Response<[u8; LEN]>>; N], WriteError<T::Error>>
+	where
+		Read: AsRef<BulkReadData>,
+	{
+		for (i, read) in reads.iter().enumerate() {
+			let read = read.as_ref();
+			if usize::from(read.count) != LEN {
+				panic!("bulk_read_array: read at index {} has count {}, expected {}", i, read.count, LEN)
+			}
+		}
+
+		let mut result = core::array::from_fn(|_| None);
+		let mut index = 0;
+		self.bulk_read_cb(reads, |_read, response| {
+			result[index] = response.ok().and_then(|response| {
+				Some(Response {
+					motor_id: response.motor_id,
+					alert: response.alert,
+					data: response.data.try_into().ok()?,
+				})
+			});
+			index += 1;
+		})?;
+		Ok(result)
+	}
+}
+
+/// Lending iterator over the responses to a bulk read instruction.
+///
+/// Created with [`Bus::bulk_read_iter()`].
+///
+/// This is not an [`Iterator`], because each response borrows the read buffer of the bus.
+/// Use a `while let` loop with [`Self::next()`] instead.
+pub struct BulkReadIter<'a, Read, ReadBuffer, WriteBuffer, T: SerialPort> {
+	bus: &'a mut Bus<ReadBuffer, WriteBuffer, T>,
+
+	/// The reads that have not been answered yet.
+	reads: &'a [Read],
+}
+
+impl<Read, ReadBuffer, WriteBuffer, T> core::fmt::Debug for BulkReadIter<'_, Read, ReadBuffer, WriteBuffer, T>
+where
+	Read: AsRef<BulkReadData>,
+	T: SerialPort + core::fmt::Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("BulkReadIter")
+			.field("remaining", &self.reads.len())
+			.finish_non_exhaustive()
+	}
+}
+
+impl<'a, Read, ReadBuffer, WriteBuffer, T> BulkReadIter<'a, Read, ReadBuffer, WriteBuffer, T>
+where
+	Read: AsRef<BulkReadData>,
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// The reads that have not been answered yet.
+	pub fn remaining(&self) -> &'a [Read] {
+		self.reads
+	}
+
+	/// Read the response of the next motor.
+	///
+	/// Returns the read that the response belongs to, together with the response.
+	/// Returns `None` when the responses of all motors have been read.
+	#[allow(clippy::should_implement_trait, clippy::type_complexity)]
+	pub fn next(&mut self) -> Option<(&'a BulkReadData, Result<Response<&[u8]>, ReadError<T::Error>>)> {
+		let (read, remaining) = self.reads.split_first()?;
+		self.reads = remaining;
+		let read = read.as_ref();
+		let response = self.bus.read_motor_status_response(read.motor_id, read.count).and_then(|response| {
+			crate::InvalidPacketId::check(response.packet_id(), read.motor_id)?;
+			crate::InvalidParameterCount::check(response.parameters().len(), read.count.into())?;
+			Ok(response)
+		});
+		Some((read, response.map(Response::from)))
+	}
 }
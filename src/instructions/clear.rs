@@ -23,7 +23,7 @@ where
 	/// If you want to broadcast this instruction, it may be more convenient to use [`Self::broadcast_clear_revolution_counter()`] instead.
 	pub fn clear_revolution_counter(&mut self, motor_id: u8) -> Result<Response<()>, TransferError<T::Error>> {
 		self.write_instruction(motor_id, instruction_id::CLEAR, CLEAR_REVOLUTION_COUNT.len(), encode_parameters)?;
-		Ok(super::read_response_if_not_broadcast(self, motor_id)?)
+		Ok(super::read_response_if_expected(self, motor_id, instruction_id::CLEAR)?)
 	}
 
 	/// Clear the revolution counter of all connected motors.
@@ -0,0 +1,260 @@
+use super::{instruction_id, packet_id};
+use crate::packet::Packet;
+use crate::serial_port::SerialPort;
+use crate::{Bus, ReadError, TransferError};
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+use super::Ping;
+#[cfg(any(feature = "alloc", feature = "std"))]
+use crate::Response;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// The highest motor ID that can be assigned to a motor.
+const MAX_MOTOR_ID: u8 = 252;
+
+/// A motor ID that is likely shared by more than one motor.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DuplicateId {
+	/// The motor ID that is likely shared by multiple motors.
+	pub motor_id: u8,
+
+	/// The observation that marked the motor ID as duplicated.
+	pub evidence: DuplicateIdEvidence,
+}
+
+/// The observation that marks a motor ID as likely duplicated.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DuplicateIdEvidence {
+	/// The motor ID sent more than one response to a single broadcast ping.
+	RepeatedPingResponse,
+
+	/// The motor ID sent more than one status packet in response to a unicast ping.
+	ExtraStatusPacket,
+
+	/// A unicast ping to the motor ID resulted in a corrupted status packet, such as one with an invalid checksum or length.
+	///
+	/// This typically happens when two motors transmit a status packet at the same time.
+	CorruptedStatusPacket,
+}
+
+/// The result of inspecting a single motor ID with [`Bus::inspect_duplicate_id`].
+#[cfg(any(feature = "alloc", feature = "std"))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DuplicateIdReport {
+	/// The inspected motor ID.
+	pub motor_id: u8,
+
+	/// The number of pings that were sent.
+	pub attempts: usize,
+
+	/// The number of intact status packets that were received from the motor ID.
+	///
+	/// This includes status packets with an error or with an unexpected number of parameters.
+	pub responses: usize,
+
+	/// The number of corrupted status packets, such as those with an invalid checksum or length.
+	pub collisions: usize,
+
+	/// The distinct model and firmware combinations that responded.
+	///
+	/// If this contains more than one entry, there are definitely multiple motors with the same ID.
+	/// If the motors are of the same model and have the same firmware version, they can not be told apart by this list.
+	pub devices: Vec<Ping>,
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl DuplicateIdReport {
+	/// Check if the inspection found evidence for multiple motors sharing the motor ID.
+	pub fn is_duplicate(&self) -> bool {
+		self.collisions > 0 || self.responses > self.attempts || self.devices.len() > 1
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T> Bus<ReadBuffer, WriteBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// Search the bus for motor IDs that are likely shared by multiple motors, returning the results in a [`Vec`].
+	///
+	/// See [`Self::find_duplicate_ids_cb`] for a description of the detection method.
+	#[cfg(any(feature = "alloc", feature = "std"))]
+	pub fn find_duplicate_ids(&mut self) -> Result<Vec<DuplicateId>, TransferError<T::Error>> {
+		let mut result = Vec::new();
+		self.find_duplicate_ids_cb(|x| result.push(x))?;
+		Ok(result)
+	}
+
+	/// Search the bus for motor IDs that are likely shared by multiple motors, calling an [`FnMut`] for each suspected ID.
+	///
+	/// Each motor ID is reported at most once.
+	///
+	/// The detection starts with a broadcast ping.
+	/// Motors respond to a broadcast ping in time slots ordered by their ID,
+	/// so any ID that responds more than once is reported immediately.
+	/// Motors sharing an ID transmit in the same time slot, which normally results in a corrupted status packet.
+	/// Such a collision narrows the duplicated ID down to the IDs between the valid responses before and after it.
+	///
+	/// Afterwards, every ID that responded and every ID in a collision window is pinged individually.
+	/// A unicast ping that results in a corrupted status packet or in more than one status packet is reported as duplicate.
+	///
+	/// Note that a collision before the first valid response or after the last valid response may require many unicast pings to resolve.
+	/// Each ping to an unused ID has to wait for the full read timeout.
+	pub fn find_duplicate_ids_cb<F>(&mut self, mut on_duplicate: F) -> Result<(), TransferError<T::Error>>
+	where
+		F: FnMut(DuplicateId),
+	{
+		let mut responded = [false; MAX_MOTOR_ID as usize + 1];
+		let mut candidates = [false; MAX_MOTOR_ID as usize + 1];
+		let mut reported = [false; MAX_MOTOR_ID as usize + 1];
+
+		let mut report = |motor_id: u8, evidence: DuplicateIdEvidence, reported: &mut [bool]| {
+			if !reported[usize::from(motor_id)] {
+				reported[usize::from(motor_id)] = true;
+				on_duplicate(DuplicateId { motor_id, evidence });
+			}
+		};
+
+		// The last valid response before a collision, or `None` if there is no pending collision.
+		// The outer `Option` tracks the collision, the inner `Option` the preceding response.
+		let mut collision_after: Option<Option<u8>> = None;
+
+		self.write_instruction(packet_id::BROADCAST, instruction_id::PING, 0, |_| ())?;
+		let timeout = self.scan_timeout();
+		let mut previous = None;
+		loop {
+			let motor_id = match self.read_status_response_timeout(timeout) {
+				Ok(response) => response.packet_id(),
+				Err(ReadError::Io(e)) if T::is_timeout_error(&e) => break,
+				Err(e) if is_collision(&e) => {
+					trace!("Collision in broadcast ping response after motor {:?}.", previous);
+					collision_after.get_or_insert(previous);
+					continue;
+				},
+				Err(e) => return Err(e.into()),
+			};
+			if motor_id > MAX_MOTOR_ID {
+				continue;
+			}
+
+			if responded[usize::from(motor_id)] {
+				report(motor_id, DuplicateIdEvidence::RepeatedPingResponse, &mut reported);
+			}
+			responded[usize::from(motor_id)] = true;
+
+			if let Some(start) = collision_after.take() {
+				mark_candidates(&mut candidates, start, motor_id);
+			}
+			previous = Some(motor_id);
+		}
+		if let Some(start) = collision_after {
+			mark_candidates(&mut candidates, start, MAX_MOTOR_ID + 1);
+		}
+
+		for motor_id in 0..=MAX_MOTOR_ID {
+			let index = usize::from(motor_id);
+			if reported[index] || !(responded[index] || candidates[index]) {
+				continue;
+			}
+			if let Some(evidence) = self.probe_duplicate_id(motor_id)? {
+				report(motor_id, evidence, &mut reported);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Inspect a single motor ID by pinging it repeatedly and collecting all intact responses.
+	///
+	/// Motors that share an ID rarely respond at exactly the same moment.
+	/// When they do not overlap completely, one of the status packets may arrive intact,
+	/// revealing the model number and firmware version of one of the motors.
+	/// Repeating the ping gives each motor a chance to be identified.
+	///
+	/// The returned report contains all distinct model and firmware combinations that responded,
+	/// and counters that can be used to decide if the ID is shared with [`DuplicateIdReport::is_duplicate`].
+	#[cfg(any(feature = "alloc", feature = "std"))]
+	pub fn inspect_duplicate_id(&mut self, motor_id: u8, attempts: usize) -> Result<DuplicateIdReport, TransferError<T::Error>> {
+		let mut report = DuplicateIdReport {
+			motor_id,
+			attempts,
+			responses: 0,
+			collisions: 0,
+			devices: Vec::new(),
+		};
+
+		for _ in 0..attempts {
+			self.write_instruction(motor_id, instruction_id::PING, 0, |_| ())?;
+			loop {
+				let response = match self.read_motor_status_response(motor_id, 3) {
+					Ok(response) => response,
+					Err(ReadError::Io(e)) if T::is_timeout_error(&e) => break,
+					Err(e) if is_collision(&e) => {
+						report.collisions += 1;
+						continue;
+					},
+					Err(ReadError::MotorError(_)) => {
+						report.responses += 1;
+						continue;
+					},
+					Err(e) => return Err(e.into()),
+				};
+				if response.packet_id() != motor_id {
+					continue;
+				}
+				report.responses += 1;
+				if let Ok(response) = Response::<Ping>::try_from(response) {
+					if !report.devices.contains(&response.data) {
+						report.devices.push(response.data);
+					}
+				}
+			}
+		}
+
+		Ok(report)
+	}
+
+	/// Ping a single motor ID and look for evidence of multiple motors responding.
+	fn probe_duplicate_id(&mut self, motor_id: u8) -> Result<Option<DuplicateIdEvidence>, TransferError<T::Error>> {
+		self.write_instruction(motor_id, instruction_id::PING, 0, |_| ())?;
+		let mut responses = 0;
+		loop {
+			match self.read_motor_status_response(motor_id, 3) {
+				Ok(response) => {
+					if response.packet_id() == motor_id {
+						responses += 1;
+					}
+				},
+				Err(ReadError::Io(e)) if T::is_timeout_error(&e) => break,
+				Err(e) if is_collision(&e) => return Ok(Some(DuplicateIdEvidence::CorruptedStatusPacket)),
+				Err(ReadError::MotorError(_)) => responses += 1,
+				Err(e) => return Err(e.into()),
+			}
+			if responses > 1 {
+				return Ok(Some(DuplicateIdEvidence::ExtraStatusPacket));
+			}
+		}
+		Ok(None)
+	}
+}
+
+/// Mark all motor IDs strictly between `start` and `end` as candidates for a duplicate ID.
+///
+/// If `start` is `None`, the range starts at motor ID 0.
+fn mark_candidates(candidates: &mut [bool], start: Option<u8>, end: u8) {
+	let start = start.map_or(0, |x| usize::from(x) + 1);
+	let end = usize::from(end).min(candidates.len());
+	for candidate in candidates.iter_mut().take(end).skip(start) {
+		*candidate = true;
+	}
+}
+
+/// Check if a read error is caused by a corrupted status packet, as happens when multiple motors transmit at the same time.
+///
+/// A collision can corrupt any part of a packet, including the length field.
+/// So besides an invalid checksum, this includes invalid or impossibly large packet lengths.
+fn is_collision<E>(error: &ReadError<E>) -> bool {
+	matches!(error, ReadError::InvalidMessage(_) | ReadError::BufferFull(_))
+}
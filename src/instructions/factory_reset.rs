@@ -5,6 +5,7 @@ use crate::{Bus, Response, TransferError, WriteError};
 /// The kind of factory reset to perform.
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum FactoryResetKind {
 	/// Reset all settings, including the motor ID and baud rate.
 	ResetAll = 0xFF,
@@ -41,7 +42,7 @@ where
 	/// Or use the ID Inspection Tool in the Dynamixel Wizard 2.0
 	pub fn factory_reset(&mut self, motor_id: u8, kind: FactoryResetKind) -> Result<Response<()>, TransferError<T::Error>> {
 		self.write_instruction(motor_id, instruction_id::FACTORY_RESET, 1, |buffer| buffer[0] = kind as u8)?;
-		Ok(super::read_response_if_not_broadcast(self, motor_id)?)
+		Ok(super::read_response_if_expected(self, motor_id, instruction_id::FACTORY_RESET)?)
 	}
 
 	/// Reset the settings of all connected motors to the factory defaults.
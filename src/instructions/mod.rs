@@ -26,9 +26,11 @@ pub mod packet_id {
 }
 
 mod action;
+mod batch;
 mod bulk_read;
 mod bulk_write;
 mod clear;
+mod duplicate_id;
 mod factory_reset;
 mod ping;
 mod read;
@@ -39,13 +41,20 @@ mod sync_write;
 mod write;
 
 use crate::SerialPort;
+pub use batch::InstructionBatch;
+pub use bulk_read::BulkReadIter;
+pub use duplicate_id::{DuplicateId, DuplicateIdEvidence};
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use duplicate_id::DuplicateIdReport;
 pub use factory_reset::FactoryResetKind;
 pub use ping::Ping;
+pub use sync_read::SyncReadIter;
 
 /// Data from or for a specific motor.
 ///
 /// Used by synchronous write commands.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SyncWriteData<T> {
 	/// The ID of the motor.
 	pub motor_id: u8,
@@ -67,6 +76,7 @@ impl<T> AsRef<SyncWriteData<T>> for SyncWriteData<T> {
 ///
 /// Used by bulk write commands.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BulkWriteData<T> {
 	/// The ID of the motor.
 	pub motor_id: u8,
@@ -86,6 +96,7 @@ impl<T> AsRef<BulkWriteData<T>> for BulkWriteData<T> {
 
 /// Parameters for a bulk read instruction.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BulkReadData {
 	/// The ID of the motor.
 	pub motor_id: u8,
@@ -103,25 +114,27 @@ impl AsRef<BulkReadData> for BulkReadData {
 	}
 }
 
-/// Read an empty response from the bus if the motor ID is not the broadcast ID.
+/// Read an empty response from the bus if the motor is expected to send one.
 ///
-/// If the motor ID is the broadcast ID, return a fake response from the broadcast ID.
-fn read_response_if_not_broadcast<ReadBuffer, WriteBuffer, T>(
+/// If the motor ID is the broadcast ID, or if the known Status Return Level of the motor suppresses the response,
+/// return a fake response from the motor ID without waiting.
+fn read_response_if_expected<ReadBuffer, WriteBuffer, T>(
 	bus: &mut crate::Bus<ReadBuffer, WriteBuffer, T>,
 	motor_id: u8,
+	instruction_id: u8,
 ) -> Result<crate::Response<()>, crate::error::ReadError<T::Error>>
 where
 	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
 	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
 	T: SerialPort,
 {
-	if motor_id == packet_id::BROADCAST {
+	if bus.motor_settings.expects_response(motor_id, instruction_id) {
+		Ok(bus.read_motor_status_response(motor_id, 0)?.try_into()?)
+	} else {
 		Ok(crate::Response {
-			motor_id: packet_id::BROADCAST,
+			motor_id,
 			alert: false,
 			data: (),
 		})
-	} else {
-		Ok(bus.read_status_response(0)?.try_into()?)
 	}
 }
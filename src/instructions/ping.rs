@@ -11,6 +11,8 @@ use alloc::vec::Vec;
 
 /// A response from a motor to a ping instruction.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ping {
 	/// The model of the motor.
 	///
@@ -58,6 +60,7 @@ where
 	/// Only timeouts are filtered out since they indicate a lack of response.
 	/// All other responses (including errors) are collected.
 	#[cfg(any(feature = "alloc", feature = "std"))]
+	#[allow(clippy::type_complexity)]
 	pub fn scan(&mut self) -> Result<Vec<Result<Response<Ping>, ReadError<T::Error>>>, crate::WriteError<T::Error>> {
 		let mut result = Vec::with_capacity(253);
 		match self.scan_cb(|x| result.push(Ok(x))) {
@@ -79,8 +82,7 @@ where
 		F: FnMut(Response<Ping>),
 	{
 		self.write_instruction(packet_id::BROADCAST, instruction_id::PING, 0, |_| ())?;
-		let response_time = crate::bus::message_transfer_time(14, self.baud_rate());
-		let timeout = response_time * 253 + Duration::from_millis(34);
+		let timeout = self.scan_timeout();
 
 		loop {
 			let response = self.read_status_response_timeout(timeout);
@@ -99,4 +101,17 @@ where
 			}
 		}
 	}
+
+	/// Get the read timeout for the responses to a broadcast ping.
+	///
+	/// Each motor responds in a time slot determined by its ID, so the timeout covers all 253 possible slots.
+	/// The margin on top of that is determined by the [`TimeoutModel`][crate::TimeoutModel] of the bus,
+	/// unless the timeout for the ping instruction is overridden.
+	pub(crate) fn scan_timeout(&self) -> Duration {
+		if let Some(timeout) = self.timeouts.instruction_timeout(instruction_id::PING) {
+			return timeout;
+		}
+		let response_time = crate::bus::message_transfer_time(14, self.baud_rate());
+		response_time * 253 + self.timeouts.margin(None, None)
+	}
 }
@@ -20,7 +20,7 @@ where
 	/// If you want to broadcast this instruction, it may be more convenient to use [`Self::broadcast_reboot()`] instead.
 	pub fn reboot(&mut self, motor_id: u8) -> Result<Response<()>, TransferError<T::Error>> {
 		self.write_instruction(motor_id, instruction_id::REBOOT, 0, |_| ())?;
-		Ok(super::read_response_if_not_broadcast(self, motor_id)?)
+		Ok(super::read_response_if_expected(self, motor_id, instruction_id::REBOOT)?)
 	}
 
 	/// Broadcast an reboot command to all connected motors to trigger a previously registered instruction.
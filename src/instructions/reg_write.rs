@@ -1,4 +1,4 @@
-use super::{instruction_id, read_response_if_not_broadcast};
+use super::{instruction_id, read_response_if_expected};
 use crate::{Bus, Response, TransferError};
 
 use crate::endian::{write_u16_le, write_u32_le};
@@ -24,7 +24,7 @@ where
 			write_u16_le(&mut buffer[0..], address);
 			buffer[2..].copy_from_slice(data)
 		})?;
-		Ok(read_response_if_not_broadcast(self, motor_id)?)
+		Ok(read_response_if_expected(self, motor_id, instruction_id::REG_WRITE)?)
 	}
 
 	/// Register a write command for a 8 bit value to a specific motor.
@@ -41,7 +41,7 @@ where
 			write_u16_le(&mut buffer[0..], address);
 			buffer[2] = value;
 		})?;
-		Ok(read_response_if_not_broadcast(self, motor_id)?)
+		Ok(read_response_if_expected(self, motor_id, instruction_id::REG_WRITE)?)
 	}
 
 	/// Register a write command for a 16 bit value to a specific motor.
@@ -58,7 +58,7 @@ where
 			write_u16_le(&mut buffer[0..], address);
 			write_u16_le(&mut buffer[2..], value);
 		})?;
-		Ok(read_response_if_not_broadcast(self, motor_id)?)
+		Ok(read_response_if_expected(self, motor_id, instruction_id::REG_WRITE)?)
 	}
 
 	/// Register a write command for a 32 bit value to a specific motor.
@@ -75,6 +75,6 @@ where
 			write_u16_le(&mut buffer[0..], address);
 			write_u32_le(&mut buffer[2..], value);
 		})?;
-		Ok(read_response_if_not_broadcast(self, motor_id)?)
+		Ok(read_response_if_expected(self, motor_id, instruction_id::REG_WRITE)?)
 	}
 }
@@ -1,3 +1,5 @@
+use core::time::Duration;
+
 use super::{instruction_id, packet_id};
 use crate::endian::write_u16_le;
 use crate::serial_port::SerialPort;
@@ -22,7 +24,7 @@ where
 		motor_ids: &'a [u8],
 		address: u16,
 		count: u16,
-		mut on_response: F,
+		on_response: F,
 	) -> Result<(), WriteError<T::Error>>
 	where
 		F: FnMut(Result<Response<&[u8]>, ReadError<T::Error>>),
@@ -32,19 +34,59 @@ where
 			write_u16_le(&mut buffer[2..], count);
 			buffer[4..].copy_from_slice(motor_ids);
 		})?;
-		for &motor_id in motor_ids {
-			let response = self.read_status_response(count).and_then(|response| {
-				crate::InvalidPacketId::check(response.packet_id(), motor_id)?;
-				crate::InvalidParameterCount::check(response.parameters().len(), count.into())?;
-				Ok(response)
-			});
+		self.read_sync_read_responses(motor_ids, count, Duration::ZERO, on_response);
+		Ok(())
+	}
+
+	/// Synchronously read an arbitrary number of bytes from multiple motors in one command, and iterate over the responses as they arrive.
+	///
+	/// Each response is read from the serial port when [`SyncReadIter::next()`] is called,
+	/// and the data borrows the read buffer of the bus until the next call.
+	/// This allows you to process the responses without a callback or allocation, and to stop early.
+	/// Responses that have not been read yet are discarded when the next instruction is sent.
+	///
+	/// If the function fails to write the instruction, an error is returned.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+	/// # let mut bus = dynamixel2::Bus::open("/dev/ttyUSB0", 57600)?;
+	/// // Read the present position of motor 1, 2 and 3.
+	/// let mut responses = bus.sync_read_iter(&[1, 2, 3], 132, 4)?;
+	/// while let Some(response) = responses.next() {
+	///     let response = response?;
+	///     let position = i32::from_le_bytes(response.data.try_into()?);
+	///     println!("Position of motor {}: {}", response.motor_id, position);
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn sync_read_iter<'a>(
+		&'a mut self,
+		motor_ids: &'a [u8],
+		address: u16,
+		count: u16,
+	) -> Result<SyncReadIter<'a, ReadBuffer, WriteBuffer, T>, WriteError<T::Error>> {
+		self.write_instruction(packet_id::BROADCAST, instruction_id::SYNC_READ, 4 + motor_ids.len(), |buffer| {
+			write_u16_le(&mut buffer[0..], address);
+			write_u16_le(&mut buffer[2..], count);
+			buffer[4..].copy_from_slice(motor_ids);
+		})?;
+		Ok(SyncReadIter::new(self, motor_ids, count, Duration::ZERO))
+	}
 
-			match response {
-				Ok(response) => on_response(Ok((&response).into())),
-				Err(e) => on_response(Err(e)),
-			}
+	/// Read the responses to a sync read instruction that has already been sent.
+	///
+	/// The `transmit_time` is added to the read timeout of the first response,
+	/// to account for outgoing data that may still be in transit.
+	pub(crate) fn read_sync_read_responses<F>(&mut self, motor_ids: &[u8], count: u16, transmit_time: Duration, mut on_response: F)
+	where
+		F: FnMut(Result<Response<&[u8]>, ReadError<T::Error>>),
+	{
+		let mut responses = SyncReadIter::new(self, motor_ids, count, transmit_time);
+		while let Some(response) = responses.next() {
+			on_response(response);
 		}
-		Ok(())
 	}
 
 	/// Synchronously read an 8 bit value from multiple motors in one command.
@@ -62,7 +104,7 @@ where
 			buffer[4..].copy_from_slice(motor_ids);
 		})?;
 		for &motor_id in motor_ids {
-			let data = self.read_status_response(count).and_then(|response| {
+			let data = self.read_motor_status_response(motor_id, count).and_then(|response| {
 				crate::InvalidPacketId::check(response.packet_id(), motor_id)?;
 				Ok(response.try_into()?)
 			});
@@ -86,7 +128,7 @@ where
 			buffer[4..].copy_from_slice(motor_ids);
 		})?;
 		for &motor_id in motor_ids {
-			let data = self.read_status_response(count).and_then(|response| {
+			let data = self.read_motor_status_response(motor_id, count).and_then(|response| {
 				crate::InvalidPacketId::check(response.packet_id(), motor_id)?;
 				Ok(response.try_into()?)
 			});
@@ -110,7 +152,7 @@ where
 			buffer[4..].copy_from_slice(motor_ids);
 		})?;
 		for &motor_id in motor_ids {
-			let data = self.read_status_response(count).and_then(|response| {
+			let data = self.read_motor_status_response(motor_id, count).and_then(|response| {
 				crate::InvalidPacketId::check(response.packet_id(), motor_id)?;
 				crate::InvalidParameterCount::check(response.parameters().len(), count.into())?;
 				Ok(response.try_into()?)
@@ -120,6 +162,46 @@ where
 		Ok(())
 	}
 
+	/// Synchronously read `LEN` bytes from `N` motors in one command, without allocating.
+	///
+	/// The result holds the response of each motor in the same order as `motor_ids`,
+	/// or `None` if a motor failed to respond with valid data.
+	/// If you need access to the error for each motor, see [`Self::sync_read_cb`].
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+	/// # let mut bus = dynamixel2::Bus::open("/dev/ttyUSB0", 57600)?;
+	/// // Read the present position of motor 1, 2 and 3.
+	/// let [a, b, c] = bus.sync_read_array::<3, 4>(&[1, 2, 3], 132)?;
+	/// if let Some(response) = a {
+	///     println!("Position of motor 1: {}", i32::from_le_bytes(response.data));
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	#[allow(clippy::type_complexity)]
+	pub fn sync_read_array<const N: usize, const LEN: usize>(
+		&mut self,
+		motor_ids: &[u8; N],
+		address: u16,
+	) -> Result<[Option<Response<[u8; LEN]>>; N], WriteError<T::Error>> {
+		const { assert!(LEN <= u16::MAX as usize, "sync_read_array: LEN must fit in a u16") };
+		let mut result = core::array::from_fn(|_| None);
+		let mut index = 0;
+		self.sync_read_cb(motor_ids, address, LEN as u16, |response| {
+			result[index] = response.ok().and_then(|response| {
+				Some(Response {
+					motor_id: response.motor_id,
+					alert: response.alert,
+					data: response.data.try_into().ok()?,
+				})
+			});
+			index += 1;
+		})?;
+		Ok(result)
+	}
+
 	/// Synchronously read an arbitrary number of bytes from multiple motors in one command.
 	///
 	/// If this function fails to get the data from any of the motors, the entire function retrns an error.
@@ -193,3 +275,75 @@ where
 		Ok(result)
 	}
 }
+
+/// Lending iterator over the responses to a sync read instruction.
+///
+/// Created with [`Bus::sync_read_iter()`].
+///
+/// This is not an [`Iterator`], because each response borrows the read buffer of the bus.
+/// Use a `while let` loop with [`Self::next()`] instead.
+pub struct SyncReadIter<'a, ReadBuffer, WriteBuffer, T: SerialPort> {
+	bus: &'a mut Bus<ReadBuffer, WriteBuffer, T>,
+
+	/// The motors that have not responded yet.
+	motor_ids: &'a [u8],
+
+	/// The number of bytes read from each motor.
+	count: u16,
+
+	/// Extra time for the first response, for outgoing data that may still be in transit.
+	transmit_time: Duration,
+}
+
+impl<ReadBuffer, WriteBuffer, T> core::fmt::Debug for SyncReadIter<'_, ReadBuffer, WriteBuffer, T>
+where
+	T: SerialPort + core::fmt::Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("SyncReadIter")
+			.field("motor_ids", &self.motor_ids)
+			.field("count", &self.count)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<'a, ReadBuffer, WriteBuffer, T> SyncReadIter<'a, ReadBuffer, WriteBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	pub(crate) fn new(bus: &'a mut Bus<ReadBuffer, WriteBuffer, T>, motor_ids: &'a [u8], count: u16, transmit_time: Duration) -> Self {
+		Self {
+			bus,
+			motor_ids,
+			count,
+			transmit_time,
+		}
+	}
+
+	/// The IDs of the motors that have not been read yet.
+	pub fn remaining(&self) -> &'a [u8] {
+		self.motor_ids
+	}
+
+	/// Read the response of the next motor.
+	///
+	/// Returns `None` when the responses of all motors have been read.
+	#[allow(clippy::should_implement_trait, clippy::type_complexity)]
+	pub fn next(&mut self) -> Option<Result<Response<&[u8]>, ReadError<T::Error>>> {
+		let (&motor_id, remaining) = self.motor_ids.split_first()?;
+		self.motor_ids = remaining;
+		let count = self.count;
+		let transmit_time = core::mem::take(&mut self.transmit_time);
+		let response = self
+			.bus
+			.read_motor_status_response_after(motor_id, count, transmit_time)
+			.and_then(|response| {
+				crate::InvalidPacketId::check(response.packet_id(), motor_id)?;
+				crate::InvalidParameterCount::check(response.parameters().len(), count.into())?;
+				Ok(response)
+			});
+		Some(response.map(Response::from))
+	}
+}
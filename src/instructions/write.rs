@@ -1,4 +1,4 @@
-use super::{instruction_id, read_response_if_not_broadcast};
+use super::{instruction_id, read_response_if_expected};
 use crate::endian::{write_u16_le, write_u32_le};
 use crate::serial_port::SerialPort;
 use crate::{Bus, Response, TransferError};
@@ -18,7 +18,7 @@ where
 			write_u16_le(&mut buffer[0..], address);
 			buffer[2..].copy_from_slice(data)
 		})?;
-		Ok(read_response_if_not_broadcast(self, motor_id)?)
+		Ok(read_response_if_expected(self, motor_id, instruction_id::WRITE)?)
 	}
 
 	/// Write an 8 bit value to a specific motor.
@@ -30,7 +30,7 @@ where
 			write_u16_le(&mut buffer[0..], address);
 			buffer[2] = value;
 		})?;
-		Ok(read_response_if_not_broadcast(self, motor_id)?)
+		Ok(read_response_if_expected(self, motor_id, instruction_id::WRITE)?)
 	}
 
 	/// Write an 16 bit value to a specific motor.
@@ -42,7 +42,7 @@ where
 			write_u16_le(&mut buffer[0..], address);
 			write_u16_le(&mut buffer[2..], value);
 		})?;
-		Ok(read_response_if_not_broadcast(self, motor_id)?)
+		Ok(read_response_if_expected(self, motor_id, instruction_id::WRITE)?)
 	}
 
 	/// Write an 32 bit value to a specific motor.
@@ -54,6 +54,6 @@ where
 			write_u16_le(&mut buffer[0..], address);
 			write_u32_le(&mut buffer[2..], value);
 		})?;
-		Ok(read_response_if_not_broadcast(self, motor_id)?)
+		Ok(read_response_if_expected(self, motor_id, instruction_id::WRITE)?)
 	}
 }
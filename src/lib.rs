@@ -18,6 +18,22 @@
 //! # Optional features
 //!
 //! You can enable the `log` feature to have the library use `log::trace!()` to log all sent instructions and received replies.
+//!
+//! On embedded targets, you can enable the `defmt` feature instead to log with [`defmt`](https://docs.rs/defmt).
+//! This also implements `defmt::Format` for the error types, [`Response`], [`instructions::Ping`] and [`Instructions`].
+//! If both the `log` and `defmt` features are enabled, messages are logged with the `log` crate.
+//!
+//! You can enable the `serde` feature to implement `Serialize` and `Deserialize` for [`Response`], [`instructions::Ping`],
+//! [`instructions::SyncWriteData`], [`instructions::BulkWriteData`], [`instructions::BulkReadData`], [`Instruction`], [`Instructions`],
+//! [`instructions::FactoryResetKind`] and the error types.
+//! The representation is stable:
+//! * Structs are represented as maps with the field names as keys, for example `{"motor_id": 1, "alert": false, "data": 1234}`.
+//! * Enums are externally tagged with the variant name in snake case, for example `"ping"`, `{"read": {"address": 132, "length": 4}}` or `{"factory_reset": "except_id"}`.
+//! * Parameters and data use the representation of their own type, so a `Vec<u8>` is a sequence of numbers.
+//!
+//! You can enable the `tracing` feature to have the [`Bus`] create a `tracing` span for each transaction.
+//! The span records the instruction, motor ID, register range, bytes on the wire, latency and the kind of any error,
+//! and contains an event for each received status packet and each error.
 
 #![warn(missing_docs)]
 #![warn(missing_debug_implementations)]
@@ -33,8 +49,12 @@ pub use serial2;
 #[macro_use]
 mod log;
 
+#[cfg(feature = "std")]
+pub mod capture;
 pub mod checksum;
 pub mod instructions;
+pub mod simulation;
+pub mod timing;
 
 mod bus;
 pub use bus::*;
@@ -42,8 +62,46 @@ pub use bus::*;
 mod device;
 pub use device::*;
 
+#[cfg(any(feature = "alloc", feature = "std"))]
+mod bridge;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use bridge::{Bridge, BridgeError, BridgeHooks, BridgedInstruction, BridgedStatus, Verdict};
+
+mod device_server;
+pub use device_server::{ControlTable, DeviceServer, MultiDeviceServer, StatusError, MAX_REGISTERED_WRITE};
+
+mod motor_settings;
+pub use motor_settings::StatusReturnLevel;
+
+mod parser;
+pub use parser::{PacketParser, ParsedPacket};
+
+mod pretty;
+pub use pretty::{PrettyPacket, RegisterNames};
+
+#[cfg(feature = "std")]
+mod sniffer;
+#[cfg(feature = "std")]
+pub use sniffer::{SniffedContent, SniffedPacket, SniffedStatus, Sniffer};
+
 mod serial_port;
 pub use serial_port::SerialPort;
+#[cfg(feature = "std")]
+pub use serial_port::virtual_bus::{VirtualBus, VirtualSerialPort};
+#[cfg(feature = "std")]
+pub use serial_port::scripted::ScriptedSerialPort;
+#[cfg(feature = "std")]
+pub use serial_port::replay::ReplaySerialPort;
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub use serial_port::fault_injection;
+
+mod span;
+
+mod timeout;
+pub use timeout::TimeoutModel;
+
+mod transaction;
+pub use transaction::{encode_instruction, Transaction, TransactionPoll};
 
 mod error;
 pub use error::*;
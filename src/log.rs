@@ -1,3 +1,47 @@
+//! Logging macros that forward to the `log` or `defmt` crate, or do nothing.
+//!
+//! If both the `log` and `defmt` features are enabled, the `log` crate is used.
+//! Format strings must be valid for both `core::fmt` and `defmt`.
+//! Use [`HexBytes`] to log a byte slice as hexadecimal, and [`DisplayFmt`] for types that only implement `Display`.
+
+/// Wrapper to log a byte slice as a list of hexadecimal bytes.
+///
+/// This formats like `{:02X?}` with `core::fmt` and `{=[u8]:02X}` with `defmt`.
+pub(crate) struct HexBytes<'a>(pub &'a [u8]);
+
+impl core::fmt::Debug for HexBytes<'_> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{:02X?}", self.0)
+	}
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for HexBytes<'_> {
+	fn format(&self, f: defmt::Formatter<'_>) {
+		defmt::write!(f, "{=[u8]:02X}", self.0)
+	}
+}
+
+/// Wrapper to log a value with its [`Display`][core::fmt::Display] implementation, for types that do not implement `defmt::Format`.
+///
+/// With `defmt`, the value is formatted on the device, so prefer implementing `defmt::Format` where possible.
+#[cfg(feature = "std")]
+pub(crate) struct DisplayFmt<T>(pub T);
+
+#[cfg(feature = "std")]
+impl<T: core::fmt::Display> core::fmt::Display for DisplayFmt<T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+#[cfg(all(feature = "defmt", feature = "std"))]
+impl<T: core::fmt::Display> defmt::Format for DisplayFmt<T> {
+	fn format(&self, f: defmt::Formatter<'_>) {
+		defmt::write!(f, "{}", defmt::Display2Format(&self.0))
+	}
+}
+
 #[cfg(feature = "log")]
 #[allow(unused)]
 #[macro_use]
@@ -33,12 +77,47 @@ mod details {
 	}
 }
 
-#[cfg(not(feature = "log"))]
+#[cfg(all(feature = "defmt", not(feature = "log")))]
+#[allow(unused)]
+#[macro_use]
+mod details {
+	macro_rules! trace {
+		($($args:tt)*) => {{
+			::defmt::trace!($($args)*);
+		}}
+	}
+
+	macro_rules! debug {
+		($($args:tt)*) => {{
+			::defmt::debug!($($args)*);
+		}}
+	}
+
+	macro_rules! info {
+		($($args:tt)*) => {{
+			::defmt::info!($($args)*);
+		}}
+	}
+
+	macro_rules! warn {
+		($($args:tt)*) => {{
+			::defmt::warn!($($args)*);
+		}}
+	}
+
+	macro_rules! error {
+		($($args:tt)*) => {{
+			::defmt::error!($($args)*);
+		}}
+	}
+}
+
+#[cfg(not(any(feature = "log", feature = "defmt")))]
 #[allow(unused)]
 #[macro_use]
 mod details {
 	// These macros all pass the arguments to `format_args!()`
-	// to trigger compilation failures even with the "log" feature disabled.
+	// to trigger compilation failures even with the "log" and "defmt" features disabled.
 
 	macro_rules! trace {
 		($($args:tt)*) => {{
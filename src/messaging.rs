@@ -1,27 +1,36 @@
-use crate::checksum::calculate_checksum;
-use crate::endian::{read_u16_le, write_u16_le};
 use crate::packet::{Packet, HEADER_PREFIX, INSTRUCTION_HEADER_SIZE, STATUS_HEADER_SIZE};
-use crate::{bytestuff, ReadError, SerialPort, WriteError};
+use crate::log::HexBytes;
+use crate::parser::PacketParser;
+use crate::instructions::instruction_id;
+use crate::{ReadError, SerialPort, StatusPacket, WriteError};
 use core::time::Duration;
 
-pub struct Messenger<ReadBuffer, WriteBuffer, T> {
+/// The timeout of the reference deadline used to measure elapsed time since the last write.
+///
+/// Elapsed times longer than this saturate.
+const CLOCK_REFERENCE: Duration = Duration::from_secs(3600);
+
+pub struct Messenger<ReadBuffer, WriteBuffer, T: SerialPort> {
 	/// The underlying stream (normally a serial port).
 	pub(crate) serial_port: T,
 
 	/// The baud rate of the serial port, if known.
 	pub(crate) baud_rate: u32,
 
-	/// The buffer for reading incoming messages.
-	pub(crate) read_buffer: ReadBuffer,
-
-	/// The total number of valid bytes in the read buffer.
-	pub(crate) read_len: usize,
-
-	/// The number of leading bytes in the read buffer that have already been used.
-	pub(crate) used_bytes: usize,
+	/// The parser for incoming messages.
+	pub(crate) parser: PacketParser<ReadBuffer>,
 
 	/// The buffer for outgoing messages.
 	pub(crate) write_buffer: WriteBuffer,
+
+	/// The time it took to receive the last packet, if the serial port can measure it.
+	pub(crate) response_time: Option<Duration>,
+
+	/// A reference deadline made at the start of the last write, to measure elapsed time.
+	write_start: Option<T::Instant>,
+
+	/// The time between the start of the last write and the first byte received after it, if the serial port can measure it.
+	pub(crate) first_byte_time: Option<Duration>,
 }
 
 impl<ReadBuffer, WriteBuffer, T> Messenger<ReadBuffer, WriteBuffer, T>
@@ -59,10 +68,11 @@ where
 		Self {
 			serial_port: serial_port.into(),
 			baud_rate,
-			read_buffer,
-			read_len: 0,
-			used_bytes: 0,
+			parser: PacketParser::new(read_buffer),
 			write_buffer,
+			response_time: None,
+			write_start: None,
+			first_byte_time: None,
 		}
 	}
 
@@ -102,170 +112,126 @@ where
 	where
 		F: FnOnce(&mut [u8]),
 	{
-		let buffer = self.write_buffer.as_mut();
-
-		// Check if the buffer can hold the unstuffed message.
-		crate::error::BufferTooSmallError::check(INSTRUCTION_HEADER_SIZE + parameter_count + 2, buffer.len())?;
-
-		// Add the header, with a placeholder for the length field.
-		buffer[4] = packet_id;
-		buffer[5] = 0;
-		buffer[6] = 0;
-		buffer[7] = instruction_id;
-		// The error byte for StatusPackets gets added in
-		encode_parameters(&mut buffer[INSTRUCTION_HEADER_SIZE..][..parameter_count]);
-
-		// Perform bitstuffing on the body.
-		// The header never needs stuffing.
-		let stuffed_body_len = bytestuff::stuff_inplace(&mut buffer[INSTRUCTION_HEADER_SIZE..], parameter_count)?;
-
-		write_u16_le(&mut buffer[5..], stuffed_body_len as u16 + 3);
+		let message_len = self.encode_instruction(0, packet_id, instruction_id, parameter_count, encode_parameters)?;
+		self.send_write_buffer(message_len)
+	}
 
-		// Add checksum.
-		let checksum_index = INSTRUCTION_HEADER_SIZE + stuffed_body_len;
-		let checksum = calculate_checksum(0, &buffer[..checksum_index]);
-		write_u16_le(&mut buffer[checksum_index..], checksum);
+	/// Encode an instruction message into the write buffer at the given offset, without sending it.
+	///
+	/// Returns the total length of the encoded message, including byte-stuffing and checksum.
+	pub fn encode_instruction<F>(
+		&mut self,
+		offset: usize,
+		packet_id: u8,
+		instruction_id: u8,
+		parameter_count: usize,
+		encode_parameters: F,
+	) -> Result<usize, crate::error::BufferTooSmallError>
+	where
+		F: FnOnce(&mut [u8]),
+	{
+		let total_size = self.write_buffer.as_ref().len();
+		let buffer = self.write_buffer.as_mut().get_mut(offset..).unwrap_or_default();
+		crate::transaction::encode_instruction(buffer, packet_id, instruction_id, parameter_count, encode_parameters).map_err(|e| {
+			crate::error::BufferTooSmallError {
+				required_size: offset + e.required_size,
+				total_size,
+			}
+		})
+	}
 
+	/// Discard all pending input and send the first `len` bytes of the write buffer.
+	pub fn send_write_buffer(&mut self, len: usize) -> Result<(), WriteError<T::Error>> {
 		// Throw away old data in the read buffer and the kernel read buffer.
 		// We don't do this when reading a reply, because we might receive multiple replies for one instruction,
 		// and read() can potentially read more than one reply per syscall.
-		self.read_len = 0;
-		self.used_bytes = 0;
+		self.parser.clear();
 		self.serial_port.discard_input_buffer().map_err(WriteError::DiscardBuffer)?;
 
 		// Send message.
-		let stuffed_message = &buffer[..checksum_index + 2];
-		trace!("sending instruction: {:02X?}", stuffed_message);
+		self.write_start = Some(self.serial_port.make_deadline(CLOCK_REFERENCE));
+		self.first_byte_time = None;
+		let stuffed_message = &self.write_buffer.as_ref()[..len];
+		trace!("sending instruction: {:?}", HexBytes(stuffed_message));
 		self.serial_port.write_all(stuffed_message).map_err(WriteError::Write)?;
 		Ok(())
 	}
 
-	/// Read a raw status response from the bus with the given deadline.
-	pub fn read_packet_response_timeout<'a, P: Packet<'a>>(&'a mut self, timeout: Duration) -> Result<P, ReadError<T::Error>> {
-		// Check that the read buffer is large enough to hold atleast a status packet header.
-		crate::error::BufferTooSmallError::check(P::HEADER_SIZE, self.read_buffer.as_mut().len())?;
+	/// Wait for the given duration, buffering any data that is received in the mean time.
+	pub fn delay(&mut self, duration: Duration) -> Result<(), T::Error> {
+		if duration.is_zero() {
+			return Ok(());
+		}
+		let deadline = self.serial_port.make_deadline(duration);
+		loop {
+			if self.parser.spare_capacity_mut().is_empty() {
+				// The buffered data is discarded before the next write anyway, so make room for more.
+				self.parser.clear();
+			}
+			match self.serial_port.read(self.parser.spare_capacity_mut(), &deadline) {
+				Ok(new_data) => self.parser.advance(new_data),
+				Err(e) if T::is_timeout_error(&e) => return Ok(()),
+				Err(e) => return Err(e),
+			}
+			if self.serial_port.remaining_time(&deadline) == Some(Duration::ZERO) {
+				return Ok(());
+			}
+		}
+	}
 
+	/// Receive a packet into the read buffer without wrapping it.
+	///
+	/// Returns the length of the packet (with byte-stuffing already undone), which can be passed to [`Self::packet()`].
+	/// This also records the time it took to receive the packet in [`Self::response_time`], if the serial port supports it.
+	pub fn receive_packet<'a, P: Packet<'a>>(&mut self, timeout: Duration) -> Result<usize, ReadError<T::Error>> {
 		let deadline = self.serial_port.make_deadline(timeout);
+		self.response_time = None;
 
-		let stuffed_message_len = loop {
-			self.remove_garbage();
-
-			// The call to remove_garbage() removes all leading bytes that don't match a status header.
-			// So if there's enough bytes left, it's a status header.
-			if self.read_len > P::HEADER_SIZE {
-				let read_buffer = &self.read_buffer.as_mut()[..self.read_len];
-				let body_len = read_buffer[5] as usize + read_buffer[6] as usize * 256;
-				let body_len = body_len - P::HEADER_OVERLAP; // Length includes some bytes which are already included in P::PACKET_HEADER_SIZE.
-
-				// Check if the read buffer is large enough for the entire message.
-				// We don't have to remove the read bytes, because `write_instruction()` already clears the read buffer.
-				crate::error::BufferTooSmallError::check(P::HEADER_SIZE + body_len, self.read_buffer.as_mut().len())?;
-
-				if self.read_len >= P::HEADER_SIZE + body_len {
-					trace!("P::HEADER_SIZE: {}, body_len: {}", P::HEADER_SIZE, body_len);
-					break P::HEADER_SIZE + body_len;
-				}
+		let packet_len = loop {
+			if let Some(packet_len) = self.parser.poll_packet_len::<P>().map_err(ReadError::into_io_error)? {
+				break packet_len;
 			}
 
 			// Try to read more data into the buffer.
-			let new_data = self.serial_port.read(&mut self.read_buffer.as_mut()[self.read_len..], &deadline)
+			let new_data = self.serial_port.read(self.parser.spare_capacity_mut(), &deadline)
 				.map_err(ReadError::Io)?;
-			if new_data == 0 {
-				continue;
+			if new_data > 0 && self.first_byte_time.is_none() {
+				self.first_byte_time = self.elapsed();
 			}
-
-			self.read_len += new_data;
+			self.parser.advance(new_data);
 		};
 
-		let buffer = self.read_buffer.as_mut();
-		let parameters_end = stuffed_message_len - 2;
-		trace!("read packet: {:02X?}", &buffer[..parameters_end]);
-
-		let checksum_message = read_u16_le(&buffer[parameters_end..]);
-		let checksum_computed = calculate_checksum(0, &buffer[..parameters_end]);
-		if checksum_message != checksum_computed {
-			self.consume_read_bytes(stuffed_message_len);
-			return Err(crate::InvalidChecksum {
-				message: checksum_message,
-				computed: checksum_computed,
-			}
-			.into());
-		}
-
-		// Mark the whole message as "used_bytes", so that the next call to `remove_garbage()` removes it.
-		self.used_bytes += stuffed_message_len;
-
-		// Remove byte-stuffing from the parameters.
-		let parameter_count = bytestuff::unstuff_inplace(&mut buffer[P::HEADER_SIZE..parameters_end]);
-
-		// Wrap the data in a `StatusPacket`.
-		let response = P::new(&self.read_buffer.as_ref()[..P::HEADER_SIZE + parameter_count]);
-		// TODO MOVE THESE BACK TO BUS
-		// crate::InvalidInstruction::check(response.instruction_id(), crate::instructions::instruction_id::STATUS)?;
-		// crate::MotorError::check(response.error())?;
-		Ok(response)
+		self.response_time = self.serial_port.remaining_time(&deadline)
+			.map(|remaining| timeout.saturating_sub(remaining));
+		Ok(packet_len)
 	}
-}
 
-impl<ReadBuffer, WriteBuffer, T> Messenger<ReadBuffer, WriteBuffer, T>
-where
-	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
-	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
-	T: SerialPort,
-{
-	/// Remove leading garbage data from the read buffer.
-	fn remove_garbage(&mut self) {
-		let read_buffer = self.read_buffer.as_mut();
-		let garbage_len = find_header(&read_buffer[..self.read_len][self.used_bytes..]);
-		if garbage_len > 0 {
-			debug!("skipping {} bytes of leading garbage.", garbage_len);
-			trace!("skipped garbage: {:02X?}", &read_buffer[..garbage_len]);
+	/// Receive a status packet into the read buffer before the deadline, unless an instruction packet is received first.
+	///
+	/// Returns the length of the status packet, which can be passed to [`Self::packet()`],
+	/// or `None` if an instruction packet was received.
+	/// The instruction packet is left in the read buffer, so that it is returned by the next call to [`Self::receive_packet()`].
+	pub fn receive_status_packet_before(&mut self, deadline: &T::Instant) -> Result<Option<usize>, ReadError<T::Error>> {
+		loop {
+			if self.parser.peek_instruction_id().is_some_and(|id| id != instruction_id::STATUS) {
+				return Ok(None);
+			}
+			if let Some(packet_len) = self.parser.poll_packet_len::<StatusPacket>().map_err(ReadError::into_io_error)? {
+				return Ok(Some(packet_len));
+			}
+			let new_data = self.serial_port.read(self.parser.spare_capacity_mut(), deadline).map_err(ReadError::Io)?;
+			self.parser.advance(new_data);
 		}
-		self.consume_read_bytes(self.used_bytes + garbage_len);
-		debug_assert_eq!(self.used_bytes, 0);
 	}
 
-	fn consume_read_bytes(&mut self, len: usize) {
-		debug_assert!(len <= self.read_len);
-		self.read_buffer.as_mut().copy_within(len..self.read_len, 0);
-		// Decrease both used_bytes and read_len together.
-		// Some consumed bytes may be garbage instead of used bytes though.
-		// So we use `saturating_sub` for `used_bytes` to cap the result at 0.
-		self.used_bytes = self.used_bytes.saturating_sub(len);
-		self.read_len -= len;
+	/// Get the time elapsed since the start of the last write, if the serial port can measure it.
+	pub fn elapsed(&self) -> Option<Duration> {
+		let remaining = self.serial_port.remaining_time(self.write_start.as_ref()?)?;
+		Some(CLOCK_REFERENCE.saturating_sub(remaining))
 	}
-}
 
-/// Find the potential starting position of a header.
-///
-/// This will return the first possible position of the header prefix.
-/// Note that if the buffer ends with a partial header prefix,
-/// the start position of the partial header prefix is returned.
-fn find_header(buffer: &[u8]) -> usize {
-	for i in 0..buffer.len() {
-		let possible_prefix = HEADER_PREFIX.len().min(buffer.len() - i);
-		if buffer[i..].starts_with(&HEADER_PREFIX[..possible_prefix]) {
-			return i;
-		}
+	/// Wrap the packet received by [`Self::receive_packet()`].
+	pub fn packet<'a, P: Packet<'a>>(&'a self, packet_len: usize) -> P {
+		self.parser.packet(packet_len)
 	}
-
-	buffer.len()
-}
-
-#[test]
-fn test_find_garbage_end() {
-	assert!(find_header(&[0xFF]) == 0);
-	assert!(find_header(&[0xFF, 0xFF]) == 0);
-	assert!(find_header(&[0xFF, 0xFF, 0xFD]) == 0);
-	assert!(find_header(&[0xFF, 0xFF, 0xFD, 0x00]) == 0);
-	assert!(find_header(&[0xFF, 0xFF, 0xFD, 0x00, 9]) == 0);
-
-	assert!(find_header(&[0, 1, 2, 3, 4, 0xFF]) == 5);
-	assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 0xFF]) == 5);
-	assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 0xFF, 0xFD]) == 5);
-	assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 0xFF, 0xFD, 0x00]) == 5);
-	assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 0xFF, 0xFD, 0x00, 9]) == 5);
-
-	assert!(find_header(&[0xFF, 1]) == 2);
-	assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 6]) == 7);
 }
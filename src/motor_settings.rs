@@ -0,0 +1,205 @@
+use core::time::Duration;
+
+use crate::instructions::{instruction_id, packet_id};
+use crate::serial_port::SerialPort;
+use crate::{Bus, Response, TransferError};
+
+/// The number of motor IDs that can be assigned to a motor (0 to 252).
+const MOTOR_COUNT: usize = 253;
+
+/// Marker for an unknown return delay time in [`MotorSettings`].
+const UNKNOWN_RETURN_DELAY: u16 = u16::MAX;
+
+/// The Status Return Level of a motor.
+///
+/// The Status Return Level determines which instructions a motor responds to with a status packet.
+/// Regardless of the Status Return Level, motors never respond to instructions sent to the broadcast ID,
+/// except for the ping, sync read and bulk read instructions.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum StatusReturnLevel {
+	/// The motor only responds to ping instructions.
+	PingOnly = 0,
+
+	/// The motor only responds to ping and read instructions, including sync read and bulk read.
+	PingAndRead = 1,
+
+	/// The motor responds to all instructions.
+	///
+	/// This is the factory default for most motors.
+	All = 2,
+}
+
+impl StatusReturnLevel {
+	/// Check if a motor with this Status Return Level responds to a unicast instruction.
+	pub fn responds_to(self, instruction_id: u8) -> bool {
+		match self {
+			Self::PingOnly => instruction_id == instruction_id::PING,
+			Self::PingAndRead => matches!(
+				instruction_id,
+				instruction_id::PING | instruction_id::READ | instruction_id::SYNC_READ | instruction_id::BULK_READ
+			),
+			Self::All => true,
+		}
+	}
+}
+
+impl From<u8> for StatusReturnLevel {
+	/// Convert the raw register value to a [`StatusReturnLevel`].
+	///
+	/// Values above 2 are treated as [`StatusReturnLevel::All`].
+	fn from(raw: u8) -> Self {
+		match raw {
+			0 => Self::PingOnly,
+			1 => Self::PingAndRead,
+			_ => Self::All,
+		}
+	}
+}
+
+/// The response related settings of all motors, as known by the bus.
+#[derive(Debug, Clone)]
+pub(crate) struct MotorSettings {
+	/// The Status Return Level of each motor, if known.
+	status_return_level: [Option<StatusReturnLevel>; MOTOR_COUNT],
+
+	/// The Return Delay Time of each motor in microseconds, or [`UNKNOWN_RETURN_DELAY`].
+	return_delay_time: [u16; MOTOR_COUNT],
+}
+
+impl MotorSettings {
+	/// Create a new set of motor settings where nothing is known about any motor.
+	pub fn new() -> Self {
+		Self {
+			status_return_level: [None; MOTOR_COUNT],
+			return_delay_time: [UNKNOWN_RETURN_DELAY; MOTOR_COUNT],
+		}
+	}
+
+	/// Get the known Status Return Level of a motor.
+	pub fn status_return_level(&self, motor_id: u8) -> Option<StatusReturnLevel> {
+		*self.status_return_level.get(usize::from(motor_id))?
+	}
+
+	/// Set the known Status Return Level of a motor.
+	pub fn set_status_return_level(&mut self, motor_id: u8, level: Option<StatusReturnLevel>) {
+		if let Some(entry) = self.status_return_level.get_mut(usize::from(motor_id)) {
+			*entry = level;
+		}
+	}
+
+	/// Get the known Return Delay Time of a motor.
+	pub fn return_delay_time(&self, motor_id: u8) -> Option<Duration> {
+		let micros = *self.return_delay_time.get(usize::from(motor_id))?;
+		if micros == UNKNOWN_RETURN_DELAY {
+			None
+		} else {
+			Some(Duration::from_micros(micros.into()))
+		}
+	}
+
+	/// Set the known Return Delay Time of a motor.
+	///
+	/// The delay is rounded up to whole microseconds and saturates at about 65 milliseconds.
+	pub fn set_return_delay_time(&mut self, motor_id: u8, delay: Option<Duration>) {
+		if let Some(entry) = self.return_delay_time.get_mut(usize::from(motor_id)) {
+			*entry = match delay {
+				None => UNKNOWN_RETURN_DELAY,
+				Some(delay) => {
+					let micros = delay.as_nanos().div_ceil(1000);
+					micros.min(u128::from(UNKNOWN_RETURN_DELAY - 1)) as u16
+				},
+			};
+		}
+	}
+
+	/// Check if a motor is expected to send a status packet in response to an instruction.
+	pub fn expects_response(&self, motor_id: u8, instruction_id: u8) -> bool {
+		if motor_id == packet_id::BROADCAST {
+			return false;
+		}
+		self.status_return_level(motor_id)
+			.is_none_or(|level| level.responds_to(instruction_id))
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T> Bus<ReadBuffer, WriteBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// Get the Status Return Level that the bus assumes for a motor.
+	///
+	/// Returns `None` if the Status Return Level of the motor is unknown.
+	/// In that case, the bus assumes that the motor responds to all instructions.
+	pub fn status_return_level(&self, motor_id: u8) -> Option<StatusReturnLevel> {
+		self.motor_settings.status_return_level(motor_id)
+	}
+
+	/// Set the Status Return Level that the bus assumes for a motor.
+	///
+	/// This does not change the setting on the motor itself.
+	/// It only tells the bus which instructions the motor responds to,
+	/// so that functions like [`Self::write`] and [`Self::reg_write`] do not wait for a status packet that is never sent.
+	///
+	/// Read instructions always wait for a response, since they can not complete without one.
+	///
+	/// Pass `None` to forget the Status Return Level of the motor.
+	pub fn set_status_return_level(&mut self, motor_id: u8, level: Option<StatusReturnLevel>) {
+		self.motor_settings.set_status_return_level(motor_id, level)
+	}
+
+	/// Get the Return Delay Time that the bus assumes for a motor.
+	///
+	/// Returns `None` if the Return Delay Time of the motor is unknown.
+	/// In that case, the bus assumes the maximum Return Delay Time of 508 microseconds when computing read timeouts.
+	pub fn return_delay_time(&self, motor_id: u8) -> Option<Duration> {
+		self.motor_settings.return_delay_time(motor_id)
+	}
+
+	/// Set the Return Delay Time that the bus assumes for a motor.
+	///
+	/// This does not change the setting on the motor itself.
+	/// It only allows the bus to compute a tighter read timeout for responses from the motor.
+	///
+	/// Pass `None` to forget the Return Delay Time of the motor.
+	pub fn set_return_delay_time(&mut self, motor_id: u8, delay: Option<Duration>) {
+		self.motor_settings.set_return_delay_time(motor_id, delay)
+	}
+
+	/// Read the Status Return Level of a motor and remember it for future instructions.
+	///
+	/// The address of the Status Return Level register depends on the motor model.
+	/// For example, it is 68 for the X-series.
+	///
+	/// Note that a motor only responds to the read instruction if its Status Return Level is at least [`StatusReturnLevel::PingAndRead`].
+	/// For motors configured with [`StatusReturnLevel::PingOnly`], use [`Self::set_status_return_level`] instead.
+	pub fn query_status_return_level(&mut self, motor_id: u8, address: u16) -> Result<Response<StatusReturnLevel>, TransferError<T::Error>> {
+		let response = self.read_u8(motor_id, address)?;
+		let level = StatusReturnLevel::from(response.data);
+		self.motor_settings.set_status_return_level(motor_id, Some(level));
+		Ok(Response {
+			motor_id: response.motor_id,
+			alert: response.alert,
+			data: level,
+		})
+	}
+
+	/// Read the Return Delay Time of a motor and remember it for computing future read timeouts.
+	///
+	/// The address of the Return Delay Time register depends on the motor model.
+	/// For example, it is 9 for the X-series.
+	///
+	/// The register value is interpreted in units of 2 microseconds, as used by all Protocol 2.0 motors.
+	pub fn query_return_delay_time(&mut self, motor_id: u8, address: u16) -> Result<Response<Duration>, TransferError<T::Error>> {
+		let response = self.read_u8(motor_id, address)?;
+		let delay = Duration::from_micros(u64::from(response.data) * 2);
+		self.motor_settings.set_return_delay_time(motor_id, Some(delay));
+		Ok(Response {
+			motor_id: response.motor_id,
+			alert: response.alert,
+			data: delay,
+		})
+	}
+}
@@ -0,0 +1,367 @@
+//! Incremental parsing of packets from a stream of bytes.
+
+use core::convert::Infallible;
+
+use crate::checksum::calculate_checksum;
+use crate::device::InstructionPacket;
+use crate::endian::read_u16_le;
+use crate::instructions::instruction_id;
+use crate::log::HexBytes;
+use crate::packet::{Packet, HEADER_PREFIX, INSTRUCTION_HEADER_SIZE};
+use crate::{bytestuff, ReadError, StatusPacket};
+
+/// Incremental parser for packets received from the bus.
+///
+/// The parser does not perform any I/O by itself.
+/// Instead, received bytes are fed to the parser as they arrive,
+/// either with [`Self::feed()`] or by writing directly into [`Self::spare_capacity_mut()`] and calling [`Self::advance()`].
+/// Afterwards, [`Self::poll_status_packet()`] or [`Self::poll_instruction_packet()`] return a complete packet when one is available.
+///
+/// This allows the parser to be used from an interrupt handler or a cooperative scheduler that can not block.
+/// The blocking [`Bus`][crate::Bus] and [`Device`][crate::Device] use the same parser internally.
+#[derive(Debug)]
+pub struct PacketParser<Buffer> {
+	/// The buffer for incoming data.
+	buffer: Buffer,
+
+	/// The total number of valid bytes in the buffer.
+	read_len: usize,
+
+	/// The number of leading bytes in the buffer that have already been used.
+	used_bytes: usize,
+}
+
+/// A packet returned by [`PacketParser::poll_packet()`].
+#[derive(Debug)]
+pub enum ParsedPacket<'a> {
+	/// An instruction packet, sent by a [`Bus`][crate::Bus].
+	Instruction(InstructionPacket<'a>),
+
+	/// A status packet, sent by a [`Device`][crate::Device].
+	Status(StatusPacket<'a>),
+}
+
+impl<Buffer> PacketParser<Buffer>
+where
+	Buffer: AsRef<[u8]> + AsMut<[u8]>,
+{
+	/// Create a new parser using a pre-allocated buffer.
+	///
+	/// The buffer must be large enough to hold a complete packet, including byte-stuffing.
+	pub fn new(buffer: Buffer) -> Self {
+		Self {
+			buffer,
+			read_len: 0,
+			used_bytes: 0,
+		}
+	}
+
+	/// Discard all buffered data.
+	///
+	/// This should be done before sending a new instruction, to get rid of any stale responses.
+	pub fn clear(&mut self) {
+		self.read_len = 0;
+		self.used_bytes = 0;
+	}
+
+	/// Get the size on the wire of the packet returned by the last successful call to [`Self::poll_packet_len()`].
+	///
+	/// This includes the header, byte-stuffing and checksum.
+	pub(crate) fn last_packet_size(&self) -> usize {
+		self.used_bytes
+	}
+
+	/// Get the number of buffered bytes that have not been returned as a packet yet.
+	pub fn pending_len(&self) -> usize {
+		self.read_len - self.used_bytes
+	}
+
+	/// Copy received bytes into the parser.
+	///
+	/// Returns the number of bytes that fit in the buffer.
+	/// If not all bytes were accepted, poll for a packet and feed the remaining bytes afterwards.
+	pub fn feed(&mut self, data: &[u8]) -> usize {
+		let spare = self.spare_capacity_mut();
+		let len = spare.len().min(data.len());
+		spare[..len].copy_from_slice(&data[..len]);
+		self.advance(len);
+		len
+	}
+
+	/// Get the unused part of the buffer, to receive new data into directly.
+	///
+	/// After writing received bytes to the start of the returned slice, call [`Self::advance()`].
+	///
+	/// Note that any packet previously returned by the parser is removed from the buffer by this function.
+	pub fn spare_capacity_mut(&mut self) -> &mut [u8] {
+		self.remove_garbage();
+		let read_len = self.read_len;
+		&mut self.buffer.as_mut()[read_len..]
+	}
+
+	/// Mark `len` bytes at the start of [`Self::spare_capacity_mut()`] as received.
+	///
+	/// # Panics
+	/// This function panics if `len` exceeds the length of the spare capacity.
+	pub fn advance(&mut self, len: usize) {
+		assert!(self.read_len + len <= self.buffer.as_ref().len());
+		self.read_len += len;
+	}
+
+	/// Check if a complete status packet has been received.
+	///
+	/// Returns `Ok(None)` if more data is needed.
+	/// A packet with an invalid checksum is removed from the buffer and reported as an error.
+	/// A packet with an invalid length or that does not fit in the buffer is reported as an error too,
+	/// and its header is removed from the buffer so that the next call looks for the next packet.
+	pub fn poll_status_packet(&mut self) -> Result<Option<StatusPacket<'_>>, ReadError<Infallible>> {
+		match self.poll_packet_len::<StatusPacket>()? {
+			Some(packet_len) => Ok(Some(self.packet(packet_len))),
+			None => Ok(None),
+		}
+	}
+
+	/// Check if a complete instruction packet has been received.
+	///
+	/// Returns `Ok(None)` if more data is needed.
+	/// A packet with an invalid checksum is removed from the buffer and reported as an error.
+	/// A packet with an invalid length or that does not fit in the buffer is reported as an error too,
+	/// and its header is removed from the buffer so that the next call looks for the next packet.
+	pub fn poll_instruction_packet(&mut self) -> Result<Option<InstructionPacket<'_>>, ReadError<Infallible>> {
+		match self.poll_packet_len::<InstructionPacket>()? {
+			Some(packet_len) => Ok(Some(self.packet(packet_len))),
+			None => Ok(None),
+		}
+	}
+
+	/// Check if a complete packet of either kind has been received.
+	///
+	/// Packets with the status instruction ID are returned as [`StatusPacket`]s, all other packets as [`InstructionPacket`]s.
+	/// This is useful to decode all traffic on a bus, for example in a sniffer.
+	///
+	/// Returns `Ok(None)` if more data is needed.
+	/// A packet with an invalid checksum is removed from the buffer and reported as an error.
+	/// A packet with an invalid length or that does not fit in the buffer is reported as an error too,
+	/// and its header is removed from the buffer so that the next call looks for the next packet.
+	pub fn poll_packet(&mut self) -> Result<Option<ParsedPacket<'_>>, ReadError<Infallible>> {
+		match self.peek_instruction_id() {
+			None => Ok(None),
+			Some(instruction_id::STATUS) => Ok(self.poll_status_packet()?.map(ParsedPacket::Status)),
+			Some(_) => Ok(self.poll_instruction_packet()?.map(ParsedPacket::Instruction)),
+		}
+	}
+
+	/// Get the instruction ID of the next packet in the buffer, without removing it.
+	///
+	/// Returns `None` if the packet header has not been received completely yet.
+	pub(crate) fn peek_instruction_id(&mut self) -> Option<u8> {
+		self.remove_garbage();
+		if self.read_len < INSTRUCTION_HEADER_SIZE {
+			return None;
+		}
+		Some(self.buffer.as_ref()[7])
+	}
+
+	/// Check if a complete packet has been received, without wrapping it.
+	///
+	/// Returns the length of the packet (with byte-stuffing already undone), which can be passed to [`Self::packet()`].
+	pub(crate) fn poll_packet_len<'a, P: Packet<'a>>(&mut self) -> Result<Option<usize>, ReadError<Infallible>> {
+		// Check that the buffer is large enough to hold atleast a packet header.
+		crate::error::BufferTooSmallError::check(P::HEADER_SIZE, self.buffer.as_ref().len())?;
+
+		self.remove_garbage();
+
+		// The call to remove_garbage() removes all leading bytes that don't match a packet header.
+		// So if there's enough bytes left, it's a packet header.
+		if self.read_len <= P::HEADER_SIZE {
+			return Ok(None);
+		}
+		let buffer = &self.buffer.as_ref()[..self.read_len];
+		let length = buffer[5] as usize + buffer[6] as usize * 256;
+
+		// Length includes some bytes which are already included in P::HEADER_SIZE, and the checksum.
+		// If it is too short for that, drop the header prefix so we resync on the next header.
+		let Some(body_len) = length.checked_sub(P::HEADER_OVERLAP).filter(|&body_len| body_len >= 2) else {
+			self.consume_read_bytes(HEADER_PREFIX.len());
+			return Err(crate::InvalidParameterCount {
+				actual: length,
+				expected: crate::ExpectedCount::Min(P::HEADER_OVERLAP + 2),
+			}
+			.into());
+		};
+
+		// Check if the buffer is large enough for the entire message.
+		if let Err(e) = crate::error::BufferTooSmallError::check(P::HEADER_SIZE + body_len, self.buffer.as_ref().len()) {
+			self.consume_read_bytes(HEADER_PREFIX.len());
+			return Err(e.into());
+		}
+		if self.read_len < P::HEADER_SIZE + body_len {
+			return Ok(None);
+		}
+		let stuffed_message_len = P::HEADER_SIZE + body_len;
+		trace!("P::HEADER_SIZE: {}, body_len: {}", P::HEADER_SIZE, body_len);
+
+		let buffer = self.buffer.as_mut();
+		let parameters_end = stuffed_message_len - 2;
+		trace!("read packet: {:?}", HexBytes(&buffer[..parameters_end]));
+
+		let checksum_message = read_u16_le(&buffer[parameters_end..]);
+		let checksum_computed = calculate_checksum(0, &buffer[..parameters_end]);
+		if checksum_message != checksum_computed {
+			self.consume_read_bytes(stuffed_message_len);
+			return Err(crate::InvalidChecksum {
+				message: checksum_message,
+				computed: checksum_computed,
+			}
+			.into());
+		}
+
+		// Mark the whole message as "used_bytes", so that the next call to `remove_garbage()` removes it.
+		self.used_bytes += stuffed_message_len;
+
+		// Remove byte-stuffing from the parameters.
+		let parameter_count = bytestuff::unstuff_inplace(&mut buffer[P::HEADER_SIZE..parameters_end]);
+		trace!(
+			"received packet: {}",
+			crate::PrettyPacket::new(buffer[4], buffer[7], &buffer[INSTRUCTION_HEADER_SIZE..P::HEADER_SIZE + parameter_count])
+		);
+		Ok(Some(P::HEADER_SIZE + parameter_count))
+	}
+
+	/// Wrap the packet found by [`Self::poll_packet_len()`].
+	pub(crate) fn packet<'a, P: Packet<'a>>(&'a self, packet_len: usize) -> P {
+		P::new(&self.buffer.as_ref()[..packet_len])
+	}
+
+	/// Remove leading garbage data from the buffer.
+	fn remove_garbage(&mut self) {
+		let buffer = self.buffer.as_mut();
+		let garbage_len = find_header(&buffer[..self.read_len][self.used_bytes..]);
+		if garbage_len > 0 {
+			debug!("skipping {} bytes of leading garbage.", garbage_len);
+			trace!("skipped garbage: {:?}", HexBytes(&buffer[self.used_bytes..][..garbage_len]));
+		}
+		self.consume_read_bytes(self.used_bytes + garbage_len);
+		debug_assert_eq!(self.used_bytes, 0);
+	}
+
+	fn consume_read_bytes(&mut self, len: usize) {
+		debug_assert!(len <= self.read_len);
+		self.buffer.as_mut().copy_within(len..self.read_len, 0);
+		// Decrease both used_bytes and read_len together.
+		// Some consumed bytes may be garbage instead of used bytes though.
+		// So we use `saturating_sub` for `used_bytes` to cap the result at 0.
+		self.used_bytes = self.used_bytes.saturating_sub(len);
+		self.read_len -= len;
+	}
+}
+
+/// Find the potential starting position of a header.
+///
+/// This will return the first possible position of the header prefix.
+/// Note that if the buffer ends with a partial header prefix,
+/// the start position of the partial header prefix is returned.
+pub(crate) fn find_header(buffer: &[u8]) -> usize {
+	for i in 0..buffer.len() {
+		let possible_prefix = HEADER_PREFIX.len().min(buffer.len() - i);
+		if buffer[i..].starts_with(&HEADER_PREFIX[..possible_prefix]) {
+			return i;
+		}
+	}
+
+	buffer.len()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_find_garbage_end() {
+		assert!(find_header(&[0xFF]) == 0);
+		assert!(find_header(&[0xFF, 0xFF]) == 0);
+		assert!(find_header(&[0xFF, 0xFF, 0xFD]) == 0);
+		assert!(find_header(&[0xFF, 0xFF, 0xFD, 0x00]) == 0);
+		assert!(find_header(&[0xFF, 0xFF, 0xFD, 0x00, 9]) == 0);
+
+		assert!(find_header(&[0, 1, 2, 3, 4, 0xFF]) == 5);
+		assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 0xFF]) == 5);
+		assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 0xFF, 0xFD]) == 5);
+		assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 0xFF, 0xFD, 0x00]) == 5);
+		assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 0xFF, 0xFD, 0x00, 9]) == 5);
+
+		assert!(find_header(&[0xFF, 1]) == 2);
+		assert!(find_header(&[0, 1, 2, 3, 4, 0xFF, 6]) == 7);
+	}
+
+	#[test]
+	fn test_feed_byte_by_byte() {
+		// Status packet from the protocol documentation: ping response of motor 1.
+		let packet = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5D];
+		let mut parser = PacketParser::new([0; 64]);
+		assert!(parser.feed(&[0x12, 0x34]) == 2);
+		for &byte in &packet[..packet.len() - 1] {
+			assert!(parser.feed(&[byte]) == 1);
+			assert!(let Ok(None) = parser.poll_status_packet());
+		}
+		assert!(parser.feed(&packet[packet.len() - 1..]) == 1);
+		let_assert!(Ok(Some(response)) = parser.poll_status_packet());
+		assert!(response.packet_id() == 1);
+		assert!(response.parameters() == [0x06, 0x04, 0x26]);
+		assert!(let Ok(None) = parser.poll_status_packet());
+		assert!(parser.pending_len() == 0);
+	}
+
+	#[test]
+	fn test_poll_packet() {
+		// Ping instruction for motor 1, followed by the status packet from the protocol documentation.
+		let instruction = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E];
+		let status = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5D];
+		let mut parser = PacketParser::new([0; 64]);
+		assert!(parser.feed(&instruction) == instruction.len());
+		assert!(parser.feed(&status) == status.len());
+
+		let_assert!(Ok(Some(ParsedPacket::Instruction(packet))) = parser.poll_packet());
+		assert!(packet.packet_id() == 1);
+		assert!(packet.instruction_id() == instruction_id::PING);
+		let_assert!(Ok(Some(ParsedPacket::Status(packet))) = parser.poll_packet());
+		assert!(packet.packet_id() == 1);
+		assert!(packet.parameters() == [0x06, 0x04, 0x26]);
+		assert!(let Ok(None) = parser.poll_packet());
+	}
+
+	#[test]
+	fn test_invalid_checksum() {
+		let packet = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5E];
+		let mut parser = PacketParser::new([0; 64]);
+		assert!(parser.feed(&packet) == packet.len());
+		assert!(let Err(ReadError::InvalidMessage(crate::InvalidMessage::InvalidChecksum(_))) = parser.poll_status_packet());
+		assert!(let Ok(None) = parser.poll_status_packet());
+	}
+
+	#[test]
+	fn test_invalid_length() {
+		let status = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5D];
+		for length in [0, 1, 2, 3] {
+			let mut parser = PacketParser::new([0; 64]);
+			assert!(parser.feed(&[0xFF, 0xFF, 0xFD, 0x00, 0x01, length, 0x00, 0x55, 0x00, 0x00]) == 10);
+			assert!(parser.feed(&status) == status.len());
+			let_assert!(Err(ReadError::InvalidMessage(crate::InvalidMessage::InvalidParameterCount(e))) = parser.poll_packet());
+			assert!(e.actual == usize::from(length));
+			let_assert!(Ok(Some(ParsedPacket::Status(packet))) = parser.poll_packet());
+			assert!(packet.parameters() == [0x06, 0x04, 0x26]);
+		}
+	}
+
+	#[test]
+	fn test_packet_too_large() {
+		let status = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5D];
+		let mut parser = PacketParser::new([0; 32]);
+		assert!(parser.feed(&[0xFF, 0xFF, 0xFD, 0x00, 0x01, 0xFF, 0xFF, 0x55, 0x00]) == 9);
+		assert!(parser.feed(&status) == status.len());
+		assert!(let Err(ReadError::BufferFull(_)) = parser.poll_status_packet());
+		let_assert!(Ok(Some(packet)) = parser.poll_status_packet());
+		assert!(packet.parameters() == [0x06, 0x04, 0x26]);
+	}
+}
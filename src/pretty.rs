@@ -0,0 +1,375 @@
+//! Human readable formatting of packets.
+
+use core::fmt::{Debug, Display, Formatter, Result as FmtResult};
+
+use crate::endian::{read_u16_le, read_u32_le};
+use crate::instructions::{instruction_id, packet_id};
+use crate::{BulkWriteIter, Packet, SyncWriteIter};
+
+/// A table that maps register addresses to names.
+///
+/// This is implemented for slices, arrays and maps of `(address, name)` pairs.
+/// The unit type `()` can be used as an empty table.
+/// See [`crate::simulation::xm430::REGISTER_NAMES`] for the table of the XM430.
+pub trait RegisterNames {
+	/// Get the name of the register at the given address, if it is known.
+	fn register_name(&self, address: u16) -> Option<&str>;
+}
+
+impl RegisterNames for () {
+	fn register_name(&self, _address: u16) -> Option<&str> {
+		None
+	}
+}
+
+impl<S: AsRef<str>> RegisterNames for [(u16, S)] {
+	fn register_name(&self, address: u16) -> Option<&str> {
+		self.iter().find(|(x, _)| *x == address).map(|(_, name)| name.as_ref())
+	}
+}
+
+impl<S: AsRef<str>, const N: usize> RegisterNames for [(u16, S); N] {
+	fn register_name(&self, address: u16) -> Option<&str> {
+		self.as_slice().register_name(address)
+	}
+}
+
+impl<T: RegisterNames + ?Sized> RegisterNames for &T {
+	fn register_name(&self, address: u16) -> Option<&str> {
+		(**self).register_name(address)
+	}
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<S: AsRef<str>> RegisterNames for alloc::vec::Vec<(u16, S)> {
+	fn register_name(&self, address: u16) -> Option<&str> {
+		self.as_slice().register_name(address)
+	}
+}
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+impl<S: AsRef<str>> RegisterNames for alloc::collections::BTreeMap<u16, S> {
+	fn register_name(&self, address: u16) -> Option<&str> {
+		self.get(&address).map(|name| name.as_ref())
+	}
+}
+
+#[cfg(feature = "std")]
+impl<S: AsRef<str>, H: std::hash::BuildHasher> RegisterNames for std::collections::HashMap<u16, S, H> {
+	fn register_name(&self, address: u16) -> Option<&str> {
+		self.get(&address).map(|name| name.as_ref())
+	}
+}
+
+/// A packet formatted for humans, such as `WRITE id=1 addr=116 (Goal Position) data=2048`.
+///
+/// The [`Display`] implementation decodes the parameters of all known instructions.
+/// Data of 1, 2 or 4 bytes is shown as an unsigned little endian number, other data as a list of bytes.
+/// Status packets are shown as `STATUS id=1 err=0 params=[06, 04, 26]`.
+/// Packets with malformed parameters are shown with their raw parameters.
+///
+/// Register names are looked up in the [`RegisterNames`] table passed to [`Self::with_names()`].
+///
+/// # Example
+/// ```
+/// use dynamixel2::PrettyPacket;
+/// use dynamixel2::instructions::instruction_id;
+/// use dynamixel2::simulation::xm430::REGISTER_NAMES;
+///
+/// let packet = PrettyPacket::new(1, instruction_id::WRITE, &[116, 0, 0x00, 0x08, 0x00, 0x00]);
+/// assert_eq!(packet.to_string(), "WRITE id=1 addr=116 data=2048");
+/// assert_eq!(packet.with_names(REGISTER_NAMES).to_string(), "WRITE id=1 addr=116 (Goal Position) data=2048");
+/// ```
+pub struct PrettyPacket<'a, N: ?Sized = ()> {
+	packet_id: u8,
+	instruction_id: u8,
+	parameters: &'a [u8],
+	names: &'a N,
+}
+
+impl<'a> PrettyPacket<'a> {
+	/// Format a packet from its packet ID, instruction ID and parameters.
+	///
+	/// For status packets, the parameters must start with the error field.
+	pub fn new(packet_id: u8, instruction_id: u8, parameters: &'a [u8]) -> Self {
+		Self {
+			packet_id,
+			instruction_id,
+			parameters,
+			names: &(),
+		}
+	}
+
+	/// Format a packet from its raw bytes, without byte-stuffing and without the CRC.
+	///
+	/// Returns `None` if the data is too short to hold a packet header.
+	pub fn from_bytes(data: &'a [u8]) -> Option<Self> {
+		if data.len() < crate::packet::INSTRUCTION_HEADER_SIZE {
+			return None;
+		}
+		Some(Self::new(data[4], data[7], &data[crate::packet::INSTRUCTION_HEADER_SIZE..]))
+	}
+
+	/// Format a received instruction or status packet.
+	pub fn from_packet<P: Packet<'a>>(packet: &'a P) -> Self {
+		let data = packet.as_bytes();
+		Self::new(data[4], data[7], &data[crate::packet::INSTRUCTION_HEADER_SIZE..])
+	}
+}
+
+impl<'a, N: ?Sized> PrettyPacket<'a, N> {
+	/// Use a table to show the names of the registers.
+	pub fn with_names<M: RegisterNames + ?Sized>(self, names: &'a M) -> PrettyPacket<'a, M> {
+		PrettyPacket {
+			packet_id: self.packet_id,
+			instruction_id: self.instruction_id,
+			parameters: self.parameters,
+			names,
+		}
+	}
+}
+
+impl<N: RegisterNames + ?Sized> PrettyPacket<'_, N> {
+	/// Write the instruction name and the packet ID.
+	fn write_header(&self, f: &mut Formatter) -> FmtResult {
+		match instruction_name(self.instruction_id) {
+			Some(name) => write!(f, "{}", name)?,
+			None => write!(f, "INSTRUCTION(0x{:02X})", self.instruction_id)?,
+		}
+		if self.packet_id == packet_id::BROADCAST {
+			write!(f, " id=broadcast")
+		} else {
+			write!(f, " id={}", self.packet_id)
+		}
+	}
+
+	/// Write the packet with the raw parameters, for unknown instructions or malformed packets.
+	fn write_raw(&self, f: &mut Formatter) -> FmtResult {
+		self.write_header(f)?;
+		write!(f, " params={:02X?}", self.parameters)
+	}
+
+	/// Write a register address, with the register name if it is known.
+	fn write_address(&self, f: &mut Formatter, address: u16) -> FmtResult {
+		write!(f, "addr={}", address)?;
+		if let Some(name) = self.names.register_name(address) {
+			write!(f, " ({})", name)?;
+		}
+		Ok(())
+	}
+}
+
+impl<N: RegisterNames + ?Sized> Display for PrettyPacket<'_, N> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		let parameters = self.parameters;
+		match self.instruction_id {
+			instruction_id::STATUS => {
+				let Some((&error, parameters)) = parameters.split_first() else {
+					return self.write_raw(f);
+				};
+				self.write_header(f)?;
+				write!(f, " err={}", error & 0x7F)?;
+				if error & 0x80 != 0 {
+					write!(f, " alert")?;
+				}
+				write!(f, " params={:02X?}", parameters)
+			},
+			instruction_id::PING | instruction_id::ACTION | instruction_id::REBOOT if parameters.is_empty() => self.write_header(f),
+			instruction_id::FACTORY_RESET if parameters.len() == 1 => {
+				self.write_header(f)?;
+				write!(f, " mode=0x{:02X}", parameters[0])
+			},
+			instruction_id::READ if parameters.len() == 4 => {
+				self.write_header(f)?;
+				write!(f, " ")?;
+				self.write_address(f, read_u16_le(&parameters[0..]))?;
+				write!(f, " len={}", read_u16_le(&parameters[2..]))
+			},
+			instruction_id::WRITE | instruction_id::REG_WRITE if parameters.len() >= 2 => {
+				self.write_header(f)?;
+				write!(f, " ")?;
+				self.write_address(f, read_u16_le(&parameters[0..]))?;
+				write!(f, " data={}", Data(&parameters[2..]))
+			},
+			instruction_id::SYNC_READ if parameters.len() >= 4 => {
+				self.write_header(f)?;
+				write!(f, " ")?;
+				self.write_address(f, read_u16_le(&parameters[0..]))?;
+				write!(f, " len={} ids={:?}", read_u16_le(&parameters[2..]), &parameters[4..])
+			},
+			instruction_id::SYNC_WRITE if parameters.len() >= 4 => {
+				let address = read_u16_le(&parameters[0..]);
+				let length = read_u16_le(&parameters[2..]);
+				let Ok(writes) = SyncWriteIter::new(address, length, &parameters[4..]) else {
+					return self.write_raw(f);
+				};
+				self.write_header(f)?;
+				write!(f, " ")?;
+				self.write_address(f, address)?;
+				write!(f, " len={} data={{", length)?;
+				for (i, write) in writes.enumerate() {
+					let separator = if i == 0 { "" } else { ", " };
+					write!(f, "{}{}: {}", separator, write.motor_id, Data(write.data))?;
+				}
+				write!(f, "}}")
+			},
+			instruction_id::BULK_READ if parameters.len().is_multiple_of(5) => {
+				self.write_header(f)?;
+				write!(f, " reads={{")?;
+				for (i, block) in parameters.chunks_exact(5).enumerate() {
+					let separator = if i == 0 { "" } else { ", " };
+					write!(f, "{}{}: ", separator, block[0])?;
+					self.write_address(f, read_u16_le(&block[1..]))?;
+					write!(f, " len={}", read_u16_le(&block[3..]))?;
+				}
+				write!(f, "}}")
+			},
+			instruction_id::BULK_WRITE => {
+				let Ok(writes) = BulkWriteIter::new(parameters) else {
+					return self.write_raw(f);
+				};
+				self.write_header(f)?;
+				write!(f, " writes={{")?;
+				for (i, write) in writes.enumerate() {
+					let separator = if i == 0 { "" } else { ", " };
+					write!(f, "{}{}: ", separator, write.motor_id)?;
+					self.write_address(f, write.address)?;
+					write!(f, " data={}", Data(write.data))?;
+				}
+				write!(f, "}}")
+			},
+			_ => self.write_raw(f),
+		}
+	}
+}
+
+impl<N: ?Sized> Debug for PrettyPacket<'_, N> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("PrettyPacket")
+			.field("packet_id", &self.packet_id)
+			.field("instruction_id", &self.instruction_id)
+			.field("parameters", &self.parameters)
+			.finish_non_exhaustive()
+	}
+}
+
+/// The `defmt` representation only contains the instruction, packet ID and raw parameters, to keep the frames small.
+#[cfg(feature = "defmt")]
+impl<N: ?Sized> defmt::Format for PrettyPacket<'_, N> {
+	fn format(&self, f: defmt::Formatter<'_>) {
+		match instruction_name(self.instruction_id) {
+			Some(name) => defmt::write!(f, "{=str}", name),
+			None => defmt::write!(f, "INSTRUCTION({=u8:#04X})", self.instruction_id),
+		}
+		defmt::write!(f, " id={=u8} params={=[u8]:02X}", self.packet_id, self.parameters)
+	}
+}
+
+impl<N: ?Sized> Clone for PrettyPacket<'_, N> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<N: ?Sized> Copy for PrettyPacket<'_, N> {}
+
+/// Register data, shown as a number if it has the size of a common register.
+struct Data<'a>(&'a [u8]);
+
+impl Display for Data<'_> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self.0.len() {
+			1 => write!(f, "{}", self.0[0]),
+			2 => write!(f, "{}", read_u16_le(self.0)),
+			4 => write!(f, "{}", read_u32_le(self.0)),
+			_ => write!(f, "{:02X?}", self.0),
+		}
+	}
+}
+
+/// Get the name of an instruction.
+fn instruction_name(instruction_id: u8) -> Option<&'static str> {
+	match instruction_id {
+		instruction_id::PING => Some("PING"),
+		instruction_id::READ => Some("READ"),
+		instruction_id::WRITE => Some("WRITE"),
+		instruction_id::REG_WRITE => Some("REG_WRITE"),
+		instruction_id::ACTION => Some("ACTION"),
+		instruction_id::FACTORY_RESET => Some("FACTORY_RESET"),
+		instruction_id::REBOOT => Some("REBOOT"),
+		instruction_id::CLEAR => Some("CLEAR"),
+		instruction_id::SYNC_READ => Some("SYNC_READ"),
+		instruction_id::SYNC_WRITE => Some("SYNC_WRITE"),
+		instruction_id::BULK_READ => Some("BULK_READ"),
+		instruction_id::BULK_WRITE => Some("BULK_WRITE"),
+		instruction_id::STATUS => Some("STATUS"),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::simulation::xm430::REGISTER_NAMES;
+	use assert2::assert;
+
+	/// Format a packet with the XM430 register names.
+	fn pretty(packet_id: u8, instruction_id: u8, parameters: &[u8]) -> String {
+		PrettyPacket::new(packet_id, instruction_id, parameters)
+			.with_names(REGISTER_NAMES)
+			.to_string()
+	}
+
+	#[test]
+	fn test_instructions() {
+		assert!(pretty(1, instruction_id::PING, &[]) == "PING id=1");
+		assert!(pretty(packet_id::BROADCAST, instruction_id::ACTION, &[]) == "ACTION id=broadcast");
+		assert!(pretty(1, instruction_id::READ, &[132, 0, 4, 0]) == "READ id=1 addr=132 (Present Position) len=4");
+		assert!(pretty(1, instruction_id::WRITE, &[64, 0, 1]) == "WRITE id=1 addr=64 (Torque Enable) data=1");
+		assert!(pretty(1, instruction_id::REG_WRITE, &[200, 0, 1, 2, 3]) == "REG_WRITE id=1 addr=200 data=[01, 02, 03]");
+		assert!(pretty(1, instruction_id::FACTORY_RESET, &[0xFF]) == "FACTORY_RESET id=1 mode=0xFF");
+		assert!(
+			pretty(packet_id::BROADCAST, instruction_id::SYNC_READ, &[132, 0, 4, 0, 1, 2])
+				== "SYNC_READ id=broadcast addr=132 (Present Position) len=4 ids=[1, 2]"
+		);
+		assert!(
+			pretty(
+				packet_id::BROADCAST,
+				instruction_id::SYNC_WRITE,
+				&[104, 0, 2, 0, 1, 10, 0, 2, 20, 0]
+			) == "SYNC_WRITE id=broadcast addr=104 (Goal Velocity) len=2 data={1: 10, 2: 20}"
+		);
+		assert!(
+			pretty(packet_id::BROADCAST, instruction_id::BULK_READ, &[1, 132, 0, 4, 0, 2, 65, 0, 1, 0])
+				== "BULK_READ id=broadcast reads={1: addr=132 (Present Position) len=4, 2: addr=65 (LED) len=1}"
+		);
+		assert!(
+			pretty(
+				packet_id::BROADCAST,
+				instruction_id::BULK_WRITE,
+				&[1, 65, 0, 1, 0, 1, 2, 64, 0, 1, 0, 0]
+			) == "BULK_WRITE id=broadcast writes={1: addr=65 (LED) data=1, 2: addr=64 (Torque Enable) data=0}"
+		);
+	}
+
+	#[test]
+	fn test_status() {
+		assert!(pretty(1, instruction_id::STATUS, &[0, 0x06, 0x04, 0x26]) == "STATUS id=1 err=0 params=[06, 04, 26]");
+		assert!(pretty(1, instruction_id::STATUS, &[0x87]) == "STATUS id=1 err=7 alert params=[]");
+	}
+
+	#[test]
+	fn test_malformed() {
+		assert!(pretty(1, instruction_id::READ, &[132, 0]) == "READ id=1 params=[84, 00]");
+		assert!(pretty(1, instruction_id::BULK_WRITE, &[1, 65, 0, 4, 0, 1]) == "BULK_WRITE id=1 params=[01, 41, 00, 04, 00, 01]");
+		assert!(pretty(1, 0x20, &[1]) == "INSTRUCTION(0x20) id=1 params=[01]");
+		assert!(pretty(1, instruction_id::STATUS, &[]) == "STATUS id=1 params=[]");
+	}
+
+	#[test]
+	fn test_from_bytes() {
+		let packet = PrettyPacket::from_bytes(&[0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01]);
+		assert!(packet.map(|x| x.to_string()) == Some("PING id=1".into()));
+		assert!(PrettyPacket::from_bytes(&[0xFF, 0xFF, 0xFD]).is_none());
+	}
+}
@@ -0,0 +1,524 @@
+//! Fault injection for serial ports, to test how a bus or device copes with a noisy line.
+//!
+//! A [`FaultySerialPort`] wraps any other [`SerialPort`] and corrupts the data passing through it,
+//! according to a [`FaultSchedule`].
+//! The schedule can be a seeded pseudo-random schedule ([`RandomFaults`]) or a fixed script ([`ScriptedFaults`]),
+//! so that every test run sees exactly the same faults.
+//!
+//! Two kinds of faults are supported:
+//! * [`PacketFault`]s corrupt a packet written to the wrapped port.
+//!   Every call to [`SerialPort::write_all()`] is treated as one packet.
+//! * [`ReadFault`]s change how received data is delivered by [`SerialPort::read()`].
+//!
+//! To corrupt the status packets received by a [`Bus`][crate::Bus], wrap the serial port of the [`Device`][crate::Device] on the other side,
+//! or use read faults on the serial port of the bus.
+//!
+//! # Example
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use dynamixel2::Bus;
+//! use dynamixel2::fault_injection::{FaultySerialPort, RandomFaults};
+//!
+//! let serial_port = serial2::SerialPort::open("/dev/ttyUSB0", 57600)?;
+//! let faults = RandomFaults::new(1234).with_flip_bit(0.01).with_split_read(0.5);
+//! let mut bus = Bus::with_buffers(FaultySerialPort::new(serial_port, faults), vec![0; 128], vec![0; 128])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::SerialPort;
+
+/// A fault that corrupts a packet written to a [`FaultySerialPort`].
+///
+/// Indices that are out of range for the packet wrap around.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PacketFault {
+	/// Leave out the byte at the given index.
+	DropByte(usize),
+
+	/// Flip a single bit (0 to 7) of the byte at the given index.
+	FlipBit {
+		/// The index of the byte in the packet.
+		index: usize,
+
+		/// The bit to flip.
+		bit: u8,
+	},
+
+	/// Write some garbage before the packet header.
+	InsertGarbage {
+		/// The value of the garbage bytes.
+		byte: u8,
+
+		/// The number of garbage bytes.
+		count: usize,
+	},
+
+	/// Write the packet twice.
+	Duplicate,
+}
+
+/// A fault that affects the data delivered by [`FaultySerialPort::read()`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ReadFault {
+	/// Deliver at most the given number of bytes (but at least one), and keep the rest for the next read.
+	SplitRead(usize),
+
+	/// Hold back the received data until the deadline has passed.
+	///
+	/// The read fails with the timeout error of the wrapped port.
+	/// If the wrapped port returns no data at the deadline instead of an error, the read returns no data too.
+	/// The data that was held back is delivered by a later read,
+	/// even if the input buffer is discarded in the mean time.
+	Delay,
+}
+
+/// A schedule that decides which faults are injected by a [`FaultySerialPort`].
+pub trait FaultSchedule {
+	/// Decide which fault to inject in a packet that is about to be written.
+	fn packet_fault(&mut self, packet: &[u8]) -> Option<PacketFault>;
+
+	/// Decide which fault to inject in a read that is about to deliver `available` bytes.
+	///
+	/// Reads that do not deliver any data do not consult the schedule.
+	fn read_fault(&mut self, available: usize) -> Option<ReadFault>;
+}
+
+impl<S: FaultSchedule + ?Sized> FaultSchedule for &mut S {
+	fn packet_fault(&mut self, packet: &[u8]) -> Option<PacketFault> {
+		(**self).packet_fault(packet)
+	}
+
+	fn read_fault(&mut self, available: usize) -> Option<ReadFault> {
+		(**self).read_fault(available)
+	}
+}
+
+/// A fault schedule that follows a fixed script.
+///
+/// Each written packet takes the next entry of the packet script,
+/// and each read that delivers data takes the next entry of the read script.
+/// When a script runs out, no more faults of that kind are injected.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedFaults {
+	packet_faults: VecDeque<Option<PacketFault>>,
+	read_faults: VecDeque<Option<ReadFault>>,
+}
+
+impl ScriptedFaults {
+	/// Create a new empty script.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add an entry to the packet script.
+	///
+	/// Use `None` to let a packet pass without faults.
+	pub fn then_packet(mut self, fault: impl Into<Option<PacketFault>>) -> Self {
+		self.packet_faults.push_back(fault.into());
+		self
+	}
+
+	/// Add an entry to the read script.
+	///
+	/// Use `None` to let a read pass without faults.
+	pub fn then_read(mut self, fault: impl Into<Option<ReadFault>>) -> Self {
+		self.read_faults.push_back(fault.into());
+		self
+	}
+
+	/// Get the number of remaining entries in the packet script.
+	pub fn remaining_packet_faults(&self) -> usize {
+		self.packet_faults.len()
+	}
+
+	/// Get the number of remaining entries in the read script.
+	pub fn remaining_read_faults(&self) -> usize {
+		self.read_faults.len()
+	}
+}
+
+impl FaultSchedule for ScriptedFaults {
+	fn packet_fault(&mut self, _packet: &[u8]) -> Option<PacketFault> {
+		self.packet_faults.pop_front().flatten()
+	}
+
+	fn read_fault(&mut self, _available: usize) -> Option<ReadFault> {
+		self.read_faults.pop_front().flatten()
+	}
+}
+
+/// A fault schedule that injects faults at random, with a fixed seed.
+///
+/// Each kind of fault has its own probability, which defaults to zero.
+/// The same seed always produces the same sequence of faults for the same sequence of packets and reads.
+#[derive(Debug, Clone)]
+pub struct RandomFaults {
+	state: u64,
+	drop_byte: f32,
+	flip_bit: f32,
+	insert_garbage: f32,
+	duplicate: f32,
+	split_read: f32,
+	delay: f32,
+}
+
+impl RandomFaults {
+	/// Create a new random schedule with the given seed, without any faults enabled.
+	pub fn new(seed: u64) -> Self {
+		Self {
+			// The xorshift state must not be zero.
+			state: (seed ^ 0x9E37_79B9_7F4A_7C15).max(1),
+			drop_byte: 0.0,
+			flip_bit: 0.0,
+			insert_garbage: 0.0,
+			duplicate: 0.0,
+			split_read: 0.0,
+			delay: 0.0,
+		}
+	}
+
+	/// Set the probability that a byte is dropped from a packet.
+	pub fn with_drop_byte(mut self, probability: f32) -> Self {
+		self.drop_byte = probability;
+		self
+	}
+
+	/// Set the probability that a bit is flipped in a packet.
+	pub fn with_flip_bit(mut self, probability: f32) -> Self {
+		self.flip_bit = probability;
+		self
+	}
+
+	/// Set the probability that garbage is written before a packet.
+	pub fn with_insert_garbage(mut self, probability: f32) -> Self {
+		self.insert_garbage = probability;
+		self
+	}
+
+	/// Set the probability that a packet is written twice.
+	pub fn with_duplicate(mut self, probability: f32) -> Self {
+		self.duplicate = probability;
+		self
+	}
+
+	/// Set the probability that a read is split in two.
+	pub fn with_split_read(mut self, probability: f32) -> Self {
+		self.split_read = probability;
+		self
+	}
+
+	/// Set the probability that received data is held back until after the deadline.
+	pub fn with_delay(mut self, probability: f32) -> Self {
+		self.delay = probability;
+		self
+	}
+
+	/// Get the next pseudo-random number (xorshift64*).
+	fn next_u64(&mut self) -> u64 {
+		self.state ^= self.state >> 12;
+		self.state ^= self.state << 25;
+		self.state ^= self.state >> 27;
+		self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+	}
+
+	/// Get a pseudo-random number in the range `0..max`.
+	fn next_below(&mut self, max: usize) -> usize {
+		(self.next_u64() % max.max(1) as u64) as usize
+	}
+
+	/// Randomly decide if an event with the given probability happens.
+	fn chance(&mut self, probability: f32) -> bool {
+		// Always draw a number, so the sequence does not depend on which probabilities are zero.
+		let sample = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+		sample < probability
+	}
+}
+
+impl FaultSchedule for RandomFaults {
+	fn packet_fault(&mut self, packet: &[u8]) -> Option<PacketFault> {
+		if self.chance(self.drop_byte) {
+			Some(PacketFault::DropByte(self.next_below(packet.len())))
+		} else if self.chance(self.flip_bit) {
+			let index = self.next_below(packet.len());
+			let bit = self.next_below(8) as u8;
+			Some(PacketFault::FlipBit { index, bit })
+		} else if self.chance(self.insert_garbage) {
+			let byte = self.next_u64() as u8;
+			let count = 1 + self.next_below(8);
+			Some(PacketFault::InsertGarbage { byte, count })
+		} else if self.chance(self.duplicate) {
+			Some(PacketFault::Duplicate)
+		} else {
+			None
+		}
+	}
+
+	fn read_fault(&mut self, available: usize) -> Option<ReadFault> {
+		if self.chance(self.delay) {
+			Some(ReadFault::Delay)
+		} else if available > 1 && self.chance(self.split_read) {
+			Some(ReadFault::SplitRead(1 + self.next_below(available - 1)))
+		} else {
+			None
+		}
+	}
+}
+
+/// A serial port wrapper that injects faults according to a [`FaultSchedule`].
+///
+/// See the [module documentation](self) for more details.
+#[derive(Debug)]
+pub struct FaultySerialPort<T, S> {
+	inner: T,
+	schedule: S,
+	pending: VecDeque<u8>,
+	delayed: VecDeque<u8>,
+}
+
+impl<T: SerialPort, S: FaultSchedule> FaultySerialPort<T, S> {
+	/// Wrap a serial port to inject faults according to the given schedule.
+	pub fn new(inner: T, schedule: S) -> Self {
+		Self {
+			inner,
+			schedule,
+			pending: VecDeque::new(),
+			delayed: VecDeque::new(),
+		}
+	}
+
+	/// Get a reference to the wrapped serial port.
+	pub fn inner(&self) -> &T {
+		&self.inner
+	}
+
+	/// Get a mutable reference to the wrapped serial port.
+	pub fn inner_mut(&mut self) -> &mut T {
+		&mut self.inner
+	}
+
+	/// Get a reference to the fault schedule.
+	pub fn schedule(&self) -> &S {
+		&self.schedule
+	}
+
+	/// Get a mutable reference to the fault schedule.
+	pub fn schedule_mut(&mut self) -> &mut S {
+		&mut self.schedule
+	}
+
+	/// Consume the wrapper to get the wrapped serial port and the fault schedule.
+	///
+	/// Any data that was received but not delivered yet is lost.
+	pub fn into_parts(self) -> (T, S) {
+		(self.inner, self.schedule)
+	}
+}
+
+impl<T: SerialPort, S: FaultSchedule> SerialPort for FaultySerialPort<T, S> {
+	type Error = T::Error;
+
+	type Instant = T::Instant;
+
+	fn baud_rate(&self) -> Result<u32, Self::Error> {
+		self.inner.baud_rate()
+	}
+
+	fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> {
+		self.inner.set_baud_rate(baud_rate)
+	}
+
+	fn discard_input_buffer(&mut self) -> Result<(), Self::Error> {
+		// Delayed data is still "on the wire", so it survives.
+		self.pending.clear();
+		self.inner.discard_input_buffer()
+	}
+
+	fn read(&mut self, buffer: &mut [u8], deadline: &Self::Instant) -> Result<usize, Self::Error> {
+		if buffer.is_empty() {
+			return Ok(0);
+		}
+		self.pending.extend(self.delayed.drain(..));
+		if self.pending.is_empty() {
+			let read = self.inner.read(buffer, deadline)?;
+			self.pending.extend(&buffer[..read]);
+		}
+		if self.pending.is_empty() {
+			return Ok(0);
+		}
+
+		let mut len = self.pending.len().min(buffer.len());
+		match self.schedule.read_fault(self.pending.len()) {
+			None => (),
+			Some(ReadFault::SplitRead(max)) => {
+				trace!("fault injection: splitting read after {} bytes", max.max(1));
+				len = len.min(max.max(1));
+			},
+			Some(ReadFault::Delay) => {
+				trace!("fault injection: delaying {} bytes until after the deadline", self.pending.len());
+				self.delayed.extend(self.pending.drain(..));
+				loop {
+					let read = self.inner.read(buffer, deadline)?;
+					self.delayed.extend(&buffer[..read]);
+					if read == 0 || self.inner.remaining_time(deadline) == Some(Duration::ZERO) {
+						return Ok(0);
+					}
+				}
+			},
+		}
+
+		for (output, byte) in buffer.iter_mut().zip(self.pending.drain(..len)) {
+			*output = byte;
+		}
+		Ok(len)
+	}
+
+	fn write_all(&mut self, buffer: &[u8]) -> Result<(), Self::Error> {
+		let Some(fault) = self.schedule.packet_fault(buffer) else {
+			return self.inner.write_all(buffer);
+		};
+		if buffer.is_empty() {
+			return self.inner.write_all(buffer);
+		}
+
+		let mut packet = Vec::with_capacity(buffer.len() * 2);
+		match fault {
+			PacketFault::DropByte(index) => {
+				let index = index % buffer.len();
+				trace!("fault injection: dropping byte {} of packet", index);
+				packet.extend_from_slice(&buffer[..index]);
+				packet.extend_from_slice(&buffer[index + 1..]);
+			},
+			PacketFault::FlipBit { index, bit } => {
+				let index = index % buffer.len();
+				trace!("fault injection: flipping bit {} of byte {} of packet", bit % 8, index);
+				packet.extend_from_slice(buffer);
+				packet[index] ^= 1 << (bit % 8);
+			},
+			PacketFault::InsertGarbage { byte, count } => {
+				trace!("fault injection: inserting {} garbage bytes before packet", count);
+				packet.resize(count, byte);
+				packet.extend_from_slice(buffer);
+			},
+			PacketFault::Duplicate => {
+				trace!("fault injection: duplicating packet");
+				packet.extend_from_slice(buffer);
+				packet.extend_from_slice(buffer);
+			},
+		}
+		self.inner.write_all(&packet)
+	}
+
+	fn make_deadline(&self, timeout: Duration) -> Self::Instant {
+		self.inner.make_deadline(timeout)
+	}
+
+	fn is_timeout_error(error: &Self::Error) -> bool {
+		T::is_timeout_error(error)
+	}
+
+	fn remaining_time(&self, deadline: &Self::Instant) -> Option<Duration> {
+		self.inner.remaining_time(deadline)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_random_faults_are_deterministic() {
+		let packet = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E];
+		let make = || RandomFaults::new(42).with_flip_bit(0.2).with_duplicate(0.2).with_split_read(0.5);
+		let mut a = make();
+		let mut b = make();
+		let mut faults = 0;
+		for _ in 0..100 {
+			let fault = a.packet_fault(&packet);
+			assert!(fault == b.packet_fault(&packet));
+			assert!(a.read_fault(10) == b.read_fault(10));
+			if let Some(fault) = fault {
+				faults += 1;
+				assert!(let PacketFault::FlipBit { .. } | PacketFault::Duplicate = fault);
+			}
+		}
+		assert!(faults > 10);
+		assert!(faults < 90);
+	}
+
+	#[test]
+	fn test_random_faults_stay_in_range() {
+		let mut schedule = RandomFaults::new(7).with_drop_byte(1.0).with_split_read(1.0);
+		for _ in 0..100 {
+			let_assert!(Some(PacketFault::DropByte(index)) = schedule.packet_fault(&[0; 10]));
+			assert!(index < 10);
+			let_assert!(Some(ReadFault::SplitRead(max)) = schedule.read_fault(5));
+			assert!((1..5).contains(&max));
+		}
+	}
+
+	/// A serial port that returns no data at the deadline instead of a timeout error.
+	struct EmptyReadPort {
+		data: Vec<u8>,
+		reads: usize,
+	}
+
+	impl SerialPort for EmptyReadPort {
+		type Error = std::io::Error;
+
+		type Instant = ();
+
+		fn baud_rate(&self) -> Result<u32, Self::Error> {
+			Ok(57600)
+		}
+
+		fn set_baud_rate(&mut self, _baud_rate: u32) -> Result<(), Self::Error> {
+			Ok(())
+		}
+
+		fn discard_input_buffer(&mut self) -> Result<(), Self::Error> {
+			Ok(())
+		}
+
+		fn read(&mut self, buffer: &mut [u8], _deadline: &Self::Instant) -> Result<usize, Self::Error> {
+			self.reads += 1;
+			let len = self.data.len().min(buffer.len());
+			buffer[..len].copy_from_slice(&self.data[..len]);
+			self.data.drain(..len);
+			Ok(len)
+		}
+
+		fn write_all(&mut self, _buffer: &[u8]) -> Result<(), Self::Error> {
+			Ok(())
+		}
+
+		fn make_deadline(&self, _timeout: Duration) -> Self::Instant {}
+
+		fn is_timeout_error(error: &Self::Error) -> bool {
+			error.kind() == std::io::ErrorKind::TimedOut
+		}
+	}
+
+	#[test]
+	fn test_delay_with_empty_reads() {
+		let inner = EmptyReadPort {
+			data: vec![1, 2, 3],
+			reads: 0,
+		};
+		let mut port = FaultySerialPort::new(inner, ScriptedFaults::new().then_read(ReadFault::Delay));
+		let mut buffer = [0; 8];
+
+		// The delayed read must stop when the wrapped port runs out of data, instead of spinning forever.
+		assert!(let Ok(0) = port.read(&mut buffer, &()));
+		assert!(port.inner().reads == 2);
+
+		// The data that was held back is delivered by the next read.
+		assert!(let Ok(3) = port.read(&mut buffer, &()));
+		assert!(buffer[..3] == [1, 2, 3]);
+	}
+}
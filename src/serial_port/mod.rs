@@ -5,6 +5,18 @@ use core::time::Duration;
 #[cfg(feature = "serial2")]
 pub mod serial2;
 
+#[cfg(feature = "std")]
+pub mod virtual_bus;
+
+#[cfg(feature = "std")]
+pub mod scripted;
+
+#[cfg(feature = "std")]
+pub mod replay;
+
+#[cfg(any(feature = "alloc", feature = "std"))]
+pub mod fault_injection;
+
 /// [`SerialPort`]s are used to communicate with the hardware by reading and writing data.
 ///
 /// The implementor of the trait must also configure the serial line to use 8 bits characters, 1 stop bit, no parity and no flow control.
@@ -35,4 +47,16 @@ pub trait SerialPort {
 
 	/// Check if an error indicates a timeout.
 	fn is_timeout_error(error: &Self::Error) -> bool;
+
+	/// Get the time remaining until a deadline expires.
+	///
+	/// Returns zero if the deadline already expired,
+	/// or `None` if the serial port can not measure time.
+	///
+	/// This is used to measure response latencies, for example by [`TimeoutModel::Adaptive`][crate::TimeoutModel::Adaptive].
+	/// The default implementation always returns `None`.
+	fn remaining_time(&self, deadline: &Self::Instant) -> Option<Duration> {
+		let _ = deadline;
+		None
+	}
 }
@@ -0,0 +1,230 @@
+//! Serial port that replays captured traffic.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::capture::{CapturedPacket, Framer};
+use crate::instructions::instruction_id;
+use crate::log::HexBytes;
+
+/// A serial port that replays one side of a captured session, to reproduce a bug deterministically.
+///
+/// Use [`Self::for_bus()`] to replay the recorded status packets into a [`Bus`][crate::Bus],
+/// or [`Self::for_device()`] to replay the recorded instructions into a [`Device`][crate::Device].
+/// The packets can come from any capture, for example one read with a [`CaptureReader`][crate::capture::CaptureReader].
+/// Packets are sorted into instructions and status packets by their instruction ID, so the recorded direction does not matter.
+///
+/// The packets of the other side are what the code under test is expected to write.
+/// A replayed packet is only delivered after all expected packets that precede it in the capture have been written.
+/// Written packets that differ from the capture are counted as mismatches, see [`Self::mismatches()`].
+/// If the code under test skips ahead, replayed packets that should have been read before the written packet are dropped.
+///
+/// By default, a replayed packet is delivered as soon as it is allowed,
+/// and a read fails immediately with a timeout error if no packet can be delivered.
+/// With [`Self::with_pacing()`], the recorded time between packets is reproduced as well.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use dynamixel2::{Bus, ReplaySerialPort};
+/// use dynamixel2::capture::CaptureReader;
+///
+/// let packets = CaptureReader::new(std::fs::File::open("capture.pcapng")?)?.collect::<Result<Vec<_>, _>>()?;
+/// let mut bus = Bus::with_buffers(ReplaySerialPort::for_bus(packets, 57600), vec![0; 128], vec![0; 128])?;
+/// let position = bus.read_u32(1, 132)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReplaySerialPort {
+	baud_rate: u32,
+	packets: Vec<CapturedPacket>,
+	replay_status: bool,
+	pacing: bool,
+	next_replay: usize,
+	next_expected: usize,
+	written: Framer,
+	read_buffer: VecDeque<u8>,
+	last_event: Option<(Instant, SystemTime)>,
+	mismatches: usize,
+}
+
+impl ReplaySerialPort {
+	/// Create a serial port that replays the status packets of a capture, for use with a [`Bus`][crate::Bus].
+	pub fn for_bus(packets: impl IntoIterator<Item = CapturedPacket>, baud_rate: u32) -> Self {
+		Self::new(packets, baud_rate, true)
+	}
+
+	/// Create a serial port that replays the instructions of a capture, for use with a [`Device`][crate::Device].
+	pub fn for_device(packets: impl IntoIterator<Item = CapturedPacket>, baud_rate: u32) -> Self {
+		Self::new(packets, baud_rate, false)
+	}
+
+	fn new(packets: impl IntoIterator<Item = CapturedPacket>, baud_rate: u32, replay_status: bool) -> Self {
+		let mut port = Self {
+			baud_rate,
+			packets: packets.into_iter().collect(),
+			replay_status,
+			pacing: false,
+			next_replay: 0,
+			next_expected: 0,
+			written: Framer::default(),
+			read_buffer: VecDeque::new(),
+			last_event: None,
+			mismatches: 0,
+		};
+		port.next_replay = port.find(0, true);
+		port.next_expected = port.find(0, false);
+		port
+	}
+
+	/// Enable or disable real-time pacing.
+	///
+	/// With pacing enabled, each replayed packet is delivered no sooner than the recorded time since the previous packet,
+	/// where the previous packet can be a replayed packet or a written packet.
+	pub fn with_pacing(mut self, pacing: bool) -> Self {
+		self.pacing = pacing;
+		self
+	}
+
+	/// Get the number of replayed packets that have not been delivered yet.
+	pub fn remaining_packets(&self) -> usize {
+		self.packets[self.next_replay..]
+			.iter()
+			.filter(|packet| self.is_replayed(packet))
+			.count()
+	}
+
+	/// Check if all replayed packets have been delivered and all expected packets have been written.
+	pub fn is_finished(&self) -> bool {
+		self.next_replay == self.packets.len() && self.next_expected == self.packets.len()
+	}
+
+	/// Get the number of written packets that did not match the capture.
+	///
+	/// This includes packets written after all expected packets have been written.
+	pub fn mismatches(&self) -> usize {
+		self.mismatches
+	}
+
+	/// Check if a packet is replayed, rather than expected to be written.
+	fn is_replayed(&self, packet: &CapturedPacket) -> bool {
+		let is_status = packet.data.get(7) == Some(&instruction_id::STATUS);
+		is_status == self.replay_status
+	}
+
+	/// Find the index of the first replayed or expected packet at or after `start`.
+	fn find(&self, start: usize, replayed: bool) -> usize {
+		self.packets[start.min(self.packets.len())..]
+			.iter()
+			.position(|packet| self.is_replayed(packet) == replayed)
+			.map_or(self.packets.len(), |i| start + i)
+	}
+
+	/// Get the time at which the next replayed packet may be delivered, if it is allowed at all.
+	fn next_ready(&self) -> Option<Instant> {
+		if self.next_replay == self.packets.len() || self.next_expected < self.next_replay {
+			return None;
+		}
+		let now = Instant::now();
+		match self.last_event {
+			Some((instant, timestamp)) if self.pacing => {
+				let delay = self.packets[self.next_replay]
+					.timestamp
+					.duration_since(timestamp)
+					.unwrap_or_default();
+				Some((instant + delay).max(now))
+			},
+			_ => Some(now),
+		}
+	}
+
+	/// Check a packet written by the code under test against the capture.
+	fn check_written(&mut self, data: Vec<u8>) {
+		let Some(expected) = self.packets.get(self.next_expected) else {
+			debug!("replay: unexpected packet written after the end of the capture: {:?}", HexBytes(&data));
+			self.mismatches += 1;
+			return;
+		};
+		if expected.data != data {
+			debug!(
+				"replay: written packet does not match the capture:\n  expected: {:?}\n  written:  {:?}",
+				HexBytes(&expected.data),
+				HexBytes(&data)
+			);
+			self.mismatches += 1;
+		}
+		self.last_event = Some((Instant::now(), expected.timestamp));
+
+		let skipped = self.next_expected;
+		self.next_expected = self.find(self.next_expected + 1, false);
+		if self.next_replay < skipped {
+			debug!("replay: dropping replayed packets that were not read before packet {}", skipped);
+			self.next_replay = self.find(skipped, true);
+		}
+	}
+}
+
+impl crate::SerialPort for ReplaySerialPort {
+	type Error = std::io::Error;
+
+	type Instant = std::time::Instant;
+
+	fn baud_rate(&self) -> Result<u32, Self::Error> {
+		Ok(self.baud_rate)
+	}
+
+	fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> {
+		self.baud_rate = baud_rate;
+		Ok(())
+	}
+
+	fn discard_input_buffer(&mut self) -> Result<(), Self::Error> {
+		self.read_buffer.clear();
+		Ok(())
+	}
+
+	fn read(&mut self, buffer: &mut [u8], deadline: &Self::Instant) -> Result<usize, Self::Error> {
+		while self.read_buffer.is_empty() && !buffer.is_empty() {
+			let Some(ready) = self.next_ready() else {
+				return Err(std::io::ErrorKind::TimedOut.into());
+			};
+			if ready > *deadline {
+				std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+				return Err(std::io::ErrorKind::TimedOut.into());
+			}
+			std::thread::sleep(ready.saturating_duration_since(Instant::now()));
+
+			let packet = &self.packets[self.next_replay];
+			self.read_buffer.extend(&packet.data);
+			self.last_event = Some((Instant::now(), packet.timestamp));
+			self.next_replay = self.find(self.next_replay + 1, true);
+		}
+
+		let len = buffer.len().min(self.read_buffer.len());
+		for (output, byte) in buffer.iter_mut().zip(self.read_buffer.drain(..len)) {
+			*output = byte;
+		}
+		Ok(len)
+	}
+
+	fn write_all(&mut self, buffer: &[u8]) -> Result<(), Self::Error> {
+		self.written.buffer.extend_from_slice(buffer);
+		while let Some(data) = self.written.next_packet() {
+			self.check_written(data);
+		}
+		Ok(())
+	}
+
+	fn make_deadline(&self, timeout: Duration) -> Self::Instant {
+		Instant::now() + timeout
+	}
+
+	fn is_timeout_error(error: &Self::Error) -> bool {
+		error.kind() == std::io::ErrorKind::TimedOut
+	}
+
+	fn remaining_time(&self, deadline: &Self::Instant) -> Option<Duration> {
+		Some(deadline.saturating_duration_since(Instant::now()))
+	}
+}
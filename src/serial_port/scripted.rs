@@ -0,0 +1,269 @@
+//! Serial port that checks written instructions against a script of expectations.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::instructions::instruction_id;
+use crate::{Instruction, Instructions, PacketParser};
+
+/// A serial port for unit tests that expects a scripted sequence of instructions, and answers them with scripted status packets.
+///
+/// Expectations are declared as typed [`Instructions`], and the status packets are encoded by the port itself,
+/// so tests do not need to deal with the raw bytes on the bus.
+///
+/// Every instruction packet written to the port is compared with the next expectation.
+/// If it does not match, the port panics with a diff between the expected and the received instruction.
+/// When the instruction matches, the replies of the expectation can be read from the port.
+/// A read with no more replies to deliver fails immediately with a timeout error, so tests do not have to wait for real timeouts.
+///
+/// When the port is dropped, it panics if not all expectations have been consumed.
+///
+/// # Example
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use dynamixel2::{Bus, Instructions, ScriptedSerialPort};
+///
+/// let serial_port = ScriptedSerialPort::new(57600)
+///   .expect(1, Instructions::Read { address: 132, length: 4 })
+///   .reply(1, 1234u32.to_le_bytes())
+///   .expect(1, Instructions::Write { address: 116, parameters: 2000u32.to_le_bytes().to_vec() })
+///   .reply(1, []);
+///
+/// let mut bus = Bus::with_buffers(serial_port, vec![0; 128], vec![0; 128])?;
+/// assert_eq!(bus.read_u32(1, 132)?.data, 1234);
+/// bus.write_u32(1, 116, 2000)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ScriptedSerialPort {
+	baud_rate: u32,
+	expectations: VecDeque<Expectation>,
+	matched: usize,
+	parser: PacketParser<Vec<u8>>,
+	read_buffer: VecDeque<u8>,
+}
+
+/// An expected instruction with the status packets to reply with.
+#[derive(Debug)]
+struct Expectation {
+	instruction: Instruction<Vec<u8>>,
+	replies: Vec<u8>,
+}
+
+impl ScriptedSerialPort {
+	/// Create a new scripted serial port without any expectations.
+	pub fn new(baud_rate: u32) -> Self {
+		Self {
+			baud_rate,
+			expectations: VecDeque::new(),
+			matched: 0,
+			parser: PacketParser::new(vec![0; 1024]),
+			read_buffer: VecDeque::new(),
+		}
+	}
+
+	/// Expect an instruction to be sent to the given motor ID.
+	///
+	/// Use [`crate::instructions::packet_id::BROADCAST`] as motor ID for broadcast instructions.
+	/// Without a call to [`Self::reply()`], the instruction is not answered.
+	pub fn expect(mut self, motor_id: u8, instruction: Instructions<Vec<u8>>) -> Self {
+		self.expectations.push_back(Expectation {
+			instruction: Instruction { id: motor_id, instruction },
+			replies: Vec::new(),
+		});
+		self
+	}
+
+	/// Reply to the last expected instruction with a status packet without errors.
+	///
+	/// This can be called multiple times to reply with multiple status packets, for example for a sync read.
+	///
+	/// # Panics
+	/// This function panics if no instruction has been expected yet.
+	pub fn reply(self, motor_id: u8, parameters: impl AsRef<[u8]>) -> Self {
+		self.reply_error(motor_id, 0, parameters)
+	}
+
+	/// Reply to the last expected instruction with a status packet with the given error field.
+	///
+	/// # Panics
+	/// This function panics if no instruction has been expected yet.
+	pub fn reply_error(mut self, motor_id: u8, error: u8, parameters: impl AsRef<[u8]>) -> Self {
+		let parameters = parameters.as_ref();
+		let expectation = self.expectations.back_mut().expect("reply() called before expect()");
+		let mut buffer = vec![0; 2 * (parameters.len() + 1) + 10];
+		let len = crate::encode_instruction(&mut buffer, motor_id, instruction_id::STATUS, parameters.len() + 1, |buffer| {
+			buffer[0] = error;
+			buffer[1..].copy_from_slice(parameters);
+		})
+		.unwrap();
+		expectation.replies.extend_from_slice(&buffer[..len]);
+		self
+	}
+
+	/// Reply to the last expected instruction with raw bytes, for example to simulate a corrupted status packet.
+	///
+	/// # Panics
+	/// This function panics if no instruction has been expected yet.
+	pub fn reply_raw(mut self, data: impl AsRef<[u8]>) -> Self {
+		let expectation = self.expectations.back_mut().expect("reply_raw() called before expect()");
+		expectation.replies.extend_from_slice(data.as_ref());
+		self
+	}
+
+	/// Get the number of expectations that have not been consumed yet.
+	pub fn remaining_expectations(&self) -> usize {
+		self.expectations.len()
+	}
+
+	/// Check the next complete instruction packet that has been written to the port.
+	///
+	/// Returns false if no complete packet has been written.
+	fn check_instruction(&mut self) -> bool {
+		let packet = match self.parser.poll_instruction_packet() {
+			Ok(Some(packet)) => packet,
+			Ok(None) => return false,
+			Err(e) => panic!("ScriptedSerialPort: received an invalid instruction packet: {e}"),
+		};
+		let received: Instruction<Vec<u8>> = match packet.try_into() {
+			Ok(x) => x,
+			Err(e) => panic!("ScriptedSerialPort: received an invalid instruction packet: {e}"),
+		};
+
+		let Some(expectation) = self.expectations.pop_front() else {
+			panic!(
+				"ScriptedSerialPort: received an unexpected instruction after all {} expectations were consumed:\n{:#?}",
+				self.matched, received
+			);
+		};
+		if expectation.instruction != received {
+			panic!(
+				"ScriptedSerialPort: instruction {} does not match the expectation:\n{}",
+				self.matched + 1,
+				diff(&format!("{:#?}", expectation.instruction), &format!("{:#?}", received)),
+			);
+		}
+		self.matched += 1;
+		self.read_buffer.extend(expectation.replies);
+		true
+	}
+}
+
+impl Drop for ScriptedSerialPort {
+	fn drop(&mut self) {
+		if std::thread::panicking() || self.expectations.is_empty() {
+			return;
+		}
+		let mut message = format!(
+			"ScriptedSerialPort: dropped with {} unconsumed expectation(s):",
+			self.expectations.len()
+		);
+		for expectation in &self.expectations {
+			write!(message, "\n{:?}", expectation.instruction).unwrap();
+		}
+		panic!("{message}");
+	}
+}
+
+/// Make a line based diff between the expected and the received text.
+///
+/// Lines only in the expected text are prefixed with `-`, lines only in the received text with `+`.
+fn diff(expected: &str, received: &str) -> String {
+	let expected: Vec<&str> = expected.lines().collect();
+	let received: Vec<&str> = received.lines().collect();
+
+	// Longest common subsequence table, filled from the back.
+	let mut lcs = vec![vec![0usize; received.len() + 1]; expected.len() + 1];
+	for i in (0..expected.len()).rev() {
+		for j in (0..received.len()).rev() {
+			lcs[i][j] = if expected[i] == received[j] {
+				lcs[i + 1][j + 1] + 1
+			} else {
+				lcs[i + 1][j].max(lcs[i][j + 1])
+			};
+		}
+	}
+
+	let mut output = String::new();
+	let (mut i, mut j) = (0, 0);
+	while i < expected.len() || j < received.len() {
+		if i < expected.len() && j < received.len() && expected[i] == received[j] {
+			writeln!(output, "  {}", expected[i]).unwrap();
+			i += 1;
+			j += 1;
+		} else if j == received.len() || (i < expected.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+			writeln!(output, "- {}", expected[i]).unwrap();
+			i += 1;
+		} else {
+			writeln!(output, "+ {}", received[j]).unwrap();
+			j += 1;
+		}
+	}
+	output
+}
+
+impl crate::SerialPort for ScriptedSerialPort {
+	type Error = std::io::Error;
+
+	type Instant = std::time::Instant;
+
+	fn baud_rate(&self) -> Result<u32, Self::Error> {
+		Ok(self.baud_rate)
+	}
+
+	fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> {
+		self.baud_rate = baud_rate;
+		Ok(())
+	}
+
+	fn discard_input_buffer(&mut self) -> Result<(), Self::Error> {
+		self.read_buffer.clear();
+		Ok(())
+	}
+
+	fn read(&mut self, buffer: &mut [u8], _deadline: &Self::Instant) -> Result<usize, Self::Error> {
+		if self.read_buffer.is_empty() && !buffer.is_empty() {
+			return Err(std::io::ErrorKind::TimedOut.into());
+		}
+		let len = buffer.len().min(self.read_buffer.len());
+		for (output, byte) in buffer.iter_mut().zip(self.read_buffer.drain(..len)) {
+			*output = byte;
+		}
+		Ok(len)
+	}
+
+	fn write_all(&mut self, mut buffer: &[u8]) -> Result<(), Self::Error> {
+		while !buffer.is_empty() {
+			let fed = self.parser.feed(buffer);
+			buffer = &buffer[fed..];
+			if !self.check_instruction() && fed == 0 {
+				panic!("ScriptedSerialPort: received an instruction packet that is too large");
+			}
+		}
+		while self.check_instruction() {}
+		Ok(())
+	}
+
+	fn make_deadline(&self, timeout: Duration) -> Self::Instant {
+		Instant::now() + timeout
+	}
+
+	fn is_timeout_error(error: &Self::Error) -> bool {
+		error.kind() == std::io::ErrorKind::TimedOut
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::assert;
+
+	#[test]
+	fn test_diff() {
+		let expected = "Read {\n    address: 132,\n    length: 4,\n}";
+		let received = "Read {\n    address: 128,\n    length: 4,\n}";
+		assert!(diff(expected, received) == "  Read {\n-     address: 132,\n+     address: 128,\n      length: 4,\n  }\n");
+	}
+}
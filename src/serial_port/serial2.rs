@@ -41,4 +41,8 @@ impl crate::SerialPort for serial2::SerialPort {
 	fn is_timeout_error(error: &Self::Error) -> bool {
 		error.kind() == std::io::ErrorKind::TimedOut
 	}
+
+	fn remaining_time(&self, deadline: &Self::Instant) -> Option<Duration> {
+		Some(deadline.saturating_duration_since(Instant::now()))
+	}
 }
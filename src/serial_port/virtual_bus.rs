@@ -0,0 +1,295 @@
+//! In-process multi-drop bus shared by any number of virtual serial ports.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::bus::message_transfer_time;
+
+/// A virtual half-duplex bus that connects any number of [`VirtualSerialPort`]s in the same process.
+///
+/// Every byte written to one port is received by all other ports on the bus,
+/// just like on a real multi-drop RS-485 or TTL bus.
+/// This allows a [`Bus`][crate::Bus] and any number of [`Device`][crate::Device]s to talk to each other without hardware.
+///
+/// The transfer of each byte takes as long as it would on a real serial line with the baud rate of the transmitting port.
+/// A port that receives data with a different baud rate than its own receives garbage instead.
+///
+/// If two ports transmit at the same time, the bus registers a collision,
+/// and all bytes that overlap in time are corrupted for the receivers.
+/// The number of collisions can be retrieved with [`Self::collision_count()`].
+///
+/// # Example
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use dynamixel2::{Bus, Device, VirtualBus};
+///
+/// let virtual_bus = VirtualBus::new();
+/// let bus = Bus::with_buffers(virtual_bus.connect(57600), vec![0; 128], vec![0; 128])?;
+/// let motor_1 = Device::with_buffers(virtual_bus.connect(57600), vec![0; 128], vec![0; 128])?;
+/// let motor_2 = Device::with_buffers(virtual_bus.connect(57600), vec![0; 128], vec![0; 128])?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VirtualBus {
+	shared: Arc<Shared>,
+}
+
+/// A serial port connected to a [`VirtualBus`].
+#[derive(Debug)]
+pub struct VirtualSerialPort {
+	shared: Arc<Shared>,
+	index: usize,
+}
+
+/// The state shared by all ports on a virtual bus.
+#[derive(Debug, Default)]
+struct Shared {
+	medium: Mutex<Medium>,
+	condvar: Condvar,
+}
+
+/// The transmission medium of a virtual bus.
+#[derive(Debug, Default)]
+struct Medium {
+	/// The ports connected to the bus, or `None` for ports that have been dropped.
+	endpoints: Vec<Option<Endpoint>>,
+
+	/// The number of collisions since the bus was created.
+	collisions: usize,
+}
+
+/// The state of a single port on the bus.
+#[derive(Debug)]
+struct Endpoint {
+	/// The baud rate of the port.
+	baud_rate: u32,
+
+	/// The bytes received by the port, sorted by arrival time.
+	inbox: VecDeque<ReceivedByte>,
+
+	/// The time span of the last transmission of the port.
+	transmission: Option<(Instant, Instant)>,
+}
+
+/// A byte in the inbox of a port.
+#[derive(Debug, Clone, Copy)]
+struct ReceivedByte {
+	/// The index of the port that sent the byte.
+	sender: usize,
+
+	/// The time at which the last bit of the byte arrives.
+	arrival: Instant,
+
+	/// The value of the byte.
+	value: u8,
+
+	/// If true, the byte was corrupted by a collision or a baud rate mismatch.
+	corrupted: bool,
+}
+
+impl ReceivedByte {
+	/// Get the value of the byte as it is received.
+	fn received_value(&self) -> u8 {
+		if self.corrupted {
+			!self.value
+		} else {
+			self.value
+		}
+	}
+}
+
+impl VirtualBus {
+	/// Create a new virtual bus without any ports.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Connect a new serial port to the bus.
+	pub fn connect(&self, baud_rate: u32) -> VirtualSerialPort {
+		let mut medium = self.shared.lock();
+		let index = medium.endpoints.len();
+		medium.endpoints.push(Some(Endpoint {
+			baud_rate,
+			inbox: VecDeque::new(),
+			transmission: None,
+		}));
+		VirtualSerialPort {
+			shared: self.shared.clone(),
+			index,
+		}
+	}
+
+	/// Get the number of collisions that occurred on the bus.
+	pub fn collision_count(&self) -> usize {
+		self.shared.lock().collisions
+	}
+}
+
+impl Shared {
+	/// Lock the medium.
+	///
+	/// A poisoned lock is recovered, since the medium is always left in a consistent state.
+	fn lock(&self) -> MutexGuard<'_, Medium> {
+		self.medium.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl VirtualSerialPort {
+	/// Get the [`VirtualBus`] that this port is connected to.
+	pub fn bus(&self) -> VirtualBus {
+		VirtualBus {
+			shared: self.shared.clone(),
+		}
+	}
+}
+
+impl Medium {
+	/// Get the state of a connected port.
+	fn endpoint(&mut self, index: usize) -> &mut Endpoint {
+		self.endpoints[index].as_mut().expect("virtual serial port is not connected")
+	}
+
+	/// Put the data written by a port on the bus.
+	///
+	/// Returns the time at which the transmission is complete.
+	fn transmit(&mut self, sender: usize, data: &[u8]) -> Instant {
+		let start = Instant::now();
+		let baud_rate = self.endpoint(sender).baud_rate;
+		let end = start + message_transfer_time(data.len() as u32, baud_rate);
+		let byte_time = message_transfer_time(1, baud_rate);
+
+		// Check for other transmissions that are still in progress.
+		let mut collision_end = None;
+		for (index, endpoint) in self.endpoints.iter().enumerate() {
+			let Some(endpoint) = endpoint else { continue };
+			if let Some((_, other_end)) = endpoint.transmission {
+				if index != sender && other_end > start {
+					collision_end = collision_end.max(Some((index, other_end)));
+				}
+			}
+		}
+
+		if let Some((other, _)) = collision_end {
+			self.collisions += 1;
+			debug!("collision on virtual bus between port {} and port {}", sender, other);
+			// Corrupt the bytes of the other transmissions that were still under way.
+			for endpoint in self.endpoints.iter_mut().flatten() {
+				for byte in &mut endpoint.inbox {
+					if byte.sender != sender && byte.arrival > start {
+						byte.corrupted = true;
+					}
+				}
+			}
+		}
+
+		for (index, endpoint) in self.endpoints.iter_mut().enumerate() {
+			let Some(endpoint) = endpoint else { continue };
+			if index == sender {
+				endpoint.transmission = Some((start, end));
+				continue;
+			}
+			for (i, &value) in data.iter().enumerate() {
+				let arrival = start + message_transfer_time(i as u32 + 1, baud_rate);
+				let collided = collision_end.is_some_and(|(_, other_end)| arrival - byte_time < other_end);
+				let byte = ReceivedByte {
+					sender,
+					arrival,
+					value,
+					corrupted: collided || endpoint.baud_rate != baud_rate,
+				};
+				let position = endpoint.inbox.partition_point(|other| other.arrival <= arrival);
+				endpoint.inbox.insert(position, byte);
+			}
+		}
+		end
+	}
+}
+
+impl Drop for VirtualSerialPort {
+	fn drop(&mut self) {
+		self.shared.lock().endpoints[self.index] = None;
+	}
+}
+
+impl crate::SerialPort for VirtualSerialPort {
+	type Error = std::io::Error;
+
+	type Instant = std::time::Instant;
+
+	fn baud_rate(&self) -> Result<u32, Self::Error> {
+		Ok(self.shared.lock().endpoint(self.index).baud_rate)
+	}
+
+	fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Self::Error> {
+		self.shared.lock().endpoint(self.index).baud_rate = baud_rate;
+		Ok(())
+	}
+
+	fn discard_input_buffer(&mut self) -> Result<(), Self::Error> {
+		// Only discard bytes that have already arrived: the rest is still on the wire.
+		let now = Instant::now();
+		let mut medium = self.shared.lock();
+		let inbox = &mut medium.endpoint(self.index).inbox;
+		let arrived = inbox.partition_point(|byte| byte.arrival <= now);
+		inbox.drain(..arrived);
+		Ok(())
+	}
+
+	fn read(&mut self, buffer: &mut [u8], deadline: &Self::Instant) -> Result<usize, Self::Error> {
+		let mut medium = self.shared.lock();
+		loop {
+			let now = Instant::now();
+			let inbox = &mut medium.endpoint(self.index).inbox;
+			let mut len = 0;
+			while len < buffer.len() {
+				match inbox.front() {
+					Some(byte) if byte.arrival <= now => {
+						buffer[len] = byte.received_value();
+						inbox.pop_front();
+						len += 1;
+					},
+					_ => break,
+				}
+			}
+			if len > 0 || buffer.is_empty() {
+				return Ok(len);
+			}
+			if now >= *deadline {
+				return Err(std::io::ErrorKind::TimedOut.into());
+			}
+			let wake = inbox.front().map_or(*deadline, |byte| byte.arrival.min(*deadline));
+			medium = self
+				.shared
+				.condvar
+				.wait_timeout(medium, wake - now)
+				.unwrap_or_else(|e| e.into_inner())
+				.0;
+		}
+	}
+
+	fn write_all(&mut self, buffer: &[u8]) -> Result<(), Self::Error> {
+		let end = self.shared.lock().transmit(self.index, buffer);
+		self.shared.condvar.notify_all();
+
+		// Like a half-duplex transceiver, wait until the last byte is on the wire.
+		let now = Instant::now();
+		if end > now {
+			std::thread::sleep(end - now);
+		}
+		Ok(())
+	}
+
+	fn make_deadline(&self, timeout: Duration) -> Self::Instant {
+		Instant::now() + timeout
+	}
+
+	fn is_timeout_error(error: &Self::Error) -> bool {
+		error.kind() == std::io::ErrorKind::TimedOut
+	}
+
+	fn remaining_time(&self, deadline: &Self::Instant) -> Option<Duration> {
+		Some(deadline.saturating_duration_since(Instant::now()))
+	}
+}
@@ -0,0 +1,183 @@
+//! Simulated motors for testing without hardware.
+//!
+//! A [`SimulatedMotor`] answers instructions on a [`Device`] like a real motor would,
+//! while a physics model of the motor advances on a simulated clock.
+//! Combined with an in-memory [`SerialPort`], this allows a complete control stack to be tested without any hardware.
+
+use core::time::Duration;
+
+use crate::{Device, DeviceServer, SerialPort, TransferError};
+
+pub mod xm430;
+pub use xm430::{Xm430, Xm430Parameters};
+
+/// The clock that advances the simulation of a [`SimulatedMotor`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimulationClock {
+	/// The simulation only advances when [`SimulatedMotor::advance()`] is called.
+	Manual,
+
+	/// The simulation advances by a fixed time step for every call to [`SimulatedMotor::serve_one()`].
+	///
+	/// This makes the simulation fully deterministic, regardless of the speed of the host.
+	FixedStep(Duration),
+
+	/// The simulation follows the wall clock, scaled by a speed factor.
+	///
+	/// The simulation advances by the scaled time since the previous call to [`SimulatedMotor::serve_one()`].
+	///
+	/// This requires the `std` feature to read the wall clock.
+	/// Without it, the simulation does not advance, like with a [manual clock][Self::Manual].
+	RealTime {
+		/// The speed of the simulation relative to the wall clock.
+		speed: f64,
+	},
+}
+
+/// A simulated motor, answering instructions received by a [`Device`].
+///
+/// The motor combines a [`DeviceServer`] with the [`Xm430`] control table and physics model.
+/// Changes to the ID register take effect after the status packet of the instruction has been sent, like on a real motor.
+/// Changes to the Baud Rate register are stored, but the simulated motor keeps using the baud rate of the [`Device`].
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use dynamixel2::Device;
+/// use dynamixel2::simulation::{SimulatedMotor, SimulationClock};
+/// use std::time::Duration;
+///
+/// let device = Device::open("/dev/ttyUSB0", 57600)?;
+/// let mut motor = SimulatedMotor::new(device, 1);
+/// motor.set_clock(SimulationClock::FixedStep(Duration::from_millis(1)));
+/// loop {
+///   // Timeouts are expected when the bus is idle.
+///   let _ = motor.serve_one(Duration::from_millis(1));
+/// }
+/// # }
+/// ```
+pub struct SimulatedMotor<ReadBuffer, WriteBuffer, T: SerialPort> {
+	server: DeviceServer<ReadBuffer, WriteBuffer, T, Xm430>,
+	clock: SimulationClock,
+	#[cfg(feature = "std")]
+	last_wall_time: Option<std::time::Instant>,
+}
+
+impl<ReadBuffer, WriteBuffer, T> core::fmt::Debug for SimulatedMotor<ReadBuffer, WriteBuffer, T>
+where
+	T: SerialPort + core::fmt::Debug,
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("SimulatedMotor")
+			.field("server", &self.server)
+			.field("clock", &self.clock)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T> SimulatedMotor<ReadBuffer, WriteBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// Create a new simulated XM430-W350 with the given ID.
+	///
+	/// The simulation uses a [manual clock][SimulationClock::Manual].
+	pub fn new(device: Device<ReadBuffer, WriteBuffer, T>, id: u8) -> Self {
+		let mut motor = Xm430::new();
+		motor.set_id(id);
+		Self::with_motor(device, motor)
+	}
+
+	/// Create a new simulated motor with the given control table and physical state.
+	///
+	/// The simulation uses a [manual clock][SimulationClock::Manual].
+	pub fn with_motor(device: Device<ReadBuffer, WriteBuffer, T>, motor: Xm430) -> Self {
+		Self {
+			server: DeviceServer::new(device, motor.id(), motor),
+			clock: SimulationClock::Manual,
+			#[cfg(feature = "std")]
+			last_wall_time: None,
+		}
+	}
+
+	/// Get the clock that advances the simulation.
+	pub fn clock(&self) -> SimulationClock {
+		self.clock
+	}
+
+	/// Set the clock that advances the simulation.
+	pub fn set_clock(&mut self, clock: SimulationClock) {
+		self.clock = clock;
+		#[cfg(feature = "std")]
+		{
+			self.last_wall_time = None;
+		}
+	}
+
+	/// Get a reference to the simulated motor.
+	pub fn motor(&self) -> &Xm430 {
+		self.server.control_table()
+	}
+
+	/// Get a mutable reference to the simulated motor.
+	///
+	/// This can be used to apply external torques or inject hardware errors.
+	pub fn motor_mut(&mut self) -> &mut Xm430 {
+		self.server.control_table_mut()
+	}
+
+	/// Get a reference to the underlying [`DeviceServer`].
+	pub fn server(&self) -> &DeviceServer<ReadBuffer, WriteBuffer, T, Xm430> {
+		&self.server
+	}
+
+	/// Get a mutable reference to the underlying [`DeviceServer`].
+	pub fn server_mut(&mut self) -> &mut DeviceServer<ReadBuffer, WriteBuffer, T, Xm430> {
+		&mut self.server
+	}
+
+	/// Consume the simulated motor to get back the [`Device`] and the simulated motor.
+	pub fn into_parts(self) -> (Device<ReadBuffer, WriteBuffer, T>, Xm430) {
+		self.server.into_parts()
+	}
+
+	/// Get the simulated time since the start of the simulation.
+	pub fn time(&self) -> Duration {
+		self.motor().time()
+	}
+
+	/// Advance the simulation by the given duration.
+	pub fn advance(&mut self, duration: Duration) {
+		self.motor_mut().step(duration);
+	}
+
+	/// Wait for a single instruction and handle it, then advance the simulation according to the clock.
+	///
+	/// The simulation also advances if no instruction is received before the timeout.
+	pub fn serve_one(&mut self, timeout: Duration) -> Result<(), TransferError<T::Error>> {
+		let result = self.server.serve_one(timeout);
+
+		let id = self.motor().id();
+		if id != self.server.id() {
+			self.server.set_id(id);
+		}
+
+		match self.clock {
+			SimulationClock::Manual => (),
+			SimulationClock::FixedStep(step) => self.advance(step),
+			#[cfg(feature = "std")]
+			SimulationClock::RealTime { speed } => {
+				let now = std::time::Instant::now();
+				if let Some(last) = self.last_wall_time.replace(now) {
+					self.advance((now - last).mul_f64(speed));
+				}
+			},
+			#[cfg(not(feature = "std"))]
+			SimulationClock::RealTime { .. } => (),
+		}
+
+		result
+	}
+}
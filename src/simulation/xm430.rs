@@ -0,0 +1,1287 @@
+//! Simulated XM430 servo motor.
+
+use core::f64::consts::PI;
+use core::time::Duration;
+
+use crate::{Clear, ControlTable, FactoryReset, StatusError, StatusReturnLevel};
+
+/// The addresses of the registers in the control table of an XM430 motor.
+///
+/// See the [e-manual](https://emanual.robotis.com/docs/en/dxl/x/xm430-w350/#control-table) for a description of each register.
+#[allow(missing_docs)]
+pub mod address {
+	pub const MODEL_NUMBER: u16 = 0;
+	pub const MODEL_INFORMATION: u16 = 2;
+	pub const FIRMWARE_VERSION: u16 = 6;
+	pub const ID: u16 = 7;
+	pub const BAUD_RATE: u16 = 8;
+	pub const RETURN_DELAY_TIME: u16 = 9;
+	pub const DRIVE_MODE: u16 = 10;
+	pub const OPERATING_MODE: u16 = 11;
+	pub const SECONDARY_ID: u16 = 12;
+	pub const PROTOCOL_TYPE: u16 = 13;
+	pub const HOMING_OFFSET: u16 = 20;
+	pub const MOVING_THRESHOLD: u16 = 24;
+	pub const TEMPERATURE_LIMIT: u16 = 31;
+	pub const MAX_VOLTAGE_LIMIT: u16 = 32;
+	pub const MIN_VOLTAGE_LIMIT: u16 = 34;
+	pub const PWM_LIMIT: u16 = 36;
+	pub const CURRENT_LIMIT: u16 = 38;
+	pub const VELOCITY_LIMIT: u16 = 44;
+	pub const MAX_POSITION_LIMIT: u16 = 48;
+	pub const MIN_POSITION_LIMIT: u16 = 52;
+	pub const STARTUP_CONFIGURATION: u16 = 60;
+	pub const SHUTDOWN: u16 = 63;
+	pub const TORQUE_ENABLE: u16 = 64;
+	pub const LED: u16 = 65;
+	pub const STATUS_RETURN_LEVEL: u16 = 68;
+	pub const REGISTERED_INSTRUCTION: u16 = 69;
+	pub const HARDWARE_ERROR_STATUS: u16 = 70;
+	pub const VELOCITY_I_GAIN: u16 = 76;
+	pub const VELOCITY_P_GAIN: u16 = 78;
+	pub const POSITION_D_GAIN: u16 = 80;
+	pub const POSITION_I_GAIN: u16 = 82;
+	pub const POSITION_P_GAIN: u16 = 84;
+	pub const FEEDFORWARD_2ND_GAIN: u16 = 88;
+	pub const FEEDFORWARD_1ST_GAIN: u16 = 90;
+	pub const BUS_WATCHDOG: u16 = 98;
+	pub const GOAL_PWM: u16 = 100;
+	pub const GOAL_CURRENT: u16 = 102;
+	pub const GOAL_VELOCITY: u16 = 104;
+	pub const PROFILE_ACCELERATION: u16 = 108;
+	pub const PROFILE_VELOCITY: u16 = 112;
+	pub const GOAL_POSITION: u16 = 116;
+	pub const REALTIME_TICK: u16 = 120;
+	pub const MOVING: u16 = 122;
+	pub const MOVING_STATUS: u16 = 123;
+	pub const PRESENT_PWM: u16 = 124;
+	pub const PRESENT_CURRENT: u16 = 126;
+	pub const PRESENT_VELOCITY: u16 = 128;
+	pub const PRESENT_POSITION: u16 = 132;
+	pub const VELOCITY_TRAJECTORY: u16 = 136;
+	pub const POSITION_TRAJECTORY: u16 = 140;
+	pub const PRESENT_INPUT_VOLTAGE: u16 = 144;
+	pub const PRESENT_TEMPERATURE: u16 = 146;
+	pub const BACKUP_READY: u16 = 147;
+}
+
+/// The names of the registers in the control table of an XM430 motor, for use with [`PrettyPacket`][crate::PrettyPacket].
+pub const REGISTER_NAMES: &[(u16, &str)] = &[
+	(address::MODEL_NUMBER, "Model Number"),
+	(address::MODEL_INFORMATION, "Model Information"),
+	(address::FIRMWARE_VERSION, "Firmware Version"),
+	(address::ID, "ID"),
+	(address::BAUD_RATE, "Baud Rate"),
+	(address::RETURN_DELAY_TIME, "Return Delay Time"),
+	(address::DRIVE_MODE, "Drive Mode"),
+	(address::OPERATING_MODE, "Operating Mode"),
+	(address::SECONDARY_ID, "Secondary ID"),
+	(address::PROTOCOL_TYPE, "Protocol Type"),
+	(address::HOMING_OFFSET, "Homing Offset"),
+	(address::MOVING_THRESHOLD, "Moving Threshold"),
+	(address::TEMPERATURE_LIMIT, "Temperature Limit"),
+	(address::MAX_VOLTAGE_LIMIT, "Max Voltage Limit"),
+	(address::MIN_VOLTAGE_LIMIT, "Min Voltage Limit"),
+	(address::PWM_LIMIT, "PWM Limit"),
+	(address::CURRENT_LIMIT, "Current Limit"),
+	(address::VELOCITY_LIMIT, "Velocity Limit"),
+	(address::MAX_POSITION_LIMIT, "Max Position Limit"),
+	(address::MIN_POSITION_LIMIT, "Min Position Limit"),
+	(address::STARTUP_CONFIGURATION, "Startup Configuration"),
+	(address::SHUTDOWN, "Shutdown"),
+	(address::TORQUE_ENABLE, "Torque Enable"),
+	(address::LED, "LED"),
+	(address::STATUS_RETURN_LEVEL, "Status Return Level"),
+	(address::REGISTERED_INSTRUCTION, "Registered Instruction"),
+	(address::HARDWARE_ERROR_STATUS, "Hardware Error Status"),
+	(address::VELOCITY_I_GAIN, "Velocity I Gain"),
+	(address::VELOCITY_P_GAIN, "Velocity P Gain"),
+	(address::POSITION_D_GAIN, "Position D Gain"),
+	(address::POSITION_I_GAIN, "Position I Gain"),
+	(address::POSITION_P_GAIN, "Position P Gain"),
+	(address::FEEDFORWARD_2ND_GAIN, "Feedforward 2nd Gain"),
+	(address::FEEDFORWARD_1ST_GAIN, "Feedforward 1st Gain"),
+	(address::BUS_WATCHDOG, "Bus Watchdog"),
+	(address::GOAL_PWM, "Goal PWM"),
+	(address::GOAL_CURRENT, "Goal Current"),
+	(address::GOAL_VELOCITY, "Goal Velocity"),
+	(address::PROFILE_ACCELERATION, "Profile Acceleration"),
+	(address::PROFILE_VELOCITY, "Profile Velocity"),
+	(address::GOAL_POSITION, "Goal Position"),
+	(address::REALTIME_TICK, "Realtime Tick"),
+	(address::MOVING, "Moving"),
+	(address::MOVING_STATUS, "Moving Status"),
+	(address::PRESENT_PWM, "Present PWM"),
+	(address::PRESENT_CURRENT, "Present Current"),
+	(address::PRESENT_VELOCITY, "Present Velocity"),
+	(address::PRESENT_POSITION, "Present Position"),
+	(address::VELOCITY_TRAJECTORY, "Velocity Trajectory"),
+	(address::POSITION_TRAJECTORY, "Position Trajectory"),
+	(address::PRESENT_INPUT_VOLTAGE, "Present Input Voltage"),
+	(address::PRESENT_TEMPERATURE, "Present Temperature"),
+	(address::BACKUP_READY, "Backup Ready"),
+];
+
+/// The values of the Operating Mode register.
+#[allow(missing_docs)]
+pub mod operating_mode {
+	pub const CURRENT: u8 = 0;
+	pub const VELOCITY: u8 = 1;
+	pub const POSITION: u8 = 3;
+	pub const EXTENDED_POSITION: u8 = 4;
+	pub const CURRENT_BASED_POSITION: u8 = 5;
+	pub const PWM: u8 = 16;
+}
+
+/// The bits of the Hardware Error Status register.
+#[allow(missing_docs)]
+pub mod hardware_error {
+	pub const INPUT_VOLTAGE: u8 = 0x01;
+	pub const OVERHEATING: u8 = 0x04;
+	pub const MOTOR_ENCODER: u8 = 0x08;
+	pub const ELECTRICAL_SHOCK: u8 = 0x10;
+	pub const OVERLOAD: u8 = 0x20;
+}
+
+/// The size of the simulated control table, up to and including the Backup Ready register.
+const CONTROL_TABLE_SIZE: usize = 148;
+
+/// The model number of the XM430-W350.
+const MODEL_XM430_W350: u16 = 1020;
+
+/// The model number of the XM430-W210.
+const MODEL_XM430_W210: u16 = 1030;
+
+/// The reported firmware version.
+const FIRMWARE_VERSION: u8 = 48;
+
+/// The value of the PWM registers that corresponds to 100% duty cycle.
+const PWM_FULL_SCALE: f64 = 885.0;
+
+/// One unit of the position registers in radians.
+const POSITION_UNIT: f64 = 2.0 * PI / 4096.0;
+
+/// One unit of the velocity registers (0.229 rpm) in radians per second.
+const VELOCITY_UNIT: f64 = 0.229 * 2.0 * PI / 60.0;
+
+/// One unit of the profile acceleration register (214.577 rev/min²) in radians per second squared.
+const ACCELERATION_UNIT: f64 = 214.577 * 2.0 * PI / 3600.0;
+
+/// One unit of the current registers in ampère.
+const CURRENT_UNIT: f64 = 0.00269;
+
+/// One unit of the voltage registers in volt.
+const VOLTAGE_UNIT: f64 = 0.1;
+
+/// One unit of the Return Delay Time register.
+const RETURN_DELAY_UNIT: Duration = Duration::from_micros(2);
+
+/// The largest time step of the physics simulation in seconds.
+///
+/// Longer steps are split into multiple steps of at most this length.
+const MAX_SUBSTEP: f64 = 0.001;
+
+/// The velocity loop gain in V/(rad/s) for the default Velocity P Gain.
+const VELOCITY_P_SCALE: f64 = 10.0;
+
+/// The velocity loop integral gain in V/rad for the default Velocity I Gain.
+const VELOCITY_I_SCALE: f64 = 50.0;
+
+/// The position loop gain in 1/s for the default Position P Gain.
+const POSITION_P_SCALE: f64 = 20.0;
+
+/// The fraction of the stall current above which the motor counts as overloaded.
+const OVERLOAD_CURRENT: f64 = 0.8;
+
+/// The time in seconds that the motor must be overloaded before the overload error is raised.
+const OVERLOAD_TIME: f64 = 1.0;
+
+/// The physical properties of a simulated XM430 motor.
+///
+/// All values are in SI units and relate to the output shaft.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Xm430Parameters {
+	/// The model number reported by the motor.
+	pub model_number: u16,
+
+	/// The voltage at which the stall torque, stall current and no-load speed are specified, in volt.
+	pub nominal_voltage: f64,
+
+	/// The stall torque at the nominal voltage, in newton metre.
+	pub stall_torque: f64,
+
+	/// The stall current at the nominal voltage, in ampère.
+	pub stall_current: f64,
+
+	/// The no-load speed at the nominal voltage, in radians per second.
+	pub no_load_speed: f64,
+
+	/// The moment of inertia of the rotor and the attached load, in kilogram square metre.
+	pub inertia: f64,
+
+	/// The viscous friction, in newton metre per radian per second.
+	pub friction: f64,
+
+	/// The thermal resistance between the motor and the environment, in kelvin per watt.
+	pub thermal_resistance: f64,
+
+	/// The heat capacity of the motor, in joule per kelvin.
+	pub thermal_capacity: f64,
+
+	/// The temperature of the environment, in degrees Celsius.
+	pub ambient_temperature: f64,
+}
+
+impl Xm430Parameters {
+	/// The parameters of an XM430-W350 at 12 V.
+	pub const W350: Self = Self {
+		model_number: MODEL_XM430_W350,
+		nominal_voltage: 12.0,
+		stall_torque: 4.1,
+		stall_current: 2.3,
+		no_load_speed: 46.0 * 2.0 * PI / 60.0,
+		inertia: 0.01,
+		friction: 0.02,
+		thermal_resistance: 4.0,
+		thermal_capacity: 40.0,
+		ambient_temperature: 25.0,
+	};
+
+	/// The parameters of an XM430-W210 at 12 V.
+	pub const W210: Self = Self {
+		model_number: MODEL_XM430_W210,
+		stall_torque: 3.0,
+		no_load_speed: 77.0 * 2.0 * PI / 60.0,
+		..Self::W350
+	};
+
+	/// The winding resistance in ohm.
+	fn resistance(&self) -> f64 {
+		self.nominal_voltage / self.stall_current
+	}
+
+	/// The back-EMF constant in volt per radian per second.
+	fn back_emf_constant(&self) -> f64 {
+		self.nominal_voltage / self.no_load_speed
+	}
+
+	/// The torque constant in newton metre per ampère.
+	fn torque_constant(&self) -> f64 {
+		self.stall_torque / self.stall_current
+	}
+}
+
+impl Default for Xm430Parameters {
+	fn default() -> Self {
+		Self::W350
+	}
+}
+
+/// How a register can be accessed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Access {
+	/// The register can not be written.
+	ReadOnly,
+
+	/// The register can only be written while the torque is disabled.
+	Eeprom,
+
+	/// The register can always be written.
+	Ram,
+}
+
+/// A register in the control table.
+#[derive(Debug, Copy, Clone)]
+struct Register {
+	address: u16,
+	size: u8,
+	access: Access,
+	signed: bool,
+	default: i64,
+}
+
+impl Register {
+	const fn new(address: u16, size: u8, access: Access, default: i64) -> Self {
+		Self {
+			address,
+			size,
+			access,
+			signed: false,
+			default,
+		}
+	}
+
+	const fn signed(self) -> Self {
+		Self { signed: true, ..self }
+	}
+
+	/// Check if the register overlaps with a range of addresses.
+	fn overlaps(&self, address: u16, len: usize) -> bool {
+		let start = usize::from(self.address);
+		let end = start + usize::from(self.size);
+		start < usize::from(address) + len && usize::from(address) < end
+	}
+}
+
+mod reg {
+	use super::address;
+	use super::Access::{Eeprom, Ram, ReadOnly};
+	use super::Register;
+
+	pub const MODEL_NUMBER: Register = Register::new(address::MODEL_NUMBER, 2, ReadOnly, 0);
+	pub const MODEL_INFORMATION: Register = Register::new(address::MODEL_INFORMATION, 4, ReadOnly, 0);
+	pub const FIRMWARE_VERSION: Register = Register::new(address::FIRMWARE_VERSION, 1, ReadOnly, super::FIRMWARE_VERSION as i64);
+	pub const ID: Register = Register::new(address::ID, 1, Eeprom, 1);
+	pub const BAUD_RATE: Register = Register::new(address::BAUD_RATE, 1, Eeprom, 1);
+	pub const RETURN_DELAY_TIME: Register = Register::new(address::RETURN_DELAY_TIME, 1, Eeprom, 250);
+	pub const DRIVE_MODE: Register = Register::new(address::DRIVE_MODE, 1, Eeprom, 0);
+	pub const OPERATING_MODE: Register = Register::new(address::OPERATING_MODE, 1, Eeprom, 3);
+	pub const SECONDARY_ID: Register = Register::new(address::SECONDARY_ID, 1, Eeprom, 255);
+	pub const PROTOCOL_TYPE: Register = Register::new(address::PROTOCOL_TYPE, 1, Eeprom, 2);
+	pub const HOMING_OFFSET: Register = Register::new(address::HOMING_OFFSET, 4, Eeprom, 0).signed();
+	pub const MOVING_THRESHOLD: Register = Register::new(address::MOVING_THRESHOLD, 4, Eeprom, 10);
+	pub const TEMPERATURE_LIMIT: Register = Register::new(address::TEMPERATURE_LIMIT, 1, Eeprom, 80);
+	pub const MAX_VOLTAGE_LIMIT: Register = Register::new(address::MAX_VOLTAGE_LIMIT, 2, Eeprom, 160);
+	pub const MIN_VOLTAGE_LIMIT: Register = Register::new(address::MIN_VOLTAGE_LIMIT, 2, Eeprom, 95);
+	pub const PWM_LIMIT: Register = Register::new(address::PWM_LIMIT, 2, Eeprom, 885);
+	pub const CURRENT_LIMIT: Register = Register::new(address::CURRENT_LIMIT, 2, Eeprom, 1193);
+	pub const VELOCITY_LIMIT: Register = Register::new(address::VELOCITY_LIMIT, 4, Eeprom, 200);
+	pub const MAX_POSITION_LIMIT: Register = Register::new(address::MAX_POSITION_LIMIT, 4, Eeprom, 4095);
+	pub const MIN_POSITION_LIMIT: Register = Register::new(address::MIN_POSITION_LIMIT, 4, Eeprom, 0);
+	pub const STARTUP_CONFIGURATION: Register = Register::new(address::STARTUP_CONFIGURATION, 1, Eeprom, 0);
+	pub const SHUTDOWN: Register = Register::new(address::SHUTDOWN, 1, Eeprom, 52);
+	pub const TORQUE_ENABLE: Register = Register::new(address::TORQUE_ENABLE, 1, Ram, 0);
+	pub const LED: Register = Register::new(address::LED, 1, Ram, 0);
+	pub const STATUS_RETURN_LEVEL: Register = Register::new(address::STATUS_RETURN_LEVEL, 1, Ram, 2);
+	pub const REGISTERED_INSTRUCTION: Register = Register::new(address::REGISTERED_INSTRUCTION, 1, ReadOnly, 0);
+	pub const HARDWARE_ERROR_STATUS: Register = Register::new(address::HARDWARE_ERROR_STATUS, 1, ReadOnly, 0);
+	pub const VELOCITY_I_GAIN: Register = Register::new(address::VELOCITY_I_GAIN, 2, Ram, 1920);
+	pub const VELOCITY_P_GAIN: Register = Register::new(address::VELOCITY_P_GAIN, 2, Ram, 100);
+	pub const POSITION_D_GAIN: Register = Register::new(address::POSITION_D_GAIN, 2, Ram, 0);
+	pub const POSITION_I_GAIN: Register = Register::new(address::POSITION_I_GAIN, 2, Ram, 0);
+	pub const POSITION_P_GAIN: Register = Register::new(address::POSITION_P_GAIN, 2, Ram, 800);
+	pub const FEEDFORWARD_2ND_GAIN: Register = Register::new(address::FEEDFORWARD_2ND_GAIN, 2, Ram, 0);
+	pub const FEEDFORWARD_1ST_GAIN: Register = Register::new(address::FEEDFORWARD_1ST_GAIN, 2, Ram, 0);
+	pub const BUS_WATCHDOG: Register = Register::new(address::BUS_WATCHDOG, 1, Ram, 0);
+	pub const GOAL_PWM: Register = Register::new(address::GOAL_PWM, 2, Ram, 885).signed();
+	pub const GOAL_CURRENT: Register = Register::new(address::GOAL_CURRENT, 2, Ram, 1193).signed();
+	pub const GOAL_VELOCITY: Register = Register::new(address::GOAL_VELOCITY, 4, Ram, 0).signed();
+	pub const PROFILE_ACCELERATION: Register = Register::new(address::PROFILE_ACCELERATION, 4, Ram, 0);
+	pub const PROFILE_VELOCITY: Register = Register::new(address::PROFILE_VELOCITY, 4, Ram, 0);
+	pub const GOAL_POSITION: Register = Register::new(address::GOAL_POSITION, 4, Ram, 0).signed();
+	pub const REALTIME_TICK: Register = Register::new(address::REALTIME_TICK, 2, ReadOnly, 0);
+	pub const MOVING: Register = Register::new(address::MOVING, 1, ReadOnly, 0);
+	pub const MOVING_STATUS: Register = Register::new(address::MOVING_STATUS, 1, ReadOnly, 0);
+	pub const PRESENT_PWM: Register = Register::new(address::PRESENT_PWM, 2, ReadOnly, 0).signed();
+	pub const PRESENT_CURRENT: Register = Register::new(address::PRESENT_CURRENT, 2, ReadOnly, 0).signed();
+	pub const PRESENT_VELOCITY: Register = Register::new(address::PRESENT_VELOCITY, 4, ReadOnly, 0).signed();
+	pub const PRESENT_POSITION: Register = Register::new(address::PRESENT_POSITION, 4, ReadOnly, 0).signed();
+	pub const VELOCITY_TRAJECTORY: Register = Register::new(address::VELOCITY_TRAJECTORY, 4, ReadOnly, 0).signed();
+	pub const POSITION_TRAJECTORY: Register = Register::new(address::POSITION_TRAJECTORY, 4, ReadOnly, 0).signed();
+	pub const PRESENT_INPUT_VOLTAGE: Register = Register::new(address::PRESENT_INPUT_VOLTAGE, 2, ReadOnly, 0);
+	pub const PRESENT_TEMPERATURE: Register = Register::new(address::PRESENT_TEMPERATURE, 1, ReadOnly, 0);
+	pub const BACKUP_READY: Register = Register::new(address::BACKUP_READY, 1, ReadOnly, 0);
+
+	/// All registers, sorted by address.
+	pub const ALL: [Register; 53] = [
+		MODEL_NUMBER,
+		MODEL_INFORMATION,
+		FIRMWARE_VERSION,
+		ID,
+		BAUD_RATE,
+		RETURN_DELAY_TIME,
+		DRIVE_MODE,
+		OPERATING_MODE,
+		SECONDARY_ID,
+		PROTOCOL_TYPE,
+		HOMING_OFFSET,
+		MOVING_THRESHOLD,
+		TEMPERATURE_LIMIT,
+		MAX_VOLTAGE_LIMIT,
+		MIN_VOLTAGE_LIMIT,
+		PWM_LIMIT,
+		CURRENT_LIMIT,
+		VELOCITY_LIMIT,
+		MAX_POSITION_LIMIT,
+		MIN_POSITION_LIMIT,
+		STARTUP_CONFIGURATION,
+		SHUTDOWN,
+		TORQUE_ENABLE,
+		LED,
+		STATUS_RETURN_LEVEL,
+		REGISTERED_INSTRUCTION,
+		HARDWARE_ERROR_STATUS,
+		VELOCITY_I_GAIN,
+		VELOCITY_P_GAIN,
+		POSITION_D_GAIN,
+		POSITION_I_GAIN,
+		POSITION_P_GAIN,
+		FEEDFORWARD_2ND_GAIN,
+		FEEDFORWARD_1ST_GAIN,
+		BUS_WATCHDOG,
+		GOAL_PWM,
+		GOAL_CURRENT,
+		GOAL_VELOCITY,
+		PROFILE_ACCELERATION,
+		PROFILE_VELOCITY,
+		GOAL_POSITION,
+		REALTIME_TICK,
+		MOVING,
+		MOVING_STATUS,
+		PRESENT_PWM,
+		PRESENT_CURRENT,
+		PRESENT_VELOCITY,
+		PRESENT_POSITION,
+		VELOCITY_TRAJECTORY,
+		POSITION_TRAJECTORY,
+		PRESENT_INPUT_VOLTAGE,
+		PRESENT_TEMPERATURE,
+		BACKUP_READY,
+	];
+}
+
+/// A simulated XM430 servo motor.
+///
+/// The simulation implements the control table of the XM430 series as a [`ControlTable`],
+/// so it can be served with a [`DeviceServer`][crate::DeviceServer] or wrapped in a [`SimulatedMotor`][super::SimulatedMotor].
+///
+/// The physics model is a DC motor with a linear torque-speed curve, driving an inertial load with viscous friction.
+/// The current, velocity, position, extended position, current-based position and PWM operating modes are supported,
+/// including the velocity based profile generated from the Profile Velocity and Profile Acceleration registers.
+/// The controller gains scale the loop gains of the simulation relative to their default values,
+/// but they do not match the exact control law of the real firmware.
+///
+/// The winding heats up the motor according to a first order thermal model.
+/// The overheating, overload and input voltage errors are raised like on a real motor,
+/// and disable the torque if the corresponding bit is set in the Shutdown register.
+/// Other hardware errors can be injected with [`Self::inject_hardware_error()`].
+///
+/// The simulation only advances when [`Self::step()`] is called.
+/// Reads of the control table report the state after the last step.
+#[derive(Debug, Clone)]
+pub struct Xm430 {
+	/// The raw control table.
+	registers: [u8; CONTROL_TABLE_SIZE],
+
+	/// The physical properties of the motor.
+	parameters: Xm430Parameters,
+
+	/// The simulated time since the start of the simulation.
+	time: Duration,
+
+	/// The supply voltage in volt.
+	supply_voltage: f64,
+
+	/// The external torque applied to the output shaft in newton metre.
+	external_torque: f64,
+
+	/// The position of the output shaft in radians, without homing offset.
+	position: f64,
+
+	/// The velocity of the output shaft in radians per second.
+	velocity: f64,
+
+	/// The motor current in ampère.
+	current: f64,
+
+	/// The applied PWM duty cycle, from -1 to 1.
+	pwm: f64,
+
+	/// The temperature of the motor in degrees Celsius.
+	temperature: f64,
+
+	/// The position of the profile generator in radians, without homing offset.
+	trajectory_position: f64,
+
+	/// The velocity of the profile generator in radians per second.
+	trajectory_velocity: f64,
+
+	/// The integral of the velocity error in radians.
+	velocity_integral: f64,
+
+	/// The time in seconds that the motor has been overloaded.
+	overload_time: f64,
+}
+
+impl Default for Xm430 {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Xm430 {
+	/// Create a new simulated XM430-W350 with ID 1.
+	pub fn new() -> Self {
+		Self::with_parameters(Xm430Parameters::W350)
+	}
+
+	/// Create a new simulated motor with the given physical properties and ID 1.
+	pub fn with_parameters(parameters: Xm430Parameters) -> Self {
+		let mut motor = Self {
+			registers: [0; CONTROL_TABLE_SIZE],
+			parameters,
+			time: Duration::ZERO,
+			supply_voltage: parameters.nominal_voltage,
+			external_torque: 0.0,
+			position: 0.0,
+			velocity: 0.0,
+			current: 0.0,
+			pwm: 0.0,
+			temperature: parameters.ambient_temperature,
+			trajectory_position: 0.0,
+			trajectory_velocity: 0.0,
+			velocity_integral: 0.0,
+			overload_time: 0.0,
+		};
+		for register in reg::ALL {
+			motor.set(register, register.default);
+		}
+		motor.set(reg::MODEL_NUMBER, parameters.model_number.into());
+		motor.reset_goal_position();
+		motor.update_registers();
+		motor
+	}
+
+	/// Get the physical properties of the motor.
+	pub fn parameters(&self) -> &Xm430Parameters {
+		&self.parameters
+	}
+
+	/// Get the ID stored in the control table.
+	pub fn id(&self) -> u8 {
+		self.get(reg::ID) as u8
+	}
+
+	/// Change the ID stored in the control table.
+	///
+	/// # Panics
+	/// This function panics if the ID is not a valid motor ID (0 to 252).
+	pub fn set_id(&mut self, id: u8) {
+		assert!(id <= 252, "invalid motor ID: {id}");
+		self.set(reg::ID, id.into());
+	}
+
+	/// Get the simulated time since the start of the simulation.
+	pub fn time(&self) -> Duration {
+		self.time
+	}
+
+	/// Get the position of the output shaft in radians.
+	///
+	/// This does not include the Homing Offset.
+	pub fn position(&self) -> f64 {
+		self.position
+	}
+
+	/// Move the output shaft to a position in radians, and stop it.
+	///
+	/// If the torque is enabled, the motor will try to move back to its goal.
+	pub fn set_position(&mut self, position: f64) {
+		self.position = position;
+		self.velocity = 0.0;
+		self.trajectory_position = position;
+		self.trajectory_velocity = 0.0;
+		self.update_registers();
+	}
+
+	/// Get the velocity of the output shaft in radians per second.
+	pub fn velocity(&self) -> f64 {
+		self.velocity
+	}
+
+	/// Get the motor current in ampère.
+	pub fn current(&self) -> f64 {
+		self.current
+	}
+
+	/// Get the temperature of the motor in degrees Celsius.
+	pub fn temperature(&self) -> f64 {
+		self.temperature
+	}
+
+	/// Get the supply voltage in volt.
+	pub fn supply_voltage(&self) -> f64 {
+		self.supply_voltage
+	}
+
+	/// Set the supply voltage in volt.
+	///
+	/// A voltage outside of the voltage limits of the control table raises the input voltage error.
+	pub fn set_supply_voltage(&mut self, voltage: f64) {
+		self.supply_voltage = voltage;
+		self.update_registers();
+	}
+
+	/// Get the external torque applied to the output shaft in newton metre.
+	pub fn external_torque(&self) -> f64 {
+		self.external_torque
+	}
+
+	/// Set an external torque applied to the output shaft in newton metre, such as gravity acting on a link.
+	pub fn set_external_torque(&mut self, torque: f64) {
+		self.external_torque = torque;
+	}
+
+	/// Get the Hardware Error Status register.
+	///
+	/// See [`hardware_error`] for the meaning of each bit.
+	pub fn hardware_error(&self) -> u8 {
+		self.get(reg::HARDWARE_ERROR_STATUS) as u8
+	}
+
+	/// Raise hardware errors, as if the motor detected them.
+	///
+	/// See [`hardware_error`] for the meaning of each bit.
+	/// If any of the errors is enabled in the Shutdown register, the torque is disabled.
+	/// Hardware errors are cleared by a reboot.
+	pub fn inject_hardware_error(&mut self, errors: u8) {
+		self.raise_hardware_error(errors);
+		self.update_registers();
+	}
+
+	/// Advance the simulation.
+	///
+	/// Long steps are split into multiple shorter steps internally, so the accuracy does not depend on the step size.
+	pub fn step(&mut self, duration: Duration) {
+		let mut remaining = duration.as_secs_f64();
+		while remaining > 0.0 {
+			let dt = remaining.min(MAX_SUBSTEP);
+			self.simulate(dt);
+			remaining -= dt;
+		}
+		self.time += duration;
+		self.update_registers();
+	}
+
+	/// Read a register from the control table.
+	fn get(&self, register: Register) -> i64 {
+		let data = &self.registers[usize::from(register.address)..][..usize::from(register.size)];
+		let mut value = 0u64;
+		for (i, &byte) in data.iter().enumerate() {
+			value |= u64::from(byte) << (8 * i);
+		}
+		if register.signed {
+			let shift = 64 - 8 * u32::from(register.size);
+			((value << shift) as i64) >> shift
+		} else {
+			value as i64
+		}
+	}
+
+	/// Write a register in the control table.
+	///
+	/// Values that do not fit in the register are truncated.
+	fn set(&mut self, register: Register, value: i64) {
+		let data = &mut self.registers[usize::from(register.address)..][..usize::from(register.size)];
+		for (i, byte) in data.iter_mut().enumerate() {
+			*byte = (value >> (8 * i)) as u8;
+		}
+	}
+
+	/// Check if the torque is enabled.
+	fn torque_enabled(&self) -> bool {
+		self.get(reg::TORQUE_ENABLE) != 0
+	}
+
+	/// Get the operating mode.
+	fn operating_mode(&self) -> u8 {
+		self.get(reg::OPERATING_MODE) as u8
+	}
+
+	/// Convert a position in radians to the raw value of the position registers, including the homing offset.
+	fn raw_position(&self, position: f64) -> i64 {
+		round(position / POSITION_UNIT) + self.get(reg::HOMING_OFFSET)
+	}
+
+	/// Get the Goal Position in radians, without homing offset.
+	fn goal_position(&self) -> f64 {
+		(self.get(reg::GOAL_POSITION) - self.get(reg::HOMING_OFFSET)) as f64 * POSITION_UNIT
+	}
+
+	/// Set the Goal Position to the present position, so the motor holds its position when the torque is enabled.
+	fn reset_goal_position(&mut self) {
+		let position = self.raw_position(self.position);
+		self.set(reg::GOAL_POSITION, position);
+		self.trajectory_position = self.position;
+		self.trajectory_velocity = 0.0;
+		self.velocity_integral = 0.0;
+	}
+
+	/// Set bits in the Hardware Error Status register and shut down if needed.
+	fn raise_hardware_error(&mut self, errors: u8) {
+		let errors = self.hardware_error() | errors;
+		self.set(reg::HARDWARE_ERROR_STATUS, errors.into());
+		if self.is_shut_down() {
+			self.set(reg::TORQUE_ENABLE, 0);
+		}
+	}
+
+	/// Check if a hardware error disabled the torque.
+	fn is_shut_down(&self) -> bool {
+		self.hardware_error() & self.get(reg::SHUTDOWN) as u8 != 0
+	}
+
+	/// Advance the simulation by a single short step of `dt` seconds.
+	fn simulate(&mut self, dt: f64) {
+		let parameters = self.parameters;
+		let resistance = parameters.resistance();
+		let back_emf = parameters.back_emf_constant();
+		let torque_constant = parameters.torque_constant();
+		let max_voltage = self.get(reg::PWM_LIMIT) as f64 / PWM_FULL_SCALE * self.supply_voltage;
+
+		let voltage = if self.torque_enabled() {
+			let (voltage, current_limit) = self.control(dt);
+			let voltage = match current_limit {
+				Some(limit) => voltage.clamp(back_emf * self.velocity - limit * resistance, back_emf * self.velocity + limit * resistance),
+				None => voltage,
+			};
+			Some(voltage.clamp(-max_voltage, max_voltage))
+		} else {
+			self.trajectory_position = self.position;
+			self.trajectory_velocity = self.velocity;
+			self.velocity_integral = 0.0;
+			None
+		};
+
+		match voltage {
+			Some(voltage) => {
+				self.current = (voltage - back_emf * self.velocity) / resistance;
+				self.pwm = if self.supply_voltage > 0.0 { voltage / self.supply_voltage } else { 0.0 };
+			},
+			None => {
+				self.current = 0.0;
+				self.pwm = 0.0;
+			},
+		}
+
+		// Integrate the velocity implicitly, so that the simulation is stable for any step size.
+		let (drive, damping) = match voltage {
+			Some(voltage) => (
+				torque_constant * voltage / resistance,
+				torque_constant * back_emf / resistance + parameters.friction,
+			),
+			None => (0.0, parameters.friction),
+		};
+		let inertia = parameters.inertia;
+		self.velocity = (self.velocity * inertia + dt * (drive + self.external_torque)) / (inertia + dt * damping);
+		self.position += self.velocity * dt;
+
+		// First order thermal model: heating by the winding resistance, cooling to the environment.
+		let heating = self.current * self.current * resistance;
+		let cooling = (self.temperature - parameters.ambient_temperature) / parameters.thermal_resistance;
+		self.temperature += dt * (heating - cooling) / parameters.thermal_capacity;
+
+		if self.current.abs() > OVERLOAD_CURRENT * parameters.stall_current {
+			self.overload_time += dt;
+		} else {
+			self.overload_time = (self.overload_time - dt).max(0.0);
+		}
+
+		let mut errors = 0;
+		if self.temperature > self.get(reg::TEMPERATURE_LIMIT) as f64 {
+			errors |= hardware_error::OVERHEATING;
+		}
+		if self.overload_time > OVERLOAD_TIME {
+			errors |= hardware_error::OVERLOAD;
+		}
+		if !self.supply_voltage_in_range() {
+			errors |= hardware_error::INPUT_VOLTAGE;
+		}
+		if errors != 0 {
+			self.raise_hardware_error(errors);
+		}
+	}
+
+	/// Check if the supply voltage is within the voltage limits.
+	fn supply_voltage_in_range(&self) -> bool {
+		let min = self.get(reg::MIN_VOLTAGE_LIMIT) as f64 * VOLTAGE_UNIT;
+		let max = self.get(reg::MAX_VOLTAGE_LIMIT) as f64 * VOLTAGE_UNIT;
+		(min..=max).contains(&self.supply_voltage)
+	}
+
+	/// Run the controller for the current operating mode.
+	///
+	/// Returns the motor voltage and the current limit, if the operating mode limits the current.
+	fn control(&mut self, dt: f64) -> (f64, Option<f64>) {
+		let parameters = self.parameters;
+		let current_limit = self.get(reg::CURRENT_LIMIT) as f64 * CURRENT_UNIT;
+		let goal_current = (self.get(reg::GOAL_CURRENT) as f64 * CURRENT_UNIT).clamp(-current_limit, current_limit);
+		match self.operating_mode() {
+			operating_mode::CURRENT => {
+				let voltage = goal_current * parameters.resistance() + parameters.back_emf_constant() * self.velocity;
+				(voltage, Some(current_limit))
+			},
+			operating_mode::VELOCITY => {
+				let goal = self.get(reg::GOAL_VELOCITY) as f64 * VELOCITY_UNIT;
+				let acceleration = self.profile_acceleration();
+				self.trajectory_velocity = approach(self.trajectory_velocity, goal, acceleration * dt);
+				self.trajectory_position += self.trajectory_velocity * dt;
+				(self.velocity_control(dt, self.trajectory_velocity), None)
+			},
+			operating_mode::POSITION | operating_mode::EXTENDED_POSITION | operating_mode::CURRENT_BASED_POSITION => {
+				self.update_position_profile(dt);
+				let gain = self.get(reg::POSITION_P_GAIN) as f64 / reg::POSITION_P_GAIN.default as f64 * POSITION_P_SCALE;
+				let target = self.trajectory_velocity + gain * (self.trajectory_position - self.position);
+				let voltage = self.velocity_control(dt, target);
+				if self.operating_mode() == operating_mode::CURRENT_BASED_POSITION {
+					(voltage, Some(goal_current.abs()))
+				} else {
+					(voltage, None)
+				}
+			},
+			operating_mode::PWM => {
+				let goal = self.get(reg::GOAL_PWM) as f64 / PWM_FULL_SCALE;
+				(goal * self.supply_voltage, None)
+			},
+			_ => (0.0, None),
+		}
+	}
+
+	/// Run the velocity controller and return the motor voltage.
+	fn velocity_control(&mut self, dt: f64, target: f64) -> f64 {
+		let p_gain = self.get(reg::VELOCITY_P_GAIN) as f64 / reg::VELOCITY_P_GAIN.default as f64 * VELOCITY_P_SCALE;
+		let i_gain = self.get(reg::VELOCITY_I_GAIN) as f64 / reg::VELOCITY_I_GAIN.default as f64 * VELOCITY_I_SCALE;
+		let error = target - self.velocity;
+		if i_gain > 0.0 {
+			// Limit the integral to the supply voltage to prevent wind-up.
+			let limit = self.supply_voltage / i_gain;
+			self.velocity_integral = (self.velocity_integral + error * dt).clamp(-limit, limit);
+		} else {
+			self.velocity_integral = 0.0;
+		}
+		self.parameters.back_emf_constant() * target + p_gain * error + i_gain * self.velocity_integral
+	}
+
+	/// Get the maximum acceleration of the profile in radians per second squared.
+	fn profile_acceleration(&self) -> f64 {
+		match self.get(reg::PROFILE_ACCELERATION) {
+			0 => f64::INFINITY,
+			x => x as f64 * ACCELERATION_UNIT,
+		}
+	}
+
+	/// Advance the profile generator of the position modes towards the goal position.
+	fn update_position_profile(&mut self, dt: f64) {
+		let goal = self.goal_position();
+		let max_velocity = self.get(reg::PROFILE_VELOCITY) as f64 * VELOCITY_UNIT;
+		if max_velocity == 0.0 {
+			// A profile velocity of zero means infinite velocity: the trajectory jumps to the goal.
+			self.trajectory_position = goal;
+			self.trajectory_velocity = 0.0;
+			return;
+		}
+		let acceleration = self.profile_acceleration();
+		let distance = goal - self.trajectory_position;
+		let direction = if distance < 0.0 { -1.0 } else { 1.0 };
+
+		let desired = if acceleration.is_infinite() {
+			direction * max_velocity.min(distance.abs() / dt)
+		} else {
+			let braking_distance = self.trajectory_velocity * self.trajectory_velocity / (2.0 * acceleration);
+			if self.trajectory_velocity * direction > 0.0 && braking_distance >= distance.abs() {
+				0.0
+			} else {
+				direction * max_velocity
+			}
+		};
+		self.trajectory_velocity = approach(self.trajectory_velocity, desired, acceleration * dt);
+		self.trajectory_position += self.trajectory_velocity * dt;
+
+		// Snap to the goal instead of overshooting it.
+		if (goal - self.trajectory_position) * direction <= 0.0 {
+			self.trajectory_position = goal;
+			self.trajectory_velocity = 0.0;
+		}
+	}
+
+	/// Update the read-only registers from the state of the simulation.
+	fn update_registers(&mut self) {
+		if !self.supply_voltage_in_range() {
+			self.raise_hardware_error(hardware_error::INPUT_VOLTAGE);
+		}
+
+		let position = self.raw_position(self.position);
+		let trajectory_position = self.raw_position(self.trajectory_position);
+		let goal_position = self.get(reg::GOAL_POSITION);
+		let moving_threshold = self.get(reg::MOVING_THRESHOLD);
+		let velocity = round(self.velocity / VELOCITY_UNIT);
+		let trajectory_velocity = round(self.trajectory_velocity / VELOCITY_UNIT);
+
+		self.set(reg::REALTIME_TICK, (self.time.as_millis() % 32768) as i64);
+		self.set(reg::PRESENT_PWM, round(self.pwm * PWM_FULL_SCALE));
+		self.set(reg::PRESENT_CURRENT, round(self.current / CURRENT_UNIT));
+		self.set(reg::PRESENT_VELOCITY, velocity);
+		self.set(reg::PRESENT_POSITION, position);
+		self.set(reg::VELOCITY_TRAJECTORY, trajectory_velocity);
+		self.set(reg::POSITION_TRAJECTORY, trajectory_position);
+		self.set(reg::PRESENT_INPUT_VOLTAGE, round(self.supply_voltage / VOLTAGE_UNIT));
+		self.set(reg::PRESENT_TEMPERATURE, round(self.temperature).clamp(0, 255));
+		self.set(reg::MOVING, i64::from(velocity.abs() > moving_threshold));
+
+		let profile_ongoing = self.torque_enabled()
+			&& match self.operating_mode() {
+				operating_mode::VELOCITY => trajectory_velocity != self.get(reg::GOAL_VELOCITY),
+				operating_mode::POSITION | operating_mode::EXTENDED_POSITION | operating_mode::CURRENT_BASED_POSITION => {
+					trajectory_position != goal_position || trajectory_velocity != 0
+				},
+				_ => false,
+			};
+		let in_position = (goal_position - position).abs() <= moving_threshold;
+		let profile_type = match (self.get(reg::PROFILE_VELOCITY), self.get(reg::PROFILE_ACCELERATION)) {
+			(0, _) => 0,
+			(_, 0) => 1,
+			_ => 3,
+		};
+		let moving_status = i64::from(in_position) | i64::from(profile_ongoing) << 1 | profile_type << 4;
+		self.set(reg::MOVING_STATUS, moving_status);
+	}
+
+	/// Check if a write does not violate the limits of the registers.
+	///
+	/// The registers must already contain the written data.
+	fn check_limits(&self, address: u16, len: usize) -> Result<(), StatusError> {
+		let check = |register: Register, min: i64, max: i64| {
+			if register.overlaps(address, len) && !(min..=max).contains(&self.get(register)) {
+				Err(StatusError::DataLimit)
+			} else {
+				Ok(())
+			}
+		};
+		let valid_mode = matches!(
+			self.operating_mode(),
+			operating_mode::CURRENT
+				| operating_mode::VELOCITY
+				| operating_mode::POSITION
+				| operating_mode::EXTENDED_POSITION
+				| operating_mode::CURRENT_BASED_POSITION
+				| operating_mode::PWM
+		);
+		if reg::OPERATING_MODE.overlaps(address, len) && !valid_mode {
+			return Err(StatusError::DataLimit);
+		}
+
+		check(reg::ID, 0, 252)?;
+		check(reg::BAUD_RATE, 0, 7)?;
+		check(reg::PROTOCOL_TYPE, 1, 2)?;
+		check(reg::HOMING_OFFSET, -1_044_479, 1_044_479)?;
+		check(reg::MOVING_THRESHOLD, 0, 1023)?;
+		check(reg::TEMPERATURE_LIMIT, 0, 100)?;
+		check(reg::MAX_VOLTAGE_LIMIT, 95, 160)?;
+		check(reg::MIN_VOLTAGE_LIMIT, 95, 160)?;
+		check(reg::PWM_LIMIT, 0, 885)?;
+		check(reg::CURRENT_LIMIT, 0, 1193)?;
+		check(reg::VELOCITY_LIMIT, 0, 1023)?;
+		check(reg::MAX_POSITION_LIMIT, 0, 4095)?;
+		check(reg::MIN_POSITION_LIMIT, 0, 4095)?;
+		check(reg::TORQUE_ENABLE, 0, 1)?;
+		check(reg::LED, 0, 1)?;
+		check(reg::STATUS_RETURN_LEVEL, 0, 2)?;
+		check(reg::VELOCITY_I_GAIN, 0, 16383)?;
+		check(reg::VELOCITY_P_GAIN, 0, 16383)?;
+		check(reg::POSITION_D_GAIN, 0, 16383)?;
+		check(reg::POSITION_I_GAIN, 0, 16383)?;
+		check(reg::POSITION_P_GAIN, 0, 16383)?;
+		check(reg::FEEDFORWARD_2ND_GAIN, 0, 16383)?;
+		check(reg::FEEDFORWARD_1ST_GAIN, 0, 16383)?;
+		check(reg::BUS_WATCHDOG, 0, 127)?;
+
+		let pwm_limit = self.get(reg::PWM_LIMIT);
+		let current_limit = self.get(reg::CURRENT_LIMIT);
+		let velocity_limit = self.get(reg::VELOCITY_LIMIT);
+		check(reg::GOAL_PWM, -pwm_limit, pwm_limit)?;
+		check(reg::GOAL_CURRENT, -current_limit, current_limit)?;
+		check(reg::GOAL_VELOCITY, -velocity_limit, velocity_limit)?;
+		check(reg::PROFILE_ACCELERATION, 0, 32767)?;
+		check(reg::PROFILE_VELOCITY, 0, 32767)?;
+		match self.operating_mode() {
+			operating_mode::POSITION => check(
+				reg::GOAL_POSITION,
+				self.get(reg::MIN_POSITION_LIMIT),
+				self.get(reg::MAX_POSITION_LIMIT),
+			),
+			_ => check(reg::GOAL_POSITION, -1_048_575, 1_048_575),
+		}
+	}
+
+	/// Reset the registers with the given access to their default values.
+	fn reset_registers(&mut self, access: Access) {
+		for register in reg::ALL {
+			if register.access == access {
+				self.set(register, register.default);
+			}
+		}
+	}
+}
+
+impl ControlTable for Xm430 {
+	fn model_number(&self) -> u16 {
+		self.get(reg::MODEL_NUMBER) as u16
+	}
+
+	fn firmware_version(&self) -> u8 {
+		self.get(reg::FIRMWARE_VERSION) as u8
+	}
+
+	fn read(&mut self, address: u16, buffer: &mut [u8]) -> Result<(), StatusError> {
+		let data = self
+			.registers
+			.get(usize::from(address)..)
+			.and_then(|data| data.get(..buffer.len()))
+			.ok_or(StatusError::DataRange)?;
+		buffer.copy_from_slice(data);
+		Ok(())
+	}
+
+	fn validate_write(&self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		let start = usize::from(address);
+		if start + data.len() > CONTROL_TABLE_SIZE {
+			return Err(StatusError::DataRange);
+		}
+		for byte_address in address..address + data.len() as u16 {
+			let register = reg::ALL
+				.iter()
+				.find(|register| register.overlaps(byte_address, 1))
+				.ok_or(StatusError::Access)?;
+			match register.access {
+				Access::ReadOnly => return Err(StatusError::Access),
+				Access::Eeprom if self.torque_enabled() => return Err(StatusError::Access),
+				Access::Eeprom | Access::Ram => (),
+			}
+		}
+
+		let mut updated = self.clone();
+		updated.registers[start..][..data.len()].copy_from_slice(data);
+		updated.check_limits(address, data.len())
+	}
+
+	fn write(&mut self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		let was_enabled = self.torque_enabled();
+		let start = usize::from(address);
+		self.registers[start..][..data.len()].copy_from_slice(data);
+
+		if reg::TORQUE_ENABLE.overlaps(address, data.len()) {
+			if self.is_shut_down() {
+				// The torque can not be enabled until the motor is rebooted.
+				self.set(reg::TORQUE_ENABLE, 0);
+			} else if !was_enabled && self.torque_enabled() {
+				// Start the profile from the present state of the motor.
+				self.trajectory_position = self.position;
+				self.trajectory_velocity = self.velocity;
+				self.velocity_integral = 0.0;
+			}
+		}
+		if reg::OPERATING_MODE.overlaps(address, data.len()) || reg::HOMING_OFFSET.overlaps(address, data.len()) {
+			self.reset_goal_position();
+		}
+		self.update_registers();
+		Ok(())
+	}
+
+	fn factory_reset(&mut self, kind: FactoryReset) -> Result<(), StatusError> {
+		if self.torque_enabled() {
+			return Err(StatusError::Access);
+		}
+		let id = self.get(reg::ID);
+		let baud_rate = self.get(reg::BAUD_RATE);
+		self.reset_registers(Access::Eeprom);
+		self.reset_registers(Access::Ram);
+		match kind {
+			FactoryReset::All => (),
+			FactoryReset::ExceptId => self.set(reg::ID, id),
+			FactoryReset::ExceptIdBaudRate => {
+				self.set(reg::ID, id);
+				self.set(reg::BAUD_RATE, baud_rate);
+			},
+			FactoryReset::Unknown(_) => return Err(StatusError::DataRange),
+		}
+		self.reset_goal_position();
+		self.update_registers();
+		Ok(())
+	}
+
+	fn reboot(&mut self) {
+		self.reset_registers(Access::Ram);
+		self.set(reg::HARDWARE_ERROR_STATUS, 0);
+		self.overload_time = 0.0;
+		self.reset_goal_position();
+		self.update_registers();
+	}
+
+	fn clear(&mut self, kind: Clear) -> Result<(), StatusError> {
+		match kind {
+			Clear::MultiTurns => {
+				if self.torque_enabled() || self.velocity.abs() > self.get(reg::MOVING_THRESHOLD) as f64 * VELOCITY_UNIT {
+					return Err(StatusError::ResultFail);
+				}
+				let turn = 2.0 * PI;
+				let position = self.position % turn;
+				self.position = if position < 0.0 { position + turn } else { position };
+				self.reset_goal_position();
+				self.update_registers();
+				Ok(())
+			},
+			// Clearing errors is only supported by the Y series.
+			Clear::Errors | Clear::Reserved(_) => Err(StatusError::Instruction),
+		}
+	}
+
+	fn return_delay_time(&self) -> Duration {
+		RETURN_DELAY_UNIT * self.get(reg::RETURN_DELAY_TIME) as u32
+	}
+
+	fn status_return_level(&self) -> StatusReturnLevel {
+		StatusReturnLevel::from(self.get(reg::STATUS_RETURN_LEVEL) as u8)
+	}
+
+	fn alert(&self) -> bool {
+		self.hardware_error() != 0
+	}
+}
+
+/// Move a value towards a target by at most `max_step`.
+fn approach(value: f64, target: f64, max_step: f64) -> f64 {
+	if (target - value).abs() <= max_step {
+		target
+	} else if target > value {
+		value + max_step
+	} else {
+		value - max_step
+	}
+}
+
+/// Round a value to the nearest integer.
+fn round(value: f64) -> i64 {
+	if value < 0.0 {
+		(value - 0.5) as i64
+	} else {
+		(value + 0.5) as i64
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	fn read_i32(motor: &mut Xm430, address: u16) -> i32 {
+		let mut buffer = [0; 4];
+		let_assert!(Ok(()) = motor.read(address, &mut buffer));
+		i32::from_le_bytes(buffer)
+	}
+
+	fn write(motor: &mut Xm430, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		motor.validate_write(address, data)?;
+		motor.write(address, data)
+	}
+
+	#[test]
+	fn test_control_table_defaults() {
+		let mut motor = Xm430::new();
+		assert!(motor.model_number() == MODEL_XM430_W350);
+		assert!(motor.id() == 1);
+		assert!(motor.return_delay_time() == Duration::from_micros(500));
+		assert!(motor.status_return_level() == StatusReturnLevel::All);
+		assert!(read_i32(&mut motor, address::PRESENT_POSITION) == 0);
+		let mut buffer = [0; 1];
+		assert!(let Ok(()) = motor.read(address::PRESENT_INPUT_VOLTAGE, &mut buffer));
+		assert!(buffer[0] == 120);
+	}
+
+	#[test]
+	fn test_write_access() {
+		let mut motor = Xm430::new();
+		assert!(write(&mut motor, address::MODEL_NUMBER, &[1]) == Err(StatusError::Access));
+		assert!(write(&mut motor, address::PRESENT_POSITION, &[1]) == Err(StatusError::Access));
+		assert!(write(&mut motor, 14, &[1]) == Err(StatusError::Access));
+		assert!(write(&mut motor, address::BACKUP_READY, &[0, 0]) == Err(StatusError::DataRange));
+		assert!(write(&mut motor, address::OPERATING_MODE, &[2]) == Err(StatusError::DataLimit));
+		assert!(write(&mut motor, address::GOAL_POSITION, &5000i32.to_le_bytes()) == Err(StatusError::DataLimit));
+
+		assert!(let Ok(()) = write(&mut motor, address::OPERATING_MODE, &[operating_mode::EXTENDED_POSITION]));
+		assert!(let Ok(()) = write(&mut motor, address::GOAL_POSITION, &5000i32.to_le_bytes()));
+		assert!(let Ok(()) = write(&mut motor, address::TORQUE_ENABLE, &[1]));
+		assert!(write(&mut motor, address::OPERATING_MODE, &[operating_mode::POSITION]) == Err(StatusError::Access));
+	}
+
+	#[test]
+	fn test_position_mode() {
+		let mut motor = Xm430::new();
+		assert!(let Ok(()) = write(&mut motor, address::PROFILE_VELOCITY, &100u32.to_le_bytes()));
+		assert!(let Ok(()) = write(&mut motor, address::PROFILE_ACCELERATION, &50u32.to_le_bytes()));
+		assert!(let Ok(()) = write(&mut motor, address::TORQUE_ENABLE, &[1]));
+		assert!(let Ok(()) = write(&mut motor, address::GOAL_POSITION, &1024i32.to_le_bytes()));
+
+		motor.step(Duration::from_millis(100));
+		let position = read_i32(&mut motor, address::PRESENT_POSITION);
+		assert!(position > 0);
+		assert!(position < 1024);
+		assert!(read_i32(&mut motor, address::PRESENT_VELOCITY) > 0);
+
+		motor.step(Duration::from_secs(3));
+		let position = read_i32(&mut motor, address::PRESENT_POSITION);
+		assert!((position - 1024).abs() <= 10);
+		assert!(motor.hardware_error() == 0);
+	}
+
+	#[test]
+	fn test_velocity_mode() {
+		let mut motor = Xm430::new();
+		assert!(let Ok(()) = write(&mut motor, address::OPERATING_MODE, &[operating_mode::VELOCITY]));
+		assert!(let Ok(()) = write(&mut motor, address::TORQUE_ENABLE, &[1]));
+		assert!(let Ok(()) = write(&mut motor, address::GOAL_VELOCITY, &(-100i32).to_le_bytes()));
+		motor.step(Duration::from_secs(1));
+		let velocity = read_i32(&mut motor, address::PRESENT_VELOCITY);
+		assert!((velocity + 100).abs() <= 2);
+		assert!(read_i32(&mut motor, address::PRESENT_POSITION) < 0);
+	}
+
+	#[test]
+	fn test_torque_disabled() {
+		let mut motor = Xm430::new();
+		motor.set_external_torque(0.1);
+		motor.step(Duration::from_secs(1));
+		assert!(motor.velocity() > 0.0);
+		assert!(motor.current() == 0.0);
+	}
+
+	#[test]
+	fn test_overload_shutdown() {
+		let mut motor = Xm430::new();
+		assert!(let Ok(()) = write(&mut motor, address::TORQUE_ENABLE, &[1]));
+		assert!(let Ok(()) = write(&mut motor, address::GOAL_POSITION, &2048i32.to_le_bytes()));
+		motor.set_external_torque(-10.0);
+		motor.step(Duration::from_secs(2));
+		assert!(motor.hardware_error() & hardware_error::OVERLOAD != 0);
+		assert!(motor.alert());
+		assert!(!motor.torque_enabled());
+		assert!(let Ok(()) = write(&mut motor, address::TORQUE_ENABLE, &[1]));
+		assert!(!motor.torque_enabled());
+
+		motor.reboot();
+		assert!(motor.hardware_error() == 0);
+		assert!(let Ok(()) = write(&mut motor, address::TORQUE_ENABLE, &[1]));
+		assert!(motor.torque_enabled());
+	}
+
+	#[test]
+	fn test_current_mode() {
+		let mut motor = Xm430::new();
+		assert!(let Ok(()) = write(&mut motor, address::OPERATING_MODE, &[operating_mode::CURRENT]));
+		assert!(let Ok(()) = write(&mut motor, address::TORQUE_ENABLE, &[1]));
+		assert!(let Ok(()) = write(&mut motor, address::GOAL_CURRENT, &200i16.to_le_bytes()));
+		motor.step(Duration::from_millis(10));
+		assert!((motor.current() - 200.0 * CURRENT_UNIT).abs() < 0.01);
+		assert!(motor.velocity() > 0.0);
+	}
+
+	#[test]
+	fn test_temperature_rise() {
+		let mut motor = Xm430::new();
+		assert!(let Ok(()) = write(&mut motor, address::TORQUE_ENABLE, &[1]));
+		motor.set_external_torque(-2.0);
+		motor.step(Duration::from_secs(60));
+		assert!(motor.temperature() > 30.0);
+		assert!(motor.hardware_error() == 0);
+		assert!(motor.position().abs() < 0.1);
+	}
+}
@@ -0,0 +1,229 @@
+//! Passive sniffer that decodes all packets on a bus.
+
+use std::time::{Duration, Instant};
+
+use crate::{Instruction, Instructions, Packet, PacketParser, ParsedPacket, ReadError, SerialPort};
+
+#[cfg(feature = "serial2")]
+use std::path::Path;
+
+/// A packet received by a [`Sniffer`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SniffedPacket {
+	/// The sequence number of the packet, counting all packets received by the sniffer from zero.
+	pub sequence: u64,
+
+	/// The time at which the packet was received, relative to the creation of the sniffer.
+	pub timestamp: Duration,
+
+	/// The raw bytes of the packet, without byte-stuffing and CRC.
+	///
+	/// These can be formatted with [`PrettyPacket::from_bytes()`][crate::PrettyPacket::from_bytes].
+	pub data: Vec<u8>,
+
+	/// The decoded packet.
+	pub content: SniffedContent,
+}
+
+/// The decoded content of a [`SniffedPacket`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SniffedContent {
+	/// An instruction packet, sent by the controller.
+	Instruction(Instruction<Vec<u8>>),
+
+	/// A status packet, sent by a motor.
+	Status(SniffedStatus),
+}
+
+/// A status packet received by a [`Sniffer`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SniffedStatus {
+	/// The ID of the motor that sent the status packet.
+	pub motor_id: u8,
+
+	/// The error field of the status packet, including the alert bit.
+	pub error: u8,
+
+	/// The parameters of the status packet.
+	pub parameters: Vec<u8>,
+
+	/// The sequence number of the instruction that the status packet replies to, if known.
+	///
+	/// A status packet is correlated with the most recent instruction if that instruction was addressed to the motor,
+	/// or if it was a broadcast ping, or a sync read or bulk read that includes the motor.
+	pub reply_to: Option<u64>,
+}
+
+/// A passive listener that decodes every packet on a bus, without ever transmitting anything.
+///
+/// Instruction packets are parsed into [`Instructions`].
+/// Status packets are correlated with the instruction that triggered them.
+/// Every packet is timestamped when it has been received completely.
+///
+/// Packets can be received one at a time with [`Self::next_packet()`],
+/// or by using the sniffer as an [`Iterator`] that waits for packets forever.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use dynamixel2::{Sniffer, SniffedContent};
+///
+/// for packet in Sniffer::open("/dev/ttyUSB0", 57600)? {
+///   let packet = packet?;
+///   match packet.content {
+///     SniffedContent::Instruction(instruction) => println!("{:?}: {:?}", packet.timestamp, instruction),
+///     SniffedContent::Status(status) => println!("{:?}: {:?}", packet.timestamp, status),
+///   }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Sniffer<ReadBuffer, T> {
+	serial_port: T,
+	parser: PacketParser<ReadBuffer>,
+	start: Instant,
+	next_sequence: u64,
+	last_instruction: Option<(u64, Instruction<Vec<u8>>)>,
+}
+
+#[cfg(feature = "serial2")]
+impl Sniffer<Vec<u8>, serial2::SerialPort> {
+	/// Open a serial port with the given baud rate.
+	///
+	/// This will allocate a new read buffer of 1024 bytes.
+	/// Use [`Self::with_buffer()`] if you want to use a custom buffer.
+	pub fn open(path: impl AsRef<Path>, baud_rate: u32) -> std::io::Result<Self> {
+		let port = serial2::SerialPort::open(path, baud_rate)?;
+		Ok(Self::with_buffer(port, vec![0; 1024]))
+	}
+}
+
+impl<ReadBuffer, T> Sniffer<ReadBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// Create a new sniffer using a pre-allocated read buffer.
+	///
+	/// The buffer must be large enough to hold the largest packet on the bus, including byte-stuffing.
+	pub fn with_buffer(serial_port: T, read_buffer: ReadBuffer) -> Self {
+		Self {
+			serial_port,
+			parser: PacketParser::new(read_buffer),
+			start: Instant::now(),
+			next_sequence: 0,
+			last_instruction: None,
+		}
+	}
+
+	/// Get a reference to the underlying serial port.
+	pub fn serial_port(&self) -> &T {
+		&self.serial_port
+	}
+
+	/// Consume the sniffer to get ownership of the serial port.
+	pub fn into_serial_port(self) -> T {
+		self.serial_port
+	}
+
+	/// Wait for the next packet on the bus.
+	///
+	/// Returns `Ok(None)` if no complete packet is received before the timeout.
+	/// Corrupted packets are reported as an error, after which the sniffer continues with the next packet.
+	pub fn next_packet(&mut self, timeout: Duration) -> Result<Option<SniffedPacket>, ReadError<T::Error>> {
+		let deadline = self.serial_port.make_deadline(timeout);
+		loop {
+			if let Some(packet) = self.poll()? {
+				return Ok(Some(packet));
+			}
+			match self.serial_port.read(self.parser.spare_capacity_mut(), &deadline) {
+				Ok(new_data) => self.parser.advance(new_data),
+				Err(e) if T::is_timeout_error(&e) => return Ok(None),
+				Err(e) => return Err(ReadError::Io(e)),
+			}
+		}
+	}
+
+	/// Decode the next complete packet in the read buffer, if any.
+	fn poll(&mut self) -> Result<Option<SniffedPacket>, ReadError<T::Error>> {
+		let (data, content) = match self.parser.poll_packet() {
+			Ok(None) => return Ok(None),
+			Ok(Some(ParsedPacket::Instruction(packet))) => {
+				let data = packet.as_bytes().to_vec();
+				let instruction: Instruction<Vec<u8>> = packet.try_into()?;
+				(data, SniffedContent::Instruction(instruction))
+			},
+			Ok(Some(ParsedPacket::Status(packet))) => {
+				let status = SniffedStatus {
+					motor_id: packet.packet_id(),
+					error: packet.error(),
+					parameters: packet.parameters().to_vec(),
+					reply_to: None,
+				};
+				(packet.as_bytes().to_vec(), SniffedContent::Status(status))
+			},
+			Err(e) => return Err(e.into_io_error()),
+		};
+
+		let sequence = self.next_sequence;
+		self.next_sequence += 1;
+		let content = match content {
+			SniffedContent::Instruction(instruction) => {
+				self.last_instruction = Some((sequence, instruction.clone()));
+				SniffedContent::Instruction(instruction)
+			},
+			SniffedContent::Status(mut status) => {
+				status.reply_to = self
+					.last_instruction
+					.as_ref()
+					.filter(|(_, instruction)| is_reply(instruction, status.motor_id))
+					.map(|&(sequence, _)| sequence);
+				SniffedContent::Status(status)
+			},
+		};
+		Ok(Some(SniffedPacket {
+			sequence,
+			timestamp: self.start.elapsed(),
+			data,
+			content,
+		}))
+	}
+}
+
+/// Check if a motor is expected to reply to an instruction.
+fn is_reply(instruction: &Instruction<Vec<u8>>, motor_id: u8) -> bool {
+	if instruction.id == motor_id {
+		return true;
+	}
+	if instruction.id != crate::instructions::packet_id::BROADCAST {
+		return false;
+	}
+	match &instruction.instruction {
+		Instructions::Ping => true,
+		Instructions::SyncRead { ids, .. } => ids.contains(&motor_id),
+		Instructions::BulkRead { parameters } => parameters.chunks_exact(5).any(|block| block[0] == motor_id),
+		_ => false,
+	}
+}
+
+impl<ReadBuffer, T> Iterator for Sniffer<ReadBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	type Item = Result<SniffedPacket, ReadError<T::Error>>;
+
+	/// Wait for the next packet, without any timeout.
+	///
+	/// The iterator never ends, but it does return errors.
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			match self.next_packet(Duration::from_secs(1)) {
+				Ok(Some(packet)) => return Some(Ok(packet)),
+				Ok(None) => continue,
+				Err(e) => return Some(Err(e)),
+			}
+		}
+	}
+}
@@ -0,0 +1,162 @@
+//! Spans for bus transactions, for the `tracing` feature.
+//!
+//! Without the `tracing` feature, [`TransactionSpan`] is an empty type and all functions do nothing.
+
+use core::time::Duration;
+
+use crate::{ReadError, SerialPort};
+
+/// The span of a single bus transaction: an instruction and all status packets received in response.
+///
+/// The span is created when the instruction is written, and closed when the next instruction is written.
+/// It has the following fields:
+/// * `instruction`: the name of the instruction.
+/// * `motor_id`: the packet ID of the instruction, not set for a batch of instructions.
+/// * `address` and `length`: the register range for read and write instructions.
+/// * `bytes_sent` and `bytes_received`: the number of bytes on the wire, including byte-stuffing.
+/// * `responses`: the number of status packets received.
+/// * `latency_us`: the latency of the last status packet, if the serial port can measure it.
+/// * `error`: the kind of the last error.
+///
+/// Each status packet and each error also emits an event in the span, with the `motor_id`, `bytes`, `latency_us` and `error` fields.
+#[derive(Debug, Default)]
+pub(crate) struct TransactionSpan {
+	#[cfg(feature = "tracing")]
+	inner: Option<Inner>,
+}
+
+#[cfg(feature = "tracing")]
+#[derive(Debug)]
+struct Inner {
+	span: tracing::Span,
+	bytes_received: usize,
+	responses: usize,
+}
+
+impl TransactionSpan {
+	/// Start a new transaction, closing the previous one.
+	///
+	/// The `motor_id` is the packet ID of the instruction, or `None` for a batch of instructions.
+	/// The `parameter_head` holds the first (unstuffed) parameters of the instruction, used to find the register range.
+	pub fn start(&mut self, motor_id: Option<u8>, instruction_id: u8, parameter_head: &[u8], parameter_count: usize, bytes_sent: usize) {
+		#[cfg(feature = "tracing")]
+		{
+			use tracing::field::Empty;
+			let span = tracing::debug_span!(
+				"dynamixel2::transaction",
+				instruction = instruction_name(instruction_id),
+				motor_id = motor_id,
+				address = Empty,
+				length = Empty,
+				bytes_sent = bytes_sent,
+				bytes_received = 0,
+				responses = 0,
+				latency_us = Empty,
+				error = Empty,
+			);
+			if let Some((address, length)) = register_range(instruction_id, parameter_head, parameter_count) {
+				span.record("address", address);
+				span.record("length", length);
+			}
+			self.inner = Some(Inner {
+				span,
+				bytes_received: 0,
+				responses: 0,
+			});
+		}
+		#[cfg(not(feature = "tracing"))]
+		let _ = (motor_id, instruction_id, parameter_head, parameter_count, bytes_sent);
+	}
+
+	/// Record a failure to write an instruction.
+	pub fn record_write_error(&mut self) {
+		#[cfg(feature = "tracing")]
+		if let Some(inner) = &self.inner {
+			inner.span.record("error", "write");
+			tracing::debug!(parent: &inner.span, error = "write", "failed to write instruction");
+		}
+	}
+
+	/// Record a received status packet.
+	pub fn record_response(&mut self, motor_id: u8, bytes: usize, latency: Option<Duration>) {
+		#[cfg(feature = "tracing")]
+		if let Some(inner) = &mut self.inner {
+			inner.bytes_received += bytes;
+			inner.responses += 1;
+			inner.span.record("bytes_received", inner.bytes_received);
+			inner.span.record("responses", inner.responses);
+			let latency_us = latency.map(|latency| latency.as_micros() as u64);
+			if let Some(latency_us) = latency_us {
+				inner.span.record("latency_us", latency_us);
+			}
+			tracing::debug!(parent: &inner.span, motor_id, bytes, latency_us, "received status packet");
+		}
+		#[cfg(not(feature = "tracing"))]
+		let _ = (motor_id, bytes, latency);
+	}
+
+	/// Record a failure to read a status packet.
+	pub fn record_read_error<T: SerialPort>(&mut self, motor_id: Option<u8>, error: &ReadError<T::Error>) {
+		#[cfg(feature = "tracing")]
+		if let Some(inner) = &self.inner {
+			let kind = error_kind::<T>(error);
+			inner.span.record("error", kind);
+			tracing::debug!(parent: &inner.span, motor_id, error = kind, "failed to read status packet");
+		}
+		#[cfg(not(feature = "tracing"))]
+		let _ = (motor_id, error);
+	}
+}
+
+/// Get a short name for the kind of a read error.
+#[cfg(feature = "tracing")]
+fn error_kind<T: SerialPort>(error: &ReadError<T::Error>) -> &'static str {
+	use crate::InvalidMessage;
+	match error {
+		ReadError::Io(e) if T::is_timeout_error(e) => "timeout",
+		ReadError::Io(_) => "io",
+		ReadError::BufferFull(_) => "buffer_full",
+		ReadError::InvalidMessage(InvalidMessage::InvalidHeaderPrefix(_)) => "invalid_header",
+		ReadError::InvalidMessage(InvalidMessage::InvalidChecksum(_)) => "invalid_checksum",
+		ReadError::InvalidMessage(InvalidMessage::InvalidPacketId(_)) => "invalid_packet_id",
+		ReadError::InvalidMessage(InvalidMessage::InvalidInstruction(_)) => "invalid_instruction",
+		ReadError::InvalidMessage(InvalidMessage::InvalidParameterCount(_)) => "invalid_parameter_count",
+		ReadError::MotorError(_) => "motor_error",
+	}
+}
+
+/// Get the register address and length of a read or write instruction.
+#[cfg(feature = "tracing")]
+fn register_range(instruction_id: u8, parameter_head: &[u8], parameter_count: usize) -> Option<(u16, u16)> {
+	use crate::endian::read_u16_le;
+	use crate::instructions::instruction_id;
+	let address = read_u16_le(parameter_head.get(0..2)?);
+	match instruction_id {
+		instruction_id::READ | instruction_id::SYNC_READ | instruction_id::SYNC_WRITE => {
+			Some((address, read_u16_le(parameter_head.get(2..4)?)))
+		},
+		instruction_id::WRITE | instruction_id::REG_WRITE => Some((address, (parameter_count - 2) as u16)),
+		_ => None,
+	}
+}
+
+/// Get the name of an instruction, for the `instruction` field of a span.
+#[cfg(feature = "tracing")]
+fn instruction_name(instruction_id: u8) -> &'static str {
+	use crate::instructions::instruction_id;
+	match instruction_id {
+		instruction_id::PING => "ping",
+		instruction_id::READ => "read",
+		instruction_id::WRITE => "write",
+		instruction_id::REG_WRITE => "reg_write",
+		instruction_id::ACTION => "action",
+		instruction_id::FACTORY_RESET => "factory_reset",
+		instruction_id::REBOOT => "reboot",
+		instruction_id::CLEAR => "clear",
+		instruction_id::SYNC_READ => "sync_read",
+		instruction_id::SYNC_WRITE => "sync_write",
+		instruction_id::BULK_READ => "bulk_read",
+		instruction_id::BULK_WRITE => "bulk_write",
+		_ => "unknown",
+	}
+}
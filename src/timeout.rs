@@ -0,0 +1,392 @@
+use core::time::Duration;
+
+use crate::serial_port::SerialPort;
+use crate::{Bus, TooManyTimeoutOverrides};
+
+/// The number of motor IDs that can be assigned to a motor (0 to 252).
+const MOTOR_COUNT: usize = 253;
+
+/// The maximum number of instructions that can have a timeout override at the same time.
+const MAX_OVERRIDES: usize = 16;
+
+/// The maximum Return Delay Time that can be configured on a motor.
+const MAX_RETURN_DELAY_TIME: Duration = Duration::from_micros(508);
+
+/// The default margin added to the read timeout on top of the transfer time and the Return Delay Time.
+///
+/// The official SDK adds a flat 34 milliseconds, so lets just mimick that.
+/// That includes the maximum Return Delay Time, which we account for separately.
+const DEFAULT_MARGIN: Duration = Duration::from_micros(34_000 - 508);
+
+/// The smallest step used to adjust the latency estimate of a motor, in nanoseconds.
+const MIN_ESTIMATE_STEP_NANOS: u64 = 1_000;
+
+/// The model used by a [`Bus`] to compute the read timeout for status packets.
+///
+/// The read timeout for a status packet always includes the time needed to transfer the packet at the baud rate of the bus.
+/// The model determines what is added on top of that.
+///
+/// The timeout for a single instruction can be overridden completely with [`Bus::set_instruction_timeout()`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TimeoutModel {
+	/// Add the Return Delay Time of the motor and a fixed margin.
+	///
+	/// If the Return Delay Time of a motor is not known, the maximum Return Delay Time of 508 microseconds is used.
+	///
+	/// The default model uses a margin of 33.492 milliseconds,
+	/// which results in the same flat 34 milliseconds that the official SDK uses for motors with an unknown Return Delay Time.
+	FixedMargin(Duration),
+
+	/// Add the Return Delay Time of the motor, the latency timer of a USB serial converter and an extra margin.
+	///
+	/// USB serial converters like the FTDI chips hold back received data until their buffer is full or the latency timer expires.
+	/// Short status packets are therefore delayed by up to one latency timer period.
+	///
+	/// If the Return Delay Time of a motor is not known, the maximum Return Delay Time of 508 microseconds is used.
+	UsbLatencyTimer {
+		/// The latency timer of the USB serial converter.
+		///
+		/// For FTDI chips, this defaults to 16 milliseconds, but it can often be lowered to 1 millisecond.
+		latency_timer: Duration,
+
+		/// An extra margin to account for USB frame scheduling and operating system latency.
+		extra_margin: Duration,
+	},
+
+	/// Measure the response latency of each motor and add a percentile of the measured latencies.
+	///
+	/// The latency of a motor is measured from the start of the read until the complete status packet is received,
+	/// minus the time required to transfer the status packet.
+	/// This includes the Return Delay Time of the motor and any latency of the serial port.
+	///
+	/// Until a latency has been measured for a motor, the initial margin is used instead, in addition to the Return Delay Time.
+	/// Whenever a read for a motor times out, its latency estimate is doubled.
+	///
+	/// Measuring latency requires support from the serial port (see [`SerialPort::remaining_time()`]).
+	/// If the serial port can not measure time, this model always uses the initial margin.
+	Adaptive {
+		/// The percentile of the measured latencies to use, in the range 1 to 99.
+		percentile: u8,
+
+		/// Extra time added on top of the latency percentile.
+		headroom: Duration,
+
+		/// The margin to use for motors without a measured latency.
+		initial_margin: Duration,
+	},
+}
+
+impl Default for TimeoutModel {
+	fn default() -> Self {
+		Self::FixedMargin(DEFAULT_MARGIN)
+	}
+}
+
+/// The timeout configuration and latency measurements of a bus.
+#[derive(Debug, Clone)]
+pub(crate) struct TimeoutSettings {
+	/// The model used to compute read timeouts.
+	model: TimeoutModel,
+
+	/// Absolute timeouts for specific instructions.
+	overrides: [Option<(u8, Duration)>; MAX_OVERRIDES],
+
+	/// The instruction that was sent last, used to find the applicable override.
+	current_instruction: Option<u8>,
+
+	/// The estimated latency percentile of each motor in nanoseconds.
+	latency_estimate: [u32; MOTOR_COUNT],
+
+	/// The number of latency samples for each motor, saturating at 255.
+	latency_samples: [u8; MOTOR_COUNT],
+}
+
+/// Compute the default read timeout for a status packet with the given number of parameters.
+///
+/// This matches the read timeout of a [`Bus`] with the default [`TimeoutModel`], for a motor with an unknown Return Delay Time.
+pub(crate) fn default_status_timeout(expected_parameters: u16, baud_rate: u32) -> Duration {
+	let message_size = crate::packet::STATUS_HEADER_SIZE as u32 + u32::from(expected_parameters) + 2;
+	crate::bus::message_transfer_time(message_size, baud_rate) + MAX_RETURN_DELAY_TIME + DEFAULT_MARGIN
+}
+
+impl TimeoutSettings {
+	/// Create new timeout settings with the default model and no overrides.
+	pub fn new() -> Self {
+		Self {
+			model: TimeoutModel::default(),
+			overrides: [None; MAX_OVERRIDES],
+			current_instruction: None,
+			latency_estimate: [0; MOTOR_COUNT],
+			latency_samples: [0; MOTOR_COUNT],
+		}
+	}
+
+	/// Get the timeout model.
+	pub fn model(&self) -> &TimeoutModel {
+		&self.model
+	}
+
+	/// Set the timeout model and discard all latency measurements.
+	pub fn set_model(&mut self, model: TimeoutModel) {
+		self.model = model;
+		self.latency_estimate = [0; MOTOR_COUNT];
+		self.latency_samples = [0; MOTOR_COUNT];
+	}
+
+	/// Get the timeout override for an instruction.
+	pub fn instruction_timeout(&self, instruction_id: u8) -> Option<Duration> {
+		self.overrides
+			.iter()
+			.flatten()
+			.find(|(id, _)| *id == instruction_id)
+			.map(|&(_, timeout)| timeout)
+	}
+
+	/// Set or clear the timeout override for an instruction.
+	pub fn set_instruction_timeout(&mut self, instruction_id: u8, timeout: Option<Duration>) -> Result<(), TooManyTimeoutOverrides> {
+		let existing = self.overrides.iter_mut().find(|entry| matches!(entry, Some((id, _)) if *id == instruction_id));
+		match (existing, timeout) {
+			(Some(entry), timeout) => *entry = timeout.map(|timeout| (instruction_id, timeout)),
+			(None, None) => (),
+			(None, Some(timeout)) => {
+				let Some(entry) = self.overrides.iter_mut().find(|entry| entry.is_none()) else {
+					return Err(TooManyTimeoutOverrides {
+						instruction_id,
+						max_overrides: MAX_OVERRIDES,
+					});
+				};
+				*entry = Some((instruction_id, timeout));
+			},
+		}
+		Ok(())
+	}
+
+	/// Remember the instruction that was sent last.
+	pub fn set_current_instruction(&mut self, instruction_id: u8) {
+		self.current_instruction = Some(instruction_id);
+	}
+
+	/// Get the timeout override for the instruction that was sent last.
+	pub fn current_override(&self) -> Option<Duration> {
+		self.instruction_timeout(self.current_instruction?)
+	}
+
+	/// Get the measured latency estimate of a motor.
+	pub fn measured_latency(&self, motor_id: u8) -> Option<Duration> {
+		let index = usize::from(motor_id);
+		if *self.latency_samples.get(index)? == 0 {
+			None
+		} else {
+			Some(Duration::from_nanos(self.latency_estimate[index].into()))
+		}
+	}
+
+	/// Compute the margin to add to the transfer time of a status packet.
+	///
+	/// The motor ID is `None` if the response is not from one specific motor.
+	pub fn margin(&self, motor_id: Option<u8>, return_delay: Option<Duration>) -> Duration {
+		let return_delay = return_delay.unwrap_or(MAX_RETURN_DELAY_TIME);
+		match &self.model {
+			TimeoutModel::FixedMargin(margin) => return_delay + *margin,
+			TimeoutModel::UsbLatencyTimer { latency_timer, extra_margin } => return_delay + *latency_timer + *extra_margin,
+			TimeoutModel::Adaptive {
+				headroom, initial_margin, ..
+			} => match motor_id.and_then(|motor_id| self.measured_latency(motor_id)) {
+				Some(latency) => latency + *headroom,
+				None => return_delay + *initial_margin,
+			},
+		}
+	}
+
+	/// Record the measured latency of a response from a motor.
+	///
+	/// This only has an effect for the adaptive timeout model.
+	pub fn record_latency(&mut self, motor_id: u8, latency: Duration) {
+		let TimeoutModel::Adaptive { percentile, .. } = self.model else {
+			return;
+		};
+		let index = usize::from(motor_id);
+		let Some(samples) = self.latency_samples.get_mut(index) else {
+			return;
+		};
+
+		let sample = latency.as_nanos().min(u32::MAX.into()) as u64;
+		let estimate = u64::from(self.latency_estimate[index]);
+		let estimate = if *samples == 0 {
+			sample
+		} else {
+			// Stochastic gradient descent on the quantile loss:
+			// move up by `percentile` and down by `100 - percentile` times the step size.
+			// This converges to the requested percentile of the measured latencies.
+			let percentile = u64::from(percentile.clamp(1, 99));
+			let step = (estimate / 16).max(MIN_ESTIMATE_STEP_NANOS);
+			if sample > estimate {
+				estimate + step * percentile / 100
+			} else {
+				estimate.saturating_sub(step * (100 - percentile) / 100)
+			}
+		};
+
+		self.latency_estimate[index] = estimate.min(u32::MAX.into()) as u32;
+		*samples = samples.saturating_add(1);
+	}
+
+	/// Record a read timeout for a motor.
+	///
+	/// For the adaptive timeout model, this doubles the latency estimate of the motor.
+	/// The new estimate is at least the initial margin, so an estimate that dropped to zero can still recover.
+	pub fn record_timeout(&mut self, motor_id: u8) {
+		let TimeoutModel::Adaptive { initial_margin, .. } = self.model else {
+			return;
+		};
+		let index = usize::from(motor_id);
+		if let Some(&samples) = self.latency_samples.get(index) {
+			if samples > 0 {
+				let initial_margin = initial_margin.as_nanos().min(u32::MAX.into()) as u32;
+				self.latency_estimate[index] = self.latency_estimate[index].saturating_mul(2).max(initial_margin);
+			}
+		}
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T> Bus<ReadBuffer, WriteBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// Get the model used to compute read timeouts.
+	pub fn timeout_model(&self) -> &TimeoutModel {
+		self.timeouts.model()
+	}
+
+	/// Set the model used to compute read timeouts.
+	///
+	/// This discards all latency measurements made for the [`TimeoutModel::Adaptive`] model.
+	pub fn set_timeout_model(&mut self, model: TimeoutModel) {
+		self.timeouts.set_model(model)
+	}
+
+	/// Get the timeout override for a specific instruction.
+	pub fn instruction_timeout(&self, instruction_id: u8) -> Option<Duration> {
+		self.timeouts.instruction_timeout(instruction_id)
+	}
+
+	/// Override the read timeout for a specific instruction.
+	///
+	/// The override is used as absolute timeout for each status packet received in response to the instruction,
+	/// ignoring the [`TimeoutModel`] of the bus.
+	/// For a broadcast ping, it is used as the timeout for the responses of all motors.
+	///
+	/// Pass `None` to remove the override.
+	///
+	/// At most 16 different instructions can have a timeout override at the same time.
+	/// If you try to override the timeout of more instructions, an error is returned and the overrides are left unchanged.
+	/// Changing or removing an existing override never fails.
+	pub fn set_instruction_timeout(&mut self, instruction_id: u8, timeout: Option<Duration>) -> Result<(), TooManyTimeoutOverrides> {
+		self.timeouts.set_instruction_timeout(instruction_id, timeout)
+	}
+
+	/// Get the measured response latency of a motor.
+	///
+	/// Latencies are only measured by the [`TimeoutModel::Adaptive`] model.
+	/// The returned value is the current estimate of the configured percentile.
+	pub fn measured_latency(&self, motor_id: u8) -> Option<Duration> {
+		self.timeouts.measured_latency(motor_id)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	#[test]
+	fn test_default_margin() {
+		let settings = TimeoutSettings::new();
+		assert!(settings.margin(None, None) == Duration::from_millis(34));
+		assert!(settings.margin(Some(1), Some(Duration::ZERO)) == DEFAULT_MARGIN);
+	}
+
+	#[test]
+	fn test_instruction_timeout() {
+		let mut settings = TimeoutSettings::new();
+		assert!(let Ok(()) = settings.set_instruction_timeout(0x02, Some(Duration::from_millis(5))));
+		assert!(let Ok(()) = settings.set_instruction_timeout(0x03, Some(Duration::from_millis(6))));
+		assert!(settings.instruction_timeout(0x02) == Some(Duration::from_millis(5)));
+		assert!(settings.current_override() == None);
+
+		settings.set_current_instruction(0x03);
+		assert!(settings.current_override() == Some(Duration::from_millis(6)));
+
+		assert!(let Ok(()) = settings.set_instruction_timeout(0x03, None));
+		assert!(settings.current_override() == None);
+		assert!(settings.instruction_timeout(0x02) == Some(Duration::from_millis(5)));
+	}
+
+	#[test]
+	fn test_too_many_instruction_timeouts() {
+		let mut settings = TimeoutSettings::new();
+		for instruction_id in 0..MAX_OVERRIDES as u8 {
+			assert!(let Ok(()) = settings.set_instruction_timeout(instruction_id, Some(Duration::from_millis(5))));
+		}
+		let_assert!(Err(e) = settings.set_instruction_timeout(0x80, Some(Duration::from_millis(5))));
+		assert!(e.instruction_id == 0x80);
+		assert!(settings.instruction_timeout(0x80) == None);
+
+		// Existing overrides can still be changed, and removing one makes room for another.
+		assert!(let Ok(()) = settings.set_instruction_timeout(0x01, Some(Duration::from_millis(7))));
+		assert!(let Ok(()) = settings.set_instruction_timeout(0x00, None));
+		assert!(let Ok(()) = settings.set_instruction_timeout(0x80, Some(Duration::from_millis(5))));
+		assert!(settings.instruction_timeout(0x80) == Some(Duration::from_millis(5)));
+	}
+
+	#[test]
+	fn test_adaptive_latency() {
+		let mut settings = TimeoutSettings::new();
+		settings.set_model(TimeoutModel::Adaptive {
+			percentile: 90,
+			headroom: Duration::from_micros(100),
+			initial_margin: Duration::from_millis(10),
+		});
+		assert!(settings.margin(Some(1), None) == MAX_RETURN_DELAY_TIME + Duration::from_millis(10));
+
+		// Nine fast responses for every slow response: the estimate should end up near the slow responses.
+		for i in 0..1000 {
+			let latency = if i % 10 == 0 { 2_000 } else { 500 };
+			settings.record_latency(1, Duration::from_micros(latency));
+		}
+		let_assert!(Some(latency) = settings.measured_latency(1));
+		assert!(latency >= Duration::from_micros(500));
+		assert!(latency <= Duration::from_micros(2_200));
+		assert!(settings.margin(Some(1), None) == latency + Duration::from_micros(100));
+
+		// A timeout doubles the estimate, but never drops it below the initial margin.
+		settings.record_timeout(1);
+		assert!(settings.measured_latency(1) == Some((latency * 2).max(Duration::from_millis(10))));
+		assert!(settings.measured_latency(2) == None);
+	}
+
+	#[test]
+	fn test_adaptive_timeout_recovery() {
+		let mut settings = TimeoutSettings::new();
+		settings.set_model(TimeoutModel::Adaptive {
+			percentile: 90,
+			headroom: Duration::ZERO,
+			initial_margin: Duration::from_millis(10),
+		});
+
+		// Instant responses drive the estimate down to zero.
+		for _ in 0..100 {
+			settings.record_latency(1, Duration::ZERO);
+		}
+		assert!(settings.measured_latency(1) == Some(Duration::ZERO));
+
+		// A timeout must bring the estimate back up, even though doubling zero does nothing.
+		settings.record_timeout(1);
+		assert!(settings.measured_latency(1) == Some(Duration::from_millis(10)));
+		assert!(settings.margin(Some(1), None) == Duration::from_millis(10));
+		settings.record_timeout(1);
+		assert!(settings.measured_latency(1) == Some(Duration::from_millis(20)));
+	}
+}
@@ -0,0 +1,446 @@
+//! Timing analysis of bus transactions.
+//!
+//! The [`Bus`] records the timing of every transaction in a [`TransactionTiming`],
+//! and keeps a running [`TimingSummary`] of all transactions to show how much time is spent on the wire and how much waiting for motors.
+//! Use [`Bus::last_transaction_timing()`] and [`Bus::timing_summary()`] to inspect them.
+//!
+//! A [`Schedule`] estimates the cycle time and the maximum loop rate for a fixed sequence of instructions,
+//! such as a sync read followed by a sync write in a control loop.
+//!
+//! Measured times are only available if the [`SerialPort`] can measure time, see [`SerialPort::remaining_time()`].
+
+use core::time::Duration;
+
+use crate::bus::message_transfer_time;
+use crate::packet::{INSTRUCTION_HEADER_SIZE, STATUS_HEADER_SIZE};
+use crate::serial_port::SerialPort;
+use crate::Bus;
+
+/// The timing of a single transaction: an instruction and all status packets received in response.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TransactionTiming {
+	/// The instruction ID, or the ID of the last instruction for a batch of instructions.
+	pub instruction_id: u8,
+
+	/// The packet ID of the instruction, or `None` for a batch of instructions.
+	pub motor_id: Option<u8>,
+
+	/// The number of bytes sent, including byte-stuffing.
+	pub bytes_sent: usize,
+
+	/// The number of bytes received in status packets, including byte-stuffing.
+	pub bytes_received: usize,
+
+	/// The number of status packets received.
+	pub responses: usize,
+
+	/// The time needed to transmit the instruction at the baud rate of the bus.
+	pub transmit_time: Duration,
+
+	/// The time needed to transfer the received status packets at the baud rate of the bus.
+	pub receive_time: Duration,
+
+	/// The known Return Delay Time of the motor, if any.
+	///
+	/// See [`Bus::set_return_delay_time()`].
+	pub return_delay: Option<Duration>,
+
+	/// The time from the start of the write until the first byte of the response was received.
+	pub first_byte: Option<Duration>,
+
+	/// The time from the start of the write until the last status packet was received or the last read failed.
+	///
+	/// For instructions without a response, this is the time it took to write the instruction.
+	pub duration: Option<Duration>,
+}
+
+impl TransactionTiming {
+	/// The total time spent transferring data on the wire.
+	pub fn wire_time(&self) -> Duration {
+		self.transmit_time + self.receive_time
+	}
+
+	/// The time between the end of the transmission and the first byte of the response.
+	///
+	/// This is the time the motor took to respond, including the Return Delay Time.
+	pub fn response_delay(&self) -> Option<Duration> {
+		Some(self.first_byte?.saturating_sub(self.transmit_time))
+	}
+
+	/// The time during the transaction that no data was transferred on the wire.
+	pub fn idle_time(&self) -> Option<Duration> {
+		Some(self.duration?.saturating_sub(self.wire_time()))
+	}
+}
+
+/// A summary of the timing of many transactions.
+///
+/// The measured times only include transactions with a measured duration.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimingSummary {
+	/// The number of transactions.
+	pub transactions: u64,
+
+	/// The number of status packets received.
+	pub responses: u64,
+
+	/// The number of transactions with a measured duration.
+	pub measured_transactions: u64,
+
+	/// The total time spent transmitting instructions, for the measured transactions.
+	pub transmit_time: Duration,
+
+	/// The total time spent receiving status packets, for the measured transactions.
+	pub receive_time: Duration,
+
+	/// The total duration of the measured transactions.
+	pub total_time: Duration,
+
+	/// The number of status packets received in the measured transactions.
+	pub measured_responses: u64,
+}
+
+impl TimingSummary {
+	/// Add a transaction to the summary.
+	pub fn add(&mut self, timing: &TransactionTiming) {
+		self.transactions += 1;
+		self.responses += timing.responses as u64;
+		if let Some(duration) = timing.duration {
+			self.measured_transactions += 1;
+			self.measured_responses += timing.responses as u64;
+			self.transmit_time += timing.transmit_time;
+			self.receive_time += timing.receive_time;
+			self.total_time += duration.max(timing.wire_time());
+		}
+	}
+
+	/// The total time spent transferring data on the wire, for the measured transactions.
+	pub fn wire_time(&self) -> Duration {
+		self.transmit_time + self.receive_time
+	}
+
+	/// The total time that no data was transferred on the wire, for the measured transactions.
+	///
+	/// This is mostly time spent waiting for motors to respond.
+	pub fn idle_time(&self) -> Duration {
+		self.total_time.saturating_sub(self.wire_time())
+	}
+
+	/// The fraction of the time that data was transferred on the wire, between 0 and 1.
+	///
+	/// Returns `None` if no transactions were measured.
+	pub fn utilization(&self) -> Option<f64> {
+		if self.total_time.is_zero() {
+			return None;
+		}
+		Some(self.wire_time().as_secs_f64() / self.total_time.as_secs_f64())
+	}
+
+	/// The average idle time per received status packet.
+	///
+	/// This can be used as the response delay of a [`Schedule`].
+	/// Returns `None` if no status packets were received in the measured transactions.
+	pub fn average_response_delay(&self) -> Option<Duration> {
+		let responses = u32::try_from(self.measured_responses).ok().filter(|&responses| responses > 0)?;
+		Some(self.idle_time() / responses)
+	}
+}
+
+/// An estimate of the time needed for a fixed sequence of instructions.
+///
+/// The estimate assumes that every status packet is preceded by the response delay,
+/// and that there is no other idle time on the wire.
+/// Byte-stuffing is not taken into account.
+///
+/// # Example
+/// ```
+/// use dynamixel2::timing::Schedule;
+/// use std::time::Duration;
+///
+/// // Read the present position of 4 motors and write their goal position.
+/// let schedule = Schedule::new(1_000_000)
+///     .with_response_delay(Duration::from_micros(500))
+///     .sync_read(4, 4)
+///     .sync_write(4, 4);
+/// assert!(schedule.max_loop_rate() > 300.0);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Schedule {
+	baud_rate: u32,
+	response_delay: Duration,
+	instructions: u32,
+	responses: u32,
+	bytes_sent: u32,
+	bytes_received: u32,
+}
+
+impl Schedule {
+	/// Create an empty schedule for a bus with the given baud rate.
+	pub fn new(baud_rate: u32) -> Self {
+		Self {
+			baud_rate,
+			response_delay: Duration::ZERO,
+			instructions: 0,
+			responses: 0,
+			bytes_sent: 0,
+			bytes_received: 0,
+		}
+	}
+
+	/// Set the delay before each status packet.
+	///
+	/// This should include the Return Delay Time of the motors.
+	/// A measured value can be taken from [`TimingSummary::average_response_delay()`].
+	/// The default is zero.
+	pub fn with_response_delay(mut self, delay: Duration) -> Self {
+		self.response_delay = delay;
+		self
+	}
+
+	/// Add an instruction with the given number of parameters,
+	/// answered by `responses` status packets with `response_parameters` parameters each.
+	pub fn instruction(mut self, parameters: u32, responses: u32, response_parameters: u32) -> Self {
+		self.instructions += 1;
+		self.responses += responses;
+		self.bytes_sent += INSTRUCTION_HEADER_SIZE as u32 + parameters + 2;
+		self.bytes_received += responses * (STATUS_HEADER_SIZE as u32 + response_parameters + 2);
+		self
+	}
+
+	/// Add a read of `length` bytes from a single motor.
+	pub fn read(self, length: u16) -> Self {
+		self.instruction(4, 1, length.into())
+	}
+
+	/// Add a write of `length` bytes to a single motor, which responds with a status packet.
+	pub fn write(self, length: u16) -> Self {
+		self.instruction(2 + u32::from(length), 1, 0)
+	}
+
+	/// Add a sync read of `length` bytes from `motor_count` motors.
+	pub fn sync_read(self, motor_count: u8, length: u16) -> Self {
+		let motor_count = u32::from(motor_count);
+		self.instruction(4 + motor_count, motor_count, length.into())
+	}
+
+	/// Add a sync write of `length` bytes to `motor_count` motors.
+	pub fn sync_write(self, motor_count: u8, length: u16) -> Self {
+		let motor_count = u32::from(motor_count);
+		self.instruction(4 + motor_count * (1 + u32::from(length)), 0, 0)
+	}
+
+	/// Add a bulk read of `length` bytes from each of `motor_count` motors.
+	pub fn bulk_read(self, motor_count: u8, length: u16) -> Self {
+		let motor_count = u32::from(motor_count);
+		self.instruction(5 * motor_count, motor_count, length.into())
+	}
+
+	/// Add a bulk write of `length` bytes to each of `motor_count` motors.
+	pub fn bulk_write(self, motor_count: u8, length: u16) -> Self {
+		let motor_count = u32::from(motor_count);
+		self.instruction(motor_count * (5 + u32::from(length)), 0, 0)
+	}
+
+	/// The number of instructions in the schedule.
+	pub fn instructions(&self) -> u32 {
+		self.instructions
+	}
+
+	/// The time needed to transmit all instructions.
+	pub fn transmit_time(&self) -> Duration {
+		message_transfer_time(self.bytes_sent, self.baud_rate)
+	}
+
+	/// The time needed to receive all status packets.
+	pub fn receive_time(&self) -> Duration {
+		message_transfer_time(self.bytes_received, self.baud_rate)
+	}
+
+	/// The total response delay of all status packets.
+	pub fn wait_time(&self) -> Duration {
+		self.response_delay * self.responses
+	}
+
+	/// The estimated time needed to execute the whole schedule once.
+	pub fn cycle_time(&self) -> Duration {
+		self.transmit_time() + self.receive_time() + self.wait_time()
+	}
+
+	/// The fraction of the cycle time that data is transferred on the wire, between 0 and 1.
+	pub fn utilization(&self) -> f64 {
+		let cycle_time = self.cycle_time();
+		if cycle_time.is_zero() {
+			return 0.0;
+		}
+		(self.transmit_time() + self.receive_time()).as_secs_f64() / cycle_time.as_secs_f64()
+	}
+
+	/// The theoretical maximum number of times per second that the schedule can be executed.
+	///
+	/// Returns infinity for an empty schedule.
+	pub fn max_loop_rate(&self) -> f64 {
+		1.0 / self.cycle_time().as_secs_f64()
+	}
+}
+
+/// The timing recorded by a bus.
+#[derive(Debug, Default)]
+pub(crate) struct TimingRecorder {
+	/// The timing of the current transaction.
+	current: Option<TransactionTiming>,
+
+	/// The summary of all finished transactions.
+	summary: TimingSummary,
+}
+
+impl TimingRecorder {
+	/// Start a new transaction, finishing the previous one.
+	pub fn start(&mut self, timing: TransactionTiming) {
+		if let Some(previous) = self.current.replace(timing) {
+			self.summary.add(&previous);
+		}
+	}
+
+	/// Record a read attempt in the current transaction.
+	///
+	/// The `bytes_received` are the size of the received status packet, or zero if the read failed.
+	pub fn record_read(&mut self, bytes_received: usize, baud_rate: u32, first_byte: Option<Duration>, elapsed: Option<Duration>) {
+		if let Some(timing) = &mut self.current {
+			if bytes_received > 0 {
+				timing.bytes_received += bytes_received;
+				timing.responses += 1;
+				timing.receive_time = message_transfer_time(timing.bytes_received as u32, baud_rate);
+			}
+			timing.first_byte = first_byte;
+			timing.duration = elapsed;
+		}
+	}
+}
+
+impl<ReadBuffer, WriteBuffer, T> Bus<ReadBuffer, WriteBuffer, T>
+where
+	ReadBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	WriteBuffer: AsRef<[u8]> + AsMut<[u8]>,
+	T: SerialPort,
+{
+	/// Get the timing of the last transaction.
+	///
+	/// The timing is updated for each status packet received in response to the instruction.
+	pub fn last_transaction_timing(&self) -> Option<&TransactionTiming> {
+		self.timing.current.as_ref()
+	}
+
+	/// Get a summary of the timing of all transactions, including the last one.
+	pub fn timing_summary(&self) -> TimingSummary {
+		let mut summary = self.timing.summary.clone();
+		if let Some(current) = &self.timing.current {
+			summary.add(current);
+		}
+		summary
+	}
+
+	/// Discard the timing summary of all transactions.
+	pub fn reset_timing_summary(&mut self) {
+		self.timing = TimingRecorder::default();
+	}
+
+	/// Create an empty [`Schedule`] for the baud rate of the bus.
+	pub fn schedule(&self) -> Schedule {
+		Schedule::new(self.baud_rate())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	fn timing(transmit_time: u64, receive_time: u64, duration: Option<u64>) -> TransactionTiming {
+		TransactionTiming {
+			instruction_id: crate::instructions::instruction_id::READ,
+			motor_id: Some(1),
+			bytes_sent: 14,
+			bytes_received: 15,
+			responses: 1,
+			transmit_time: Duration::from_micros(transmit_time),
+			receive_time: Duration::from_micros(receive_time),
+			return_delay: None,
+			first_byte: duration.map(|_| Duration::from_micros(transmit_time + 100)),
+			duration: duration.map(Duration::from_micros),
+		}
+	}
+
+	#[test]
+	fn test_transaction_timing() {
+		let timing = timing(140, 150, Some(400));
+		assert!(timing.wire_time() == Duration::from_micros(290));
+		assert!(timing.response_delay() == Some(Duration::from_micros(100)));
+		assert!(timing.idle_time() == Some(Duration::from_micros(110)));
+	}
+
+	#[test]
+	fn test_summary() {
+		let mut summary = TimingSummary::default();
+		assert!(summary.utilization() == None);
+		assert!(summary.average_response_delay() == None);
+
+		summary.add(&timing(140, 150, Some(400)));
+		summary.add(&timing(140, 150, Some(380)));
+		summary.add(&timing(140, 150, None));
+		assert!(summary.transactions == 3);
+		assert!(summary.responses == 3);
+		assert!(summary.measured_transactions == 2);
+		assert!(summary.wire_time() == Duration::from_micros(580));
+		assert!(summary.idle_time() == Duration::from_micros(200));
+		assert!(summary.average_response_delay() == Some(Duration::from_micros(100)));
+		let_assert!(Some(utilization) = summary.utilization());
+		assert!((utilization - 580.0 / 780.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn test_recorder() {
+		let mut recorder = TimingRecorder::default();
+		recorder.record_read(15, 1_000_000, None, None);
+		assert!(recorder.current.is_none());
+
+		let mut first = timing(140, 0, None);
+		first.bytes_received = 0;
+		first.responses = 0;
+		recorder.start(first);
+		recorder.record_read(15, 1_000_000, Some(Duration::from_micros(200)), Some(Duration::from_micros(400)));
+		recorder.record_read(0, 1_000_000, Some(Duration::from_micros(200)), Some(Duration::from_micros(900)));
+		let_assert!(Some(current) = &recorder.current);
+		assert!(current.responses == 1);
+		assert!(current.bytes_received == 15);
+		assert!(current.receive_time == Duration::from_micros(150));
+		assert!(current.duration == Some(Duration::from_micros(900)));
+
+		recorder.start(timing(140, 0, None));
+		assert!(recorder.summary.transactions == 1);
+		assert!(recorder.summary.total_time == Duration::from_micros(900));
+	}
+
+	#[test]
+	fn test_schedule() {
+		let schedule = Schedule::new(1_000_000);
+		assert!(schedule.cycle_time() == Duration::ZERO);
+		assert!(schedule.utilization() == 0.0);
+
+		// Sync read of 4 bytes from 2 motors: 16 bytes out, 2 * 15 bytes in.
+		let schedule = schedule.with_response_delay(Duration::from_micros(100)).sync_read(2, 4);
+		assert!(schedule.instructions() == 1);
+		assert!(schedule.transmit_time() == Duration::from_micros(160));
+		assert!(schedule.receive_time() == Duration::from_micros(300));
+		assert!(schedule.wait_time() == Duration::from_micros(200));
+
+		// Sync write of 4 bytes to 2 motors: 24 bytes out, no response.
+		let schedule = schedule.sync_write(2, 4);
+		assert!(schedule.transmit_time() == Duration::from_micros(400));
+		assert!(schedule.cycle_time() == Duration::from_micros(900));
+		assert!((schedule.max_loop_rate() - 1.0 / 900e-6).abs() < 1e-6);
+		assert!((schedule.utilization() - 700.0 / 900.0).abs() < 1e-9);
+	}
+}
@@ -0,0 +1,276 @@
+//! Non-blocking transactions driven by polling.
+
+use core::convert::Infallible;
+use core::time::Duration;
+
+use crate::checksum::calculate_checksum;
+use crate::endian::write_u16_le;
+use crate::error::BufferTooSmallError;
+use crate::instructions::instruction_id;
+use crate::packet::{Packet, HEADER_PREFIX, INSTRUCTION_HEADER_SIZE};
+use crate::parser::PacketParser;
+use crate::{bytestuff, ReadError, StatusPacket};
+
+/// Encode an instruction packet into a buffer.
+///
+/// Returns the total length of the encoded packet, including byte-stuffing and checksum.
+/// The first `len` bytes of the buffer can then be transmitted on the bus by any means,
+/// such as DMA or an interrupt driven UART driver.
+pub fn encode_instruction<F>(
+	buffer: &mut [u8],
+	packet_id: u8,
+	instruction_id: u8,
+	parameter_count: usize,
+	encode_parameters: F,
+) -> Result<usize, BufferTooSmallError>
+where
+	F: FnOnce(&mut [u8]),
+{
+	// Check if the buffer can hold the unstuffed message.
+	BufferTooSmallError::check(INSTRUCTION_HEADER_SIZE + parameter_count + 2, buffer.len())?;
+
+	// Add the header, with a placeholder for the length field.
+	buffer[..4].copy_from_slice(&HEADER_PREFIX);
+	buffer[4] = packet_id;
+	buffer[5] = 0;
+	buffer[6] = 0;
+	buffer[7] = instruction_id;
+	// The error byte for StatusPackets gets added in
+	encode_parameters(&mut buffer[INSTRUCTION_HEADER_SIZE..][..parameter_count]);
+	trace!(
+		"encoding packet: {}",
+		crate::PrettyPacket::new(packet_id, instruction_id, &buffer[INSTRUCTION_HEADER_SIZE..][..parameter_count])
+	);
+
+	// Perform bitstuffing on the body.
+	// The header never needs stuffing.
+	let stuffed_body_len = bytestuff::stuff_inplace(&mut buffer[INSTRUCTION_HEADER_SIZE..], parameter_count).map_err(|e| BufferTooSmallError {
+		required_size: INSTRUCTION_HEADER_SIZE + e.required_size,
+		total_size: buffer.len(),
+	})?;
+
+	// The checksum also needs to fit in the buffer.
+	BufferTooSmallError::check(INSTRUCTION_HEADER_SIZE + stuffed_body_len + 2, buffer.len())?;
+
+	write_u16_le(&mut buffer[5..], stuffed_body_len as u16 + 3);
+
+	// Add checksum.
+	let checksum_index = INSTRUCTION_HEADER_SIZE + stuffed_body_len;
+	let checksum = calculate_checksum(0, &buffer[..checksum_index]);
+	write_u16_le(&mut buffer[checksum_index..], checksum);
+
+	Ok(checksum_index + 2)
+}
+
+/// The responses to an instruction, tracked without blocking.
+///
+/// A transaction starts after an instruction has been transmitted.
+/// It expects a fixed number of status packets, each of which must arrive within a timeout after the previous one.
+/// The transaction is driven by calling [`Self::poll()`] with the current time,
+/// for example from the main loop of a cooperative scheduler.
+///
+/// Time is represented as a [`Duration`] since an arbitrary fixed point, such as the boot of the system.
+/// The caller must use the same clock for all calls.
+///
+/// # Example
+/// ```no_run
+/// # fn now() -> core::time::Duration { todo!() }
+/// # fn uart_read(buffer: &mut [u8]) -> usize { todo!() }
+/// # fn uart_write(buffer: &[u8]) { todo!() }
+/// use dynamixel2::{encode_instruction, Packet, PacketParser, Transaction, TransactionPoll};
+/// use dynamixel2::instructions::instruction_id;
+/// use core::time::Duration;
+///
+/// let mut write_buffer = [0; 64];
+/// let mut parser = PacketParser::new([0; 64]);
+///
+/// // Read the present position of motor 1.
+/// let len = encode_instruction(&mut write_buffer, 1, instruction_id::READ, 4, |buffer| {
+///   buffer[0..2].copy_from_slice(&132u16.to_le_bytes());
+///   buffer[2..4].copy_from_slice(&4u16.to_le_bytes());
+/// }).unwrap();
+/// parser.clear();
+/// uart_write(&write_buffer[..len]);
+///
+/// let mut transaction = Transaction::new(now(), 1, Duration::from_millis(10));
+/// loop {
+///   let received = uart_read(parser.spare_capacity_mut());
+///   parser.advance(received);
+///   match transaction.poll(&mut parser, now()) {
+///     TransactionPoll::Pending => continue, // Yield to other tasks here.
+///     TransactionPoll::Response(response) => println!("{:?}", response.parameters()),
+///     TransactionPoll::Error(e) => println!("{}", e),
+///     TransactionPoll::Timeout => println!("timeout"),
+///     TransactionPoll::Done => break,
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Transaction {
+	/// The number of responses that have not been received or timed out yet.
+	remaining: usize,
+
+	/// The timeout for each response.
+	timeout: Duration,
+
+	/// The deadline for the next response.
+	deadline: Duration,
+}
+
+/// The result of polling a [`Transaction`].
+#[derive(Debug)]
+pub enum TransactionPoll<'a> {
+	/// The next response has not been received yet, and it has not timed out either.
+	Pending,
+
+	/// A valid status packet was received.
+	Response(StatusPacket<'a>),
+
+	/// An invalid status packet was received, or a motor reported an error.
+	///
+	/// The packet counts as a response.
+	Error(ReadError<Infallible>),
+
+	/// The next response timed out.
+	///
+	/// The timed out response counts as handled, and the timeout for the following response starts now.
+	Timeout,
+
+	/// All expected responses have been received or timed out.
+	Done,
+}
+
+impl Transaction {
+	/// Start a new transaction that expects `expected_responses` status packets.
+	///
+	/// The transaction should be started as soon as the instruction has been transmitted.
+	/// The timeout applies to each response separately.
+	/// It should include the transfer time of the response and the Return Delay Time of the motor.
+	pub fn new(now: Duration, expected_responses: usize, timeout: Duration) -> Self {
+		Self {
+			remaining: expected_responses,
+			timeout,
+			deadline: now + timeout,
+		}
+	}
+
+	/// Get the number of responses that have not been received or timed out yet.
+	pub fn remaining(&self) -> usize {
+		self.remaining
+	}
+
+	/// Check if all expected responses have been received or timed out.
+	pub fn is_done(&self) -> bool {
+		self.remaining == 0
+	}
+
+	/// Get the deadline for the next response.
+	///
+	/// This can be used to schedule a wake-up for the next call to [`Self::poll()`].
+	/// Returns `None` if the transaction is done.
+	pub fn deadline(&self) -> Option<Duration> {
+		if self.is_done() {
+			None
+		} else {
+			Some(self.deadline)
+		}
+	}
+
+	/// Advance the transaction with the data received by the parser so far.
+	///
+	/// A received packet is reported before a timeout,
+	/// so a response that arrived just in time is not lost if polling is delayed.
+	pub fn poll<'a, Buffer>(&mut self, parser: &'a mut PacketParser<Buffer>, now: Duration) -> TransactionPoll<'a>
+	where
+		Buffer: AsRef<[u8]> + AsMut<[u8]>,
+	{
+		if self.is_done() {
+			return TransactionPoll::Done;
+		}
+
+		match parser.poll_packet_len::<StatusPacket>() {
+			Ok(Some(packet_len)) => {
+				self.next_response(now);
+				let response: StatusPacket = parser.packet(packet_len);
+				if let Err(e) = crate::InvalidInstruction::check(response.instruction_id(), instruction_id::STATUS) {
+					return TransactionPoll::Error(e.into());
+				}
+				if let Err(e) = crate::MotorError::check(response.error()) {
+					return TransactionPoll::Error(e.into());
+				}
+				TransactionPoll::Response(response)
+			},
+			Ok(None) if now >= self.deadline => {
+				self.next_response(now);
+				TransactionPoll::Timeout
+			},
+			Ok(None) => TransactionPoll::Pending,
+			Err(e) => {
+				self.next_response(now);
+				TransactionPoll::Error(e)
+			},
+		}
+	}
+
+	/// Count one response as handled and restart the timeout.
+	fn next_response(&mut self, now: Duration) {
+		self.remaining -= 1;
+		self.deadline = now + self.timeout;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use assert2::{assert, let_assert};
+
+	const PING_RESPONSE: [u8; 14] = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5D];
+
+	#[test]
+	fn test_encode_instruction() {
+		// Ping instruction from the protocol documentation.
+		let mut buffer = [0; 16];
+		let_assert!(Ok(len) = encode_instruction(&mut buffer, 1, instruction_id::PING, 0, |_| ()));
+		assert!(buffer[..len] == [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E]);
+		assert!(let Err(_) = encode_instruction(&mut buffer[..9], 1, instruction_id::PING, 0, |_| ()));
+	}
+
+	#[test]
+	fn test_transaction() {
+		let start = Duration::from_millis(100);
+		let timeout = Duration::from_millis(10);
+		let mut parser = PacketParser::new([0; 64]);
+		let mut transaction = Transaction::new(start, 2, timeout);
+		assert!(transaction.deadline() == Some(start + timeout));
+
+		assert!(let TransactionPoll::Pending = transaction.poll(&mut parser, start));
+		parser.feed(&PING_RESPONSE[..5]);
+		assert!(let TransactionPoll::Pending = transaction.poll(&mut parser, start + Duration::from_millis(5)));
+		parser.feed(&PING_RESPONSE[5..]);
+		let_assert!(TransactionPoll::Response(response) = transaction.poll(&mut parser, start + Duration::from_millis(20)));
+		assert!(response.packet_id() == 1);
+		assert!(transaction.remaining() == 1);
+		assert!(transaction.deadline() == Some(start + Duration::from_millis(30)));
+
+		assert!(let TransactionPoll::Pending = transaction.poll(&mut parser, start + Duration::from_millis(29)));
+		assert!(let TransactionPoll::Timeout = transaction.poll(&mut parser, start + Duration::from_millis(30)));
+		assert!(let TransactionPoll::Done = transaction.poll(&mut parser, start + Duration::from_millis(31)));
+		assert!(transaction.deadline() == None);
+	}
+
+	#[test]
+	fn test_transaction_oversized_packet() {
+		let start = Duration::from_millis(100);
+		let timeout = Duration::from_millis(10);
+		let mut parser = PacketParser::new([0; 32]);
+		let mut transaction = Transaction::new(start, 2, timeout);
+
+		// The header of the oversized packet is dropped, but the valid status packet behind it is kept.
+		parser.feed(&[0xFF, 0xFF, 0xFD, 0x00, 0x01, 0xFF, 0xFF, 0x55, 0x00]);
+		parser.feed(&PING_RESPONSE);
+		assert!(let TransactionPoll::Error(ReadError::BufferFull(_)) = transaction.poll(&mut parser, start));
+		let_assert!(TransactionPoll::Response(response) = transaction.poll(&mut parser, start));
+		assert!(response.packet_id() == 1);
+		assert!(let TransactionPoll::Done = transaction.poll(&mut parser, start));
+	}
+}
@@ -0,0 +1,112 @@
+use assert2::{assert, let_assert};
+use dynamixel2::simulation::xm430::address;
+use dynamixel2::simulation::SimulatedMotor;
+use dynamixel2::{
+	Bridge, BridgeError, BridgeHooks, BridgedInstruction, BridgedStatus, Bus, Device, ReadError, SerialPort, TransferError, Verdict,
+	VirtualBus, VirtualSerialPort,
+};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use test_log::test;
+
+type T = VirtualSerialPort;
+
+const BAUD_RATE: u32 = 1_000_000;
+
+/// The ID of the motor as seen by the controller.
+const VIRTUAL_ID: u8 = 5;
+
+/// The ID of the motor on the real bus.
+const MOTOR_ID: u8 = 1;
+
+/// Hooks that remap the motor ID, block writes to the torque enable register, and record all instructions.
+#[derive(Default)]
+struct Hooks {
+	instructions: Arc<Mutex<Vec<BridgedInstruction>>>,
+}
+
+impl BridgeHooks for Hooks {
+	fn on_instruction(&mut self, instruction: &mut BridgedInstruction) -> Verdict {
+		self.instructions.lock().unwrap().push(instruction.clone());
+		if instruction.packet_id == VIRTUAL_ID {
+			instruction.packet_id = MOTOR_ID;
+		}
+		if instruction.instruction_id == dynamixel2::instructions::instruction_id::WRITE
+			&& instruction.parameters[..2] == address::TORQUE_ENABLE.to_le_bytes()
+		{
+			return Verdict::Block;
+		}
+		Verdict::Forward
+	}
+
+	fn on_status(&mut self, _instruction: &BridgedInstruction, status: &mut BridgedStatus) -> Verdict {
+		if status.packet_id == MOTOR_ID {
+			status.packet_id = VIRTUAL_ID;
+		}
+		Verdict::Forward
+	}
+}
+
+#[test]
+fn test_bridge() {
+	let upstream = VirtualBus::new();
+	let downstream = VirtualBus::new();
+	let mut controller = Bus::with_buffers(upstream.connect(BAUD_RATE), vec![0; 1024], vec![0; 1024]).unwrap();
+	let bridge_device = Device::with_buffers(upstream.connect(BAUD_RATE), vec![0; 1024], vec![0; 1024]).unwrap();
+	let bridge_bus = Bus::with_buffers(downstream.connect(BAUD_RATE), vec![0; 1024], vec![0; 1024]).unwrap();
+	let motor_device = Device::with_buffers(downstream.connect(BAUD_RATE), vec![0; 1024], vec![0; 1024]).unwrap();
+
+	let hooks = Hooks::default();
+	let instructions = hooks.instructions.clone();
+	let stop = Arc::new(AtomicBool::new(false));
+
+	let bridge_t = thread::spawn({
+		let stop = stop.clone();
+		move || {
+			let mut bridge = Bridge::new(bridge_device, bridge_bus, hooks);
+			while !stop.load(Relaxed) {
+				match bridge.forward_one(Duration::from_millis(5)) {
+					Err(BridgeError::Upstream(TransferError::ReadError(ReadError::Io(e)))) if T::is_timeout_error(&e) => continue,
+					x => assert!(let Ok(()) = x),
+				}
+			}
+		}
+	});
+	let motor_t = thread::spawn({
+		let stop = stop.clone();
+		move || {
+			let mut motor = SimulatedMotor::new(motor_device, MOTOR_ID);
+			while !stop.load(Relaxed) {
+				match motor.serve_one(Duration::from_millis(5)) {
+					Err(TransferError::ReadError(ReadError::Io(e))) if T::is_timeout_error(&e) => continue,
+					x => assert!(let Ok(()) = x),
+				}
+			}
+		}
+	});
+
+	let_assert!(Ok(response) = controller.ping(VIRTUAL_ID));
+	assert!(response.motor_id == VIRTUAL_ID);
+	assert!(response.data.model == 1020);
+
+	assert!(let Ok(_) = controller.write_u32(VIRTUAL_ID, address::GOAL_POSITION, 1234));
+	let_assert!(Ok(response) = controller.read_u32(VIRTUAL_ID, address::GOAL_POSITION));
+	assert!(response.motor_id == VIRTUAL_ID);
+	assert!(response.data == 1234);
+
+	let_assert!(Err(TransferError::ReadError(ReadError::Io(e))) = controller.write_u8(VIRTUAL_ID, address::TORQUE_ENABLE, 1));
+	assert!(T::is_timeout_error(&e));
+	let_assert!(Ok(response) = controller.read_u8(VIRTUAL_ID, address::TORQUE_ENABLE));
+	assert!(response.data == 0);
+
+	stop.store(true, Relaxed);
+	bridge_t.join().unwrap();
+	motor_t.join().unwrap();
+
+	let instructions = instructions.lock().unwrap();
+	assert!(instructions.len() == 5);
+	assert!(instructions.iter().all(|instruction| instruction.packet_id == VIRTUAL_ID));
+}
@@ -0,0 +1,40 @@
+use assert2::{assert, let_assert};
+use dynamixel2::capture::{CaptureReader, CaptureWriter, CapturingSerialPort, Direction};
+use dynamixel2::{Bus, Instructions, ScriptedSerialPort};
+use std::time::SystemTime;
+use test_log::test;
+
+#[test]
+fn test_capture_bus_session() {
+	let serial_port = ScriptedSerialPort::new(57600)
+		.expect(1, Instructions::Ping)
+		.reply(1, [0x06, 0x04, 0x26])
+		.expect(1, Instructions::Read { address: 132, length: 4 })
+		.reply(1, 1234u32.to_le_bytes());
+	let capture = CaptureWriter::new(Vec::new()).unwrap();
+
+	let start = SystemTime::now();
+	let mut bus = Bus::with_buffers(CapturingSerialPort::new(serial_port, capture), vec![0; 1024], vec![0; 1024]).unwrap();
+	assert!(let Ok(_) = bus.ping(1));
+	let_assert!(Ok(response) = bus.read_u32(1, 132));
+	assert!(response.data == 1234);
+
+	let (_, capture) = bus.into_serial_port().into_parts();
+	let data = capture.into_inner();
+
+	let_assert!(Ok(reader) = CaptureReader::new(data.as_slice()));
+	let_assert!(Ok(packets) = reader.collect::<Result<Vec<_>, _>>());
+	assert!(packets.len() == 4);
+	assert!(packets[0].direction == Direction::Outbound);
+	assert!(packets[0].data == [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E]);
+	assert!(packets[1].direction == Direction::Inbound);
+	assert!(packets[1].data[7] == 0x55);
+	assert!(packets[2].direction == Direction::Outbound);
+	assert!(packets[3].direction == Direction::Inbound);
+	assert!(packets[3].data[9..13] == 1234u32.to_le_bytes());
+
+	// Timestamps are stored with microsecond resolution.
+	let start = start - std::time::Duration::from_micros(1);
+	assert!(packets.iter().all(|packet| packet.timestamp >= start));
+	assert!(packets.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+}
@@ -1,11 +1,13 @@
 use assert2::{assert, let_assert};
-use dynamixel2::{Bus, Device, Instructions, ReadError, SerialPort};
+use dynamixel2::instructions::packet_id::BROADCAST;
+use dynamixel2::instructions::{instruction_id, BulkWriteData, DuplicateId, DuplicateIdEvidence, Ping, SyncWriteData};
+use dynamixel2::{Bus, Device, Instructions, ReadError, ScriptedSerialPort, SerialPort, StatusReturnLevel, StatusWait, WriteError};
 use log::{info, trace};
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use test_log::test;
 
 mod mock_serial_port;
@@ -108,3 +110,331 @@ fn test_packet_response() {
 	kill_device.store(true, Relaxed);
 	device_t.join().unwrap();
 }
+
+#[test]
+fn test_find_duplicate_ids() {
+	let kill_device = Arc::new(AtomicBool::new(false));
+	let (mut bus, mut device) = setup_bus();
+	let bus_t = thread::spawn(move || {
+		let_assert!(Ok(duplicates) = bus.find_duplicate_ids());
+		assert!(duplicates.len() == 1);
+		assert!(duplicates[0].motor_id == DEVICE_ID);
+		assert!(duplicates[0].evidence == DuplicateIdEvidence::RepeatedPingResponse);
+	});
+	let device_t = thread::spawn({
+		let kill_device = kill_device.clone();
+		move || {
+			while !kill_device.load(Relaxed) {
+				let packet = device.read(Duration::from_millis(50));
+				let packet = match packet {
+					Err(ReadError::Io(e)) if T::is_timeout_error(&e) => continue,
+					x => x,
+				};
+				let_assert!(Ok(packet) = packet);
+				if packet.id != DEVICE_ID && packet.id != BROADCAST {
+					continue;
+				}
+				if let Instructions::Ping = packet.instruction {
+					// Pretend to be two motors with the same ID.
+					for _ in 0..2 {
+						assert!(let Ok(()) = device.write_status(DEVICE_ID, 0, 3, |buffer| {
+							buffer.copy_from_slice(&[0x06, 0x04, 0x2D]);
+						}));
+					}
+				}
+			}
+		}
+	});
+	bus_t.join().unwrap();
+	kill_device.store(true, Relaxed);
+	device_t.join().unwrap();
+}
+
+#[test]
+fn test_find_duplicate_ids_collision() {
+	let serial_port = ScriptedSerialPort::new(57600)
+		// The responses of two motors with ID 3 collide, corrupting the length field.
+		.expect(BROADCAST, Instructions::Ping)
+		.reply(1, [0x06, 0x04, 0x26])
+		.reply_raw([0xFF, 0xFF, 0xFD, 0x00, 0x03, 0x01, 0x00, 0x55])
+		.reply(5, [0x06, 0x04, 0x26])
+		// Every motor that responded and every ID in the collision window is pinged individually.
+		.expect(1, Instructions::Ping)
+		.reply(1, [0x06, 0x04, 0x26])
+		.expect(2, Instructions::Ping)
+		.expect(3, Instructions::Ping)
+		.reply_raw([0xFF, 0xFF, 0xFD, 0x00, 0x03, 0xFF, 0xFF, 0x55, 0x00, 0x06])
+		.expect(4, Instructions::Ping)
+		.expect(5, Instructions::Ping)
+		.reply(5, [0x06, 0x04, 0x26])
+		.reply(5, [0x06, 0x04, 0x26]);
+	let mut bus = Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+	let_assert!(Ok(duplicates) = bus.find_duplicate_ids());
+	assert!(duplicates == [
+		DuplicateId { motor_id: 3, evidence: DuplicateIdEvidence::CorruptedStatusPacket },
+		DuplicateId { motor_id: 5, evidence: DuplicateIdEvidence::ExtraStatusPacket },
+	]);
+}
+
+#[test]
+fn test_inspect_duplicate_id() {
+	let serial_port = ScriptedSerialPort::new(57600)
+		.expect(3, Instructions::Ping)
+		.reply(3, [0x06, 0x04, 0x26])
+		.reply_raw([0xFF, 0xFF, 0xFD, 0x00, 0x03, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x00, 0x00])
+		.expect(3, Instructions::Ping)
+		.reply(3, [0x20, 0x04, 0x2D])
+		.reply_error(3, 0x01, [0x06, 0x04, 0x26])
+		.reply(3, [0x06, 0x04]);
+	let mut bus = Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+	let_assert!(Ok(report) = bus.inspect_duplicate_id(3, 2));
+	assert!(report.attempts == 2);
+	assert!(report.responses == 4);
+	assert!(report.collisions == 1);
+	assert!(report.devices == [
+		Ping { model: 0x0406, firmware: 0x26 },
+		Ping { model: 0x0420, firmware: 0x2D },
+	]);
+	assert!(report.is_duplicate());
+
+	let serial_port = ScriptedSerialPort::new(57600)
+		.expect(3, Instructions::Ping)
+		.reply(3, [0x06, 0x04, 0x26]);
+	let mut bus = Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+	let_assert!(Ok(report) = bus.inspect_duplicate_id(3, 1));
+	assert!(report.responses == 1);
+	assert!(!report.is_duplicate());
+}
+
+#[test]
+fn test_status_return_level() {
+	let kill_device = Arc::new(AtomicBool::new(false));
+	let (mut bus, mut device) = setup_bus();
+	let bus_t = thread::spawn(move || {
+		bus.set_status_return_level(DEVICE_ID, Some(StatusReturnLevel::PingAndRead));
+		bus.set_return_delay_time(DEVICE_ID, Some(Duration::from_micros(0)));
+		assert!(bus.return_delay_time(DEVICE_ID) == Some(Duration::ZERO));
+		let_assert!(Ok(response) = bus.write_u8(DEVICE_ID, 5, 1));
+		assert!(response.motor_id == DEVICE_ID);
+		let_assert!(Ok(response) = bus.read_u8(DEVICE_ID, 5));
+		assert!(response.data == 1);
+	});
+	let device_t = thread::spawn({
+		let kill_device = kill_device.clone();
+		move || {
+			let mut control_table = ControlTable::new(10);
+			while !kill_device.load(Relaxed) {
+				let packet = device.read(Duration::from_millis(50));
+				let packet = match packet {
+					Err(ReadError::Io(e)) if T::is_timeout_error(&e) => continue,
+					x => x,
+				};
+				let_assert!(Ok(packet) = packet);
+				if packet.id != DEVICE_ID {
+					continue;
+				}
+				match packet.instruction {
+					Instructions::Read { address, length } => {
+						let_assert!(Some(data) = control_table.read(address, length));
+						assert!(let Ok(()) = device.write_status(DEVICE_ID, 0, length as usize, |buffer| {
+							buffer.copy_from_slice(data);
+						}));
+					},
+					// Status Return Level 1: no status packet for writes.
+					Instructions::Write { address, parameters } => {
+						assert!(control_table.write(address, parameters));
+					},
+					i => todo!("impl {:?}", i),
+				}
+			}
+		}
+	});
+	bus_t.join().unwrap();
+	kill_device.store(true, Relaxed);
+	device_t.join().unwrap();
+}
+
+#[test]
+fn test_batch_would_respond() {
+	let (mut bus, _device) = setup_bus();
+	let mut batch = bus.batch();
+
+	// The Status Return Level of the motor is unknown, so the bus assumes that it responds.
+	let_assert!(Err(WriteError::WouldRespond(e)) = batch.write(DEVICE_ID, 2, &[1]));
+	assert!(e.motor_id == DEVICE_ID);
+	assert!(e.instruction_id == instruction_id::WRITE);
+	assert!(batch.instruction_count() == 0);
+
+	bus.set_status_return_level(DEVICE_ID, Some(StatusReturnLevel::PingAndRead));
+	let mut batch = bus.batch();
+	assert!(let Ok(()) = batch.write(DEVICE_ID, 2, &[1]));
+	assert!(let Ok(()) = batch.action(DEVICE_ID));
+	assert!(batch.instruction_count() == 2);
+}
+
+#[test]
+fn test_device_write_data_for() {
+	let (mut bus, mut device) = setup_bus();
+	assert!(let Ok(()) = bus.sync_write(4, 2, &[
+		SyncWriteData { motor_id: 2, data: [1, 2] },
+		SyncWriteData { motor_id: DEVICE_ID, data: [3, 4] },
+	]));
+	assert!(let Ok(_) = device.read_instruction_packet_timeout(Duration::from_millis(50)));
+	let_assert!(Ok(Some(write)) = device.write_data_for(DEVICE_ID));
+	assert!(write == BulkWriteData { motor_id: DEVICE_ID, address: 4, data: &[3, 4][..] });
+	assert!(let Ok(None) = device.write_data_for(3));
+
+	assert!(let Ok(()) = bus.bulk_write(&[
+		BulkWriteData { motor_id: DEVICE_ID, address: 6, data: [5] },
+		BulkWriteData { motor_id: 2, address: 8, data: [6] },
+	]));
+	assert!(let Ok(_) = device.read_instruction_packet_timeout(Duration::from_millis(50)));
+	let_assert!(Ok(Some(write)) = device.write_data_for(DEVICE_ID));
+	assert!(write == BulkWriteData { motor_id: DEVICE_ID, address: 6, data: &[5][..] });
+
+	// Waiting for other motors discards the instruction from the read buffer.
+	assert!(let Ok(StatusWait::TimedOut) = device.wait_for_status_packet(2, Duration::ZERO));
+	assert!(let Ok(None) = device.write_data_for(DEVICE_ID));
+}
+
+#[test]
+fn test_batch_sync_write_sync_read() {
+	let kill_device = Arc::new(AtomicBool::new(false));
+	let (mut bus, mut device) = setup_bus();
+	let bus_t = thread::spawn(move || {
+		let mut batch = bus.batch();
+		assert!(let Ok(()) = batch.sync_write(2, 2, &[
+			SyncWriteData { motor_id: DEVICE_ID, data: [7, 8] },
+			SyncWriteData { motor_id: 2, data: [9, 10] },
+		]));
+		assert!(let Ok(()) = batch.broadcast_action());
+		assert!(batch.instruction_count() == 2);
+		assert!(batch.transfer_time() > Duration::ZERO);
+		let_assert!(Ok(responses) = batch.sync_read(&[DEVICE_ID], 2, 2));
+		assert!(responses.len() == 1);
+		assert!(responses[0].motor_id == DEVICE_ID);
+		assert!(responses[0].data == [7, 8]);
+	});
+	let device_t = thread::spawn({
+		let kill_device = kill_device.clone();
+		move || {
+			let mut control_table = ControlTable::new(10);
+			while !kill_device.load(Relaxed) {
+				let packet = device.read(Duration::from_millis(50));
+				let packet = match packet {
+					Err(ReadError::Io(e)) if T::is_timeout_error(&e) => continue,
+					x => x,
+				};
+				let_assert!(Ok(packet) = packet);
+				match packet.instruction {
+					Instructions::SyncWrite { address, length, parameters } => {
+						for block in parameters.chunks(1 + length as usize) {
+							if block[0] == DEVICE_ID {
+								assert!(control_table.write(address, &block[1..]));
+							}
+						}
+					},
+					Instructions::Action => (),
+					Instructions::SyncRead { address, length, ids } => {
+						assert!(ids == [DEVICE_ID]);
+						let_assert!(Some(data) = control_table.read(address, length));
+						assert!(let Ok(()) = device.write_status(DEVICE_ID, 0, length as usize, |buffer| {
+							buffer.copy_from_slice(data);
+						}));
+					},
+					i => todo!("impl {:?}", i),
+				}
+			}
+		}
+	});
+	bus_t.join().unwrap();
+	kill_device.store(true, Relaxed);
+	device_t.join().unwrap();
+}
+
+#[test]
+fn test_device_status_return_level_and_delay() {
+	let kill_device = Arc::new(AtomicBool::new(false));
+	let (mut bus, mut device) = setup_bus();
+	let bus_t = thread::spawn(move || {
+		// Tell the bus about the Return Delay Time, so the read timeout accounts for it.
+		bus.set_return_delay_time(DEVICE_ID, Some(Duration::from_millis(20)));
+		let start = Instant::now();
+		assert!(let Ok(_) = bus.ping(DEVICE_ID));
+		assert!(start.elapsed() >= Duration::from_millis(20));
+
+		// The device does not answer the write, so the bus times out.
+		assert!(let Err(_) = bus.write_u8(DEVICE_ID, 5, 1));
+		let_assert!(Ok(response) = bus.read_u8(DEVICE_ID, 5));
+		assert!(response.data == 1);
+
+		// The status packet for the broadcast write is suppressed, so it can not be mistaken for the read response.
+		assert!(let Ok(_) = bus.write_u8(BROADCAST, 5, 2));
+		let_assert!(Ok(response) = bus.read_u8(DEVICE_ID, 5));
+		assert!(response.data == 2);
+	});
+	let device_t = thread::spawn({
+		let kill_device = kill_device.clone();
+		move || {
+			let mut control_table = ControlTable::new(10);
+			device.set_status_return_level(StatusReturnLevel::PingAndRead);
+			device.set_return_delay_time(Duration::from_millis(20));
+			while !kill_device.load(Relaxed) {
+				let packet = device.read(Duration::from_millis(50));
+				let packet = match packet {
+					Err(ReadError::Io(e)) if T::is_timeout_error(&e) => continue,
+					x => x,
+				};
+				let_assert!(Ok(packet) = packet);
+				if packet.id != DEVICE_ID && packet.id != BROADCAST {
+					continue;
+				}
+				// Always try to answer, and let the device decide whether to send a status packet.
+				match packet.instruction {
+					Instructions::Ping => {
+						assert!(let Ok(()) = device.write_status(DEVICE_ID, 0, 3, |buffer| {
+							buffer.copy_from_slice(&[0x34, 0x12, 1]);
+						}));
+					},
+					Instructions::Read { address, length } => {
+						let_assert!(Some(data) = control_table.read(address, length));
+						assert!(let Ok(()) = device.write_status(DEVICE_ID, 0, length as usize, |buffer| {
+							buffer.copy_from_slice(data);
+						}));
+					},
+					Instructions::Write { address, parameters } => {
+						assert!(control_table.write(address, parameters));
+						assert!(let Ok(()) = device.write_status_ok(DEVICE_ID));
+					},
+					i => todo!("impl {:?}", i),
+				}
+			}
+		}
+	});
+	bus_t.join().unwrap();
+	kill_device.store(true, Relaxed);
+	device_t.join().unwrap();
+}
+
+#[test]
+fn test_wait_for_status_packet_interrupted() {
+	let (mut bus, mut device) = setup_bus();
+	let bus_t = thread::spawn(move || {
+		// Nobody responds to the sync read, so the bus gives up and sends the next instruction.
+		assert!(let Ok(()) = bus.sync_read_cb(&[2, DEVICE_ID], 0, 1, |_| ()));
+		bus.ping(DEVICE_ID).is_ok()
+	});
+
+	let_assert!(Ok(instruction) = device.read(Duration::from_secs(1)));
+	assert!(let Instructions::SyncRead { .. } = instruction.instruction);
+	let start = Instant::now();
+	let_assert!(Ok(StatusWait::Interrupted) = device.wait_for_status_packet(2, Duration::from_secs(1)));
+	assert!(start.elapsed() < Duration::from_millis(500));
+
+	// The instruction that interrupted the wait must not be lost.
+	let_assert!(Ok(instruction) = device.read(Duration::from_secs(1)));
+	assert!(instruction.id == DEVICE_ID);
+	assert!(let Instructions::Ping = instruction.instruction);
+	assert!(let Ok(()) = device.write_status(DEVICE_ID, 0, 3, |buffer| buffer.copy_from_slice(&[0x34, 0x12, 7])));
+	assert!(bus_t.join().unwrap());
+}
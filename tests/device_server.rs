@@ -0,0 +1,424 @@
+use assert2::{assert, let_assert};
+use dynamixel2::instructions::{instruction_id, packet_id, BulkReadData, BulkWriteData, SyncWriteData};
+use dynamixel2::{
+	Bus, ControlTable, Device, DeviceServer, MotorError, MultiDeviceServer, ReadError, Response, SerialPort, StatusError,
+	StatusReturnLevel, TransferError,
+};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use test_log::test;
+
+mod mock_serial_port;
+use crate::mock_serial_port::MockSerialPort;
+
+type ReadBuffer = Vec<u8>;
+type WriteBuffer = Vec<u8>;
+type T = MockSerialPort;
+
+const DEVICE_ID: u8 = 1;
+const MODEL_NUMBER: u16 = 0x1234;
+const READ_ONLY_ADDRESS: u16 = 0;
+
+struct Registers {
+	data: Vec<u8>,
+	return_delay: Duration,
+}
+
+impl Registers {
+	fn new(return_delay: Duration) -> Self {
+		Self { data: vec![0; 16], return_delay }
+	}
+}
+
+impl ControlTable for Registers {
+	fn model_number(&self) -> u16 {
+		MODEL_NUMBER
+	}
+
+	fn firmware_version(&self) -> u8 {
+		7
+	}
+
+	fn read(&mut self, address: u16, buffer: &mut [u8]) -> Result<(), StatusError> {
+		let start = usize::from(address);
+		let data = self.data.get(start..start + buffer.len()).ok_or(StatusError::DataRange)?;
+		buffer.copy_from_slice(data);
+		Ok(())
+	}
+
+	fn validate_write(&self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		if address == READ_ONLY_ADDRESS {
+			return Err(StatusError::Access);
+		}
+		if usize::from(address) + data.len() > self.data.len() {
+			return Err(StatusError::DataRange);
+		}
+		Ok(())
+	}
+
+	fn write(&mut self, address: u16, data: &[u8]) -> Result<(), StatusError> {
+		let start = usize::from(address);
+		self.data[start..start + data.len()].copy_from_slice(data);
+		Ok(())
+	}
+
+	fn return_delay_time(&self) -> Duration {
+		self.return_delay
+	}
+}
+
+fn setup_bus() -> (Bus<ReadBuffer, WriteBuffer, T>, Device<ReadBuffer, WriteBuffer, T>) {
+	let serial_port = MockSerialPort::new(56700);
+	let device_serial_port = serial_port.device_port();
+	(
+		Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap(),
+		Device::with_buffers(device_serial_port, vec![0; 1024], vec![0; 1024]).unwrap(),
+	)
+}
+
+type ServeOne = Box<dyn FnMut(Duration) -> Result<(), TransferError<std::io::Error>>>;
+
+fn run_server<F>(test: F)
+where
+	F: FnOnce(&mut Bus<ReadBuffer, WriteBuffer, T>) + Send + 'static,
+{
+	run_device(
+		|device| {
+			let mut server = DeviceServer::new(device, DEVICE_ID, Registers::new(Duration::ZERO));
+			Box::new(move |timeout| server.serve_one(timeout))
+		},
+		test,
+	)
+}
+
+fn run_device<S, F>(make_server: S, test: F)
+where
+	S: FnOnce(Device<ReadBuffer, WriteBuffer, T>) -> ServeOne + Send + 'static,
+	F: FnOnce(&mut Bus<ReadBuffer, WriteBuffer, T>) + Send + 'static,
+{
+	let kill_device = Arc::new(AtomicBool::new(false));
+	let (mut bus, device) = setup_bus();
+	let bus_t = thread::spawn(move || test(&mut bus));
+	let device_t = thread::spawn({
+		let kill_device = kill_device.clone();
+		move || {
+			let mut serve_one = make_server(device);
+			while !kill_device.load(Relaxed) {
+				match serve_one(Duration::from_millis(50)) {
+					Err(TransferError::ReadError(ReadError::Io(e))) if T::is_timeout_error(&e) => continue,
+					x => assert!(let Ok(()) = x),
+				}
+			}
+		}
+	});
+	let result = bus_t.join();
+	kill_device.store(true, Relaxed);
+	device_t.join().unwrap();
+	result.unwrap();
+}
+
+fn status_error<E: std::fmt::Debug>(error: TransferError<E>) -> u8 {
+	let_assert!(TransferError::ReadError(ReadError::MotorError(MotorError { raw })) = error);
+	raw
+}
+
+#[test]
+fn test_ping() {
+	run_server(|bus| {
+		let_assert!(Ok(response) = bus.ping(DEVICE_ID));
+		assert!(response.data.model == MODEL_NUMBER);
+		assert!(response.data.firmware == 7);
+		let_assert!(Ok(responses) = bus.scan());
+		assert!(responses.len() == 1);
+	});
+}
+
+#[test]
+fn test_device_settings_unchanged() {
+	let (mut bus, mut device) = setup_bus();
+	device.set_return_delay_time(Duration::from_micros(100));
+	device.set_status_return_level(StatusReturnLevel::PingAndRead);
+	let mut server = DeviceServer::new(device, DEVICE_ID, Registers::new(Duration::ZERO));
+	let bus_t = thread::spawn(move || bus.write(DEVICE_ID, 4, &[1]).is_ok());
+
+	// The control table uses the default Status Return Level, so the write is answered anyway.
+	assert!(let Ok(()) = server.serve_one(Duration::from_secs(1)));
+	assert!(bus_t.join().unwrap());
+	assert!(server.device().return_delay_time() == Duration::from_micros(100));
+	assert!(server.device().status_return_level() == StatusReturnLevel::PingAndRead);
+}
+
+#[test]
+#[should_panic(expected = "invalid motor ID: 254")]
+fn test_set_invalid_id() {
+	let (_bus, device) = setup_bus();
+	let mut server = DeviceServer::new(device, DEVICE_ID, Registers::new(Duration::ZERO));
+	server.set_id(2);
+	assert!(server.id() == 2);
+	server.set_id(packet_id::BROADCAST);
+}
+
+#[test]
+fn test_read_write() {
+	run_server(|bus| {
+		assert!(let Ok(_) = bus.write_u16(DEVICE_ID, 4, 0x0102));
+		let_assert!(Ok(response) = bus.read_u16(DEVICE_ID, 4));
+		assert!(response.data == 0x0102);
+
+		let_assert!(Err(e) = bus.write_u8(DEVICE_ID, READ_ONLY_ADDRESS, 1));
+		assert!(status_error(e) == StatusError::Access as u8);
+		let_assert!(Err(e) = bus.read(DEVICE_ID, 15, 4));
+		assert!(status_error(e) == StatusError::DataRange as u8);
+	});
+}
+
+#[test]
+fn test_reg_write_action() {
+	run_server(|bus| {
+		let_assert!(Err(e) = bus.action(DEVICE_ID));
+		assert!(status_error(e) == StatusError::Instruction as u8);
+
+		assert!(let Ok(_) = bus.reg_write_u8(DEVICE_ID, 2, 42));
+		let_assert!(Ok(response) = bus.read_u8(DEVICE_ID, 2));
+		assert!(response.data == 0);
+		assert!(let Ok(_) = bus.action(DEVICE_ID));
+		let_assert!(Ok(response) = bus.read_u8(DEVICE_ID, 2));
+		assert!(response.data == 42);
+	});
+}
+
+#[test]
+fn test_unsupported_instructions() {
+	run_server(|bus| {
+		let_assert!(Err(e) = bus.factory_reset(DEVICE_ID, dynamixel2::instructions::FactoryResetKind::ResetAll));
+		assert!(status_error(e) == StatusError::Instruction as u8);
+		assert!(let Ok(_) = bus.reboot(DEVICE_ID));
+	});
+}
+
+#[test]
+fn test_sync_read() {
+	run_server(|bus| {
+		assert!(let Ok(_) = bus.write(DEVICE_ID, 4, &[1, 2]));
+		let_assert!(Ok(responses) = bus.sync_read(&[DEVICE_ID], 4, 2));
+		assert!(responses.len() == 1);
+		assert!(responses[0].data == [1, 2]);
+
+		// Motor 2 is absent, so the device must wait for its timeout before responding.
+		let mut responses = Vec::new();
+		assert!(let Ok(()) = bus.sync_read_cb(&[2, DEVICE_ID, 3], 4, 2, |response| {
+			responses.push(response.map(|response| (response.motor_id, response.data.to_owned())));
+		}));
+		assert!(responses.len() == 3);
+		assert!(let Err(ReadError::Io(_)) = &responses[0]);
+		let_assert!(Ok((DEVICE_ID, data)) = &responses[1]);
+		assert!(data == &[1, 2]);
+		assert!(let Err(ReadError::Io(_)) = &responses[2]);
+	});
+}
+
+#[test]
+fn test_bulk_read() {
+	run_server(|bus| {
+		assert!(let Ok(_) = bus.write(DEVICE_ID, 4, &[1, 2, 3]));
+		let mut responses = Vec::new();
+		let reads = [
+			BulkReadData { motor_id: 2, address: 0, count: 4 },
+			BulkReadData { motor_id: DEVICE_ID, address: 5, count: 2 },
+		];
+		assert!(let Ok(()) = bus.bulk_read_cb(&reads, |_read, response| {
+			responses.push(response.map(|response| (response.motor_id, response.data.to_owned())));
+		}));
+		assert!(responses.len() == 2);
+		assert!(let Err(ReadError::Io(_)) = &responses[0]);
+		let_assert!(Ok((DEVICE_ID, data)) = &responses[1]);
+		assert!(data == &[2, 3]);
+	});
+}
+
+#[test]
+fn test_sync_read_array() {
+	run_server(|bus| {
+		assert!(let Ok(_) = bus.write(DEVICE_ID, 4, &[1, 2]));
+		let_assert!(Ok([None, Some(response)]) = bus.sync_read_array::<2, 2>(&[2, DEVICE_ID], 4));
+		assert!(response.motor_id == DEVICE_ID);
+		assert!(response.data == [1, 2]);
+	});
+}
+
+#[test]
+fn test_bulk_read_array() {
+	run_server(|bus| {
+		assert!(let Ok(_) = bus.write(DEVICE_ID, 4, &[1, 2, 3]));
+		let reads = [
+			BulkReadData { motor_id: 2, address: 0, count: 2 },
+			BulkReadData { motor_id: DEVICE_ID, address: 5, count: 2 },
+		];
+		let_assert!(Ok([None, Some(response)]) = bus.bulk_read_array::<_, 2, 2>(&reads));
+		assert!(response.motor_id == DEVICE_ID);
+		assert!(response.data == [2, 3]);
+	});
+}
+
+#[test]
+fn test_sync_read_iter() {
+	run_server(|bus| {
+		assert!(let Ok(_) = bus.write(DEVICE_ID, 4, &[1, 2]));
+		let_assert!(Ok(mut responses) = bus.sync_read_iter(&[DEVICE_ID, 2], 4, 2));
+		let_assert!(Some(Ok(response)) = responses.next());
+		assert!(response.motor_id == DEVICE_ID);
+		assert!(response.data == [1, 2]);
+		assert!(responses.remaining() == [2]);
+
+		// Stop early without waiting for the absent motor, and keep using the bus.
+		let_assert!(Ok(response) = bus.read_u16(DEVICE_ID, 4));
+		assert!(response.data == u16::from_le_bytes([1, 2]));
+
+		let_assert!(Ok(mut responses) = bus.sync_read_iter(&[2, DEVICE_ID], 4, 2));
+		assert!(let Some(Err(ReadError::Io(_))) = responses.next());
+		let_assert!(Some(Ok(response)) = responses.next());
+		assert!(response.data == [1, 2]);
+		assert!(let None = responses.next());
+	});
+}
+
+#[test]
+fn test_bulk_read_iter() {
+	run_server(|bus| {
+		assert!(let Ok(_) = bus.write(DEVICE_ID, 4, &[1, 2, 3]));
+		let reads = [
+			BulkReadData { motor_id: 2, address: 0, count: 4 },
+			BulkReadData { motor_id: DEVICE_ID, address: 5, count: 2 },
+		];
+		let_assert!(Ok(mut responses) = bus.bulk_read_iter(&reads));
+		let_assert!(Some((read, Err(ReadError::Io(_)))) = responses.next());
+		assert!(read.motor_id == 2);
+		let_assert!(Some((read, Ok(response))) = responses.next());
+		assert!(read.motor_id == DEVICE_ID);
+		assert!(response.motor_id == DEVICE_ID);
+		assert!(response.data == [2, 3]);
+		assert!(let None = responses.next());
+	});
+}
+
+#[test]
+fn test_array_response() {
+	run_server(|bus| {
+		assert!(let Ok(_) = bus.write(DEVICE_ID, 4, &[1, 2]));
+		let read = |buffer: &mut [u8]| buffer.copy_from_slice(&[4, 0, 2, 0]);
+		let_assert!(Ok(packet) = bus.transfer_single(DEVICE_ID, instruction_id::READ, 4, 2, read));
+		let_assert!(Ok(response) = Response::<[u8; 2]>::try_from(packet));
+		assert!(response.data == [1, 2]);
+
+		let_assert!(Ok(packet) = bus.transfer_single(DEVICE_ID, instruction_id::READ, 4, 2, read));
+		assert!(let Err(_) = Response::<[u8; 4]>::try_from(packet));
+	});
+}
+
+#[test]
+fn test_sync_write_bulk_write() {
+	run_server(|bus| {
+		assert!(let Ok(()) = bus.sync_write(4, 2, &[
+			SyncWriteData { motor_id: 2, data: [9, 9] },
+			SyncWriteData { motor_id: DEVICE_ID, data: [1, 2] },
+		]));
+		let_assert!(Ok(response) = bus.read(DEVICE_ID, 4, 2));
+		assert!(response.data == [1, 2]);
+
+		assert!(let Ok(()) = bus.bulk_write(&[
+			BulkWriteData { motor_id: DEVICE_ID, address: 8, data: &[3, 4, 5][..] },
+			BulkWriteData { motor_id: 2, address: 0, data: &[9][..] },
+		]));
+		let_assert!(Ok(response) = bus.read(DEVICE_ID, 8, 3));
+		assert!(response.data == [3, 4, 5]);
+	});
+}
+
+const IMU_ID: u8 = 100;
+const FEET_ID: u8 = 101;
+
+/// Run a test against a server for the IMU and the feet, with the given Return Delay Time for the feet.
+fn run_multi_server<F>(feet_return_delay: Duration, test: F)
+where
+	F: FnOnce(&mut Bus<ReadBuffer, WriteBuffer, T>) + Send + 'static,
+{
+	run_device(
+		move |device| {
+			let imu: Box<dyn ControlTable> = Box::new(Registers::new(Duration::ZERO));
+			let feet: Box<dyn ControlTable> = Box::new(Registers::new(feet_return_delay));
+			// Register the IDs out of order, to check that responses are still sorted.
+			let mut server = MultiDeviceServer::new(device, [(FEET_ID, feet), (IMU_ID, imu)]);
+			Box::new(move |timeout| server.serve_one(timeout))
+		},
+		test,
+	)
+}
+
+#[test]
+fn test_multi_device_ping() {
+	run_multi_server(Duration::ZERO, |bus| {
+		assert!(let Ok(_) = bus.ping(IMU_ID));
+		assert!(let Ok(_) = bus.ping(FEET_ID));
+		assert!(let Err(_) = bus.ping(DEVICE_ID));
+
+		let_assert!(Ok(responses) = bus.scan());
+		let ids: Vec<u8> = responses.iter().map(|response| response.as_ref().unwrap().motor_id).collect();
+		assert!(ids == [IMU_ID, FEET_ID]);
+	});
+}
+
+#[test]
+fn test_multi_device_read_write() {
+	run_multi_server(Duration::ZERO, |bus| {
+		assert!(let Ok(_) = bus.write_u8(IMU_ID, 2, 1));
+		assert!(let Ok(_) = bus.write_u8(FEET_ID, 2, 2));
+		assert!(let Ok(_) = bus.write_u8(packet_id::BROADCAST, 3, 9));
+		let_assert!(Ok(response) = bus.read(IMU_ID, 2, 2));
+		assert!(response.data == [1, 9]);
+		let_assert!(Ok(response) = bus.read(FEET_ID, 2, 2));
+		assert!(response.data == [2, 9]);
+
+		assert!(let Ok(()) = bus.sync_write(4, 1, &[
+			SyncWriteData { motor_id: FEET_ID, data: [5] },
+			SyncWriteData { motor_id: IMU_ID, data: [6] },
+		]));
+		let_assert!(Ok(response) = bus.read_u8(IMU_ID, 4));
+		assert!(response.data == 6);
+		let_assert!(Ok(response) = bus.read_u8(FEET_ID, 4));
+		assert!(response.data == 5);
+	});
+}
+
+#[test]
+fn test_multi_device_sync_read() {
+	run_multi_server(Duration::ZERO, |bus| {
+		assert!(let Ok(_) = bus.write_u8(IMU_ID, 4, 1));
+		assert!(let Ok(_) = bus.write_u8(FEET_ID, 4, 2));
+
+		// Motor 2 is absent, so the feet must wait for its timeout after the IMU responded.
+		let mut responses = Vec::new();
+		assert!(let Ok(()) = bus.sync_read_cb(&[IMU_ID, 2, FEET_ID], 4, 1, |response| {
+			responses.push(response.map(|response| (response.motor_id, response.data.to_owned())));
+		}));
+		assert!(responses.len() == 3);
+		let_assert!(Ok((IMU_ID, data)) = &responses[0]);
+		assert!(data == &[1]);
+		assert!(let Err(ReadError::Io(_)) = &responses[1]);
+		let_assert!(Ok((FEET_ID, data)) = &responses[2]);
+		assert!(data == &[2]);
+	});
+}
+
+#[test]
+fn test_multi_device_return_delay() {
+	run_multi_server(Duration::from_millis(20), |bus| {
+		// Tell the bus about the Return Delay Time, so the read timeout accounts for it.
+		bus.set_return_delay_time(FEET_ID, Some(Duration::from_millis(20)));
+		let start = Instant::now();
+		assert!(let Ok(_) = bus.ping(FEET_ID));
+		assert!(start.elapsed() >= Duration::from_millis(20));
+	});
+}
@@ -0,0 +1,98 @@
+use assert2::{assert, let_assert};
+use dynamixel2::fault_injection::{FaultySerialPort, PacketFault, ReadFault, ScriptedFaults};
+use dynamixel2::simulation::SimulatedMotor;
+use dynamixel2::{Bus, Device, InvalidMessage, ReadError, SerialPort, TransferError};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use test_log::test;
+
+mod mock_serial_port;
+use crate::mock_serial_port::MockSerialPort;
+
+type T = FaultySerialPort<MockSerialPort, ScriptedFaults>;
+
+const MOTOR_ID: u8 = 1;
+
+/// Run a test against a simulated motor, with scripted faults on both sides of the line.
+fn run<F>(bus_faults: ScriptedFaults, device_faults: ScriptedFaults, test: F)
+where
+	F: FnOnce(&mut Bus<Vec<u8>, Vec<u8>, T>) + Send + 'static,
+{
+	let serial_port = MockSerialPort::new(56700);
+	let device_serial_port = FaultySerialPort::new(serial_port.device_port(), device_faults);
+	let mut bus = Bus::with_buffers(FaultySerialPort::new(serial_port, bus_faults), vec![0; 1024], vec![0; 1024]).unwrap();
+	let device = Device::with_buffers(device_serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+
+	let kill_motor = Arc::new(AtomicBool::new(false));
+	let bus_t = thread::spawn(move || test(&mut bus));
+	let motor_t = thread::spawn({
+		let kill_motor = kill_motor.clone();
+		move || {
+			let mut motor = SimulatedMotor::new(device, MOTOR_ID);
+			while !kill_motor.load(Relaxed) {
+				match motor.serve_one(Duration::from_millis(5)) {
+					Err(TransferError::ReadError(ReadError::Io(e))) if T::is_timeout_error(&e) => continue,
+					x => assert!(let Ok(()) = x),
+				}
+			}
+		}
+	});
+	let result = bus_t.join();
+	kill_motor.store(true, Relaxed);
+	motor_t.join().unwrap();
+	result.unwrap();
+}
+
+#[test]
+fn test_corrupted_status_packets() {
+	let device_faults = ScriptedFaults::new()
+		.then_packet(PacketFault::FlipBit { index: 9, bit: 3 })
+		.then_packet(None)
+		.then_packet(PacketFault::InsertGarbage { byte: 0xFF, count: 5 })
+		.then_packet(PacketFault::Duplicate)
+		.then_packet(None)
+		.then_packet(PacketFault::DropByte(13))
+		.then_packet(None);
+
+	run(ScriptedFaults::new(), device_faults, |bus| {
+		// A flipped bit is caught by the checksum.
+		let_assert!(Err(TransferError::ReadError(ReadError::InvalidMessage(e))) = bus.ping(MOTOR_ID));
+		assert!(let InvalidMessage::InvalidChecksum(_) = e);
+		assert!(let Ok(_) = bus.ping(MOTOR_ID));
+
+		// Garbage before the header is skipped.
+		assert!(let Ok(_) = bus.ping(MOTOR_ID));
+
+		// A duplicated packet does not confuse the next transaction.
+		assert!(let Ok(_) = bus.ping(MOTOR_ID));
+		assert!(let Ok(_) = bus.ping(MOTOR_ID));
+
+		// A dropped byte leads to a timeout.
+		let_assert!(Err(TransferError::ReadError(ReadError::Io(e))) = bus.ping(MOTOR_ID));
+		assert!(T::is_timeout_error(&e));
+		assert!(let Ok(_) = bus.ping(MOTOR_ID));
+	});
+}
+
+#[test]
+fn test_read_faults() {
+	let bus_faults = ScriptedFaults::new()
+		.then_read(ReadFault::SplitRead(3))
+		.then_read(ReadFault::SplitRead(1))
+		.then_read(None)
+		.then_read(ReadFault::Delay);
+
+	run(bus_faults, ScriptedFaults::new(), |bus| {
+		// Split reads are reassembled by the bus.
+		let_assert!(Ok(response) = bus.ping(MOTOR_ID));
+		assert!(response.motor_id == MOTOR_ID);
+
+		// A delayed response leads to a timeout.
+		let_assert!(Err(TransferError::ReadError(ReadError::Io(e))) = bus.ping(MOTOR_ID));
+		assert!(T::is_timeout_error(&e));
+		assert!(bus.serial_port().schedule().remaining_read_faults() == 0);
+	});
+}
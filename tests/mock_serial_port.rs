@@ -53,8 +53,12 @@ impl SerialPort for MockSerialPort {
 				return Err(std::io::ErrorKind::TimedOut.into());
 			}
 			if let Ok(data) = self.read_buffer.try_lock() {
-				break data;
+				if !data.is_empty() {
+					break data;
+				}
 			}
+			// Let the other end of the port run instead of spinning, so it can send its data in time.
+			std::thread::yield_now();
 		};
 		let len = buffer.len().min(data.len());
 		buffer[..len].copy_from_slice(&data.drain(..len).collect::<Vec<u8>>());
@@ -76,4 +80,8 @@ impl SerialPort for MockSerialPort {
 	fn is_timeout_error(error: &Self::Error) -> bool {
 		error.kind() == std::io::ErrorKind::TimedOut
 	}
+
+	fn remaining_time(&self, deadline: &Self::Instant) -> Option<Duration> {
+		Some(deadline.saturating_duration_since(Instant::now()))
+	}
 }
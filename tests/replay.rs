@@ -0,0 +1,89 @@
+use assert2::{assert, let_assert};
+use dynamixel2::capture::{CaptureReader, CaptureWriter, CapturedPacket, CapturingSerialPort};
+use dynamixel2::instructions::instruction_id;
+use dynamixel2::{
+	Bus, Device, Instruction, Instructions, Packet, ReadError, ReplaySerialPort, ScriptedSerialPort, SerialPort, TransferError,
+};
+use std::time::{Duration, Instant};
+use test_log::test;
+
+/// Record a session with a ping and a read, and return the captured packets.
+fn record_session() -> Vec<CapturedPacket> {
+	let serial_port = ScriptedSerialPort::new(57600)
+		.expect(1, Instructions::Ping)
+		.reply(1, [0x06, 0x04, 0x26])
+		.expect(1, Instructions::Read { address: 132, length: 4 })
+		.reply(1, 1234u32.to_le_bytes());
+	let capture = CaptureWriter::new(Vec::new()).unwrap();
+	let mut bus = Bus::with_buffers(CapturingSerialPort::new(serial_port, capture), vec![0; 1024], vec![0; 1024]).unwrap();
+	bus.ping(1).unwrap();
+	bus.read_u32(1, 132).unwrap();
+
+	let (_, capture) = bus.into_serial_port().into_parts();
+	let data = capture.into_inner();
+	CaptureReader::new(data.as_slice()).unwrap().collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn test_replay_into_bus() {
+	let mut bus = Bus::with_buffers(ReplaySerialPort::for_bus(record_session(), 57600), vec![0; 1024], vec![0; 1024]).unwrap();
+	let_assert!(Ok(response) = bus.ping(1));
+	assert!(response.data.model == 0x0406);
+	let_assert!(Ok(response) = bus.read_u32(1, 132));
+	assert!(response.data == 1234);
+	assert!(bus.serial_port().is_finished());
+	assert!(bus.serial_port().mismatches() == 0);
+
+	let_assert!(Err(TransferError::ReadError(ReadError::Io(e))) = bus.ping(1));
+	assert!(ReplaySerialPort::is_timeout_error(&e));
+	assert!(bus.serial_port().mismatches() == 1);
+}
+
+#[test]
+fn test_replay_mismatch() {
+	let mut bus = Bus::with_buffers(ReplaySerialPort::for_bus(record_session(), 57600), vec![0; 1024], vec![0; 1024]).unwrap();
+	// The recorded reply comes from motor 1, so the bus rejects it.
+	assert!(let Err(_) = bus.ping(2));
+	assert!(bus.serial_port().mismatches() == 1);
+	assert!(let Ok(_) = bus.read_u32(1, 132));
+	assert!(bus.serial_port().mismatches() == 1);
+}
+
+#[test]
+fn test_replay_into_device() {
+	let serial_port = ReplaySerialPort::for_device(record_session(), 57600);
+	let mut device = Device::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+
+	let_assert!(Ok(packet) = device.read_instruction_packet_timeout(Duration::from_millis(10)));
+	assert!(packet.packet_id() == 1);
+	assert!(packet.instruction_id() == instruction_id::PING);
+
+	// The read instruction is only delivered after the status packet for the ping has been written.
+	let_assert!(Err(ReadError::Io(e)) = device.read_instruction_packet_timeout(Duration::from_millis(10)));
+	assert!(ReplaySerialPort::is_timeout_error(&e));
+	assert!(let Ok(()) = device.write_status(1, 0, 3, |buffer| buffer.copy_from_slice(&[0x06, 0x04, 0x26])));
+
+	let_assert!(Ok(packet) = device.read_instruction_packet_timeout(Duration::from_millis(10)));
+	let_assert!(Ok(instruction) = Instruction::<Vec<u8>>::try_from(packet));
+	assert!(instruction.instruction == Instructions::Read { address: 132, length: 4 });
+	assert!(let Ok(()) = device.write_status(1, 0, 4, |buffer| buffer.copy_from_slice(&1234u32.to_le_bytes())));
+	assert!(device.serial_port().is_finished());
+	assert!(device.serial_port().mismatches() == 0);
+}
+
+#[test]
+fn test_replay_pacing() {
+	let mut packets = record_session();
+	let start = packets[0].timestamp;
+	for (i, packet) in packets.iter_mut().enumerate() {
+		packet.timestamp = start + Duration::from_millis(20) * i as u32;
+	}
+
+	let serial_port = ReplaySerialPort::for_bus(packets, 57600).with_pacing(true);
+	let mut bus = Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+	let start = Instant::now();
+	assert!(let Ok(_) = bus.ping(1));
+	assert!(start.elapsed() >= Duration::from_millis(20));
+	assert!(let Ok(_) = bus.read_u32(1, 132));
+	assert!(start.elapsed() >= Duration::from_millis(40));
+}
@@ -0,0 +1,81 @@
+use assert2::{assert, let_assert};
+use dynamixel2::instructions::packet_id::BROADCAST;
+use dynamixel2::{Bus, Instructions, ReadError, ScriptedSerialPort, SerialPort, TransferError};
+use test_log::test;
+
+fn bus(serial_port: ScriptedSerialPort) -> Bus<Vec<u8>, Vec<u8>, ScriptedSerialPort> {
+	Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap()
+}
+
+#[test]
+fn test_scripted_transactions() {
+	let mut bus = bus(ScriptedSerialPort::new(57600)
+		.expect(1, Instructions::Ping)
+		.reply(1, [0x06, 0x04, 0x26])
+		.expect(1, Instructions::Write {
+			address: 116,
+			parameters: 2000u32.to_le_bytes().to_vec(),
+		})
+		.reply(1, [])
+		.expect(BROADCAST, Instructions::SyncRead {
+			address: 132,
+			length: 4,
+			ids: vec![1, 2],
+		})
+		.reply(1, 10u32.to_le_bytes())
+		.reply(2, 20u32.to_le_bytes())
+		.expect(3, Instructions::Read { address: 132, length: 4 })
+		.reply_error(3, 0x80 | 0x07, 0u32.to_le_bytes())
+		.expect(4, Instructions::Ping));
+
+	let_assert!(Ok(response) = bus.ping(1));
+	assert!(response.data.model == 0x0406);
+	assert!(response.data.firmware == 0x26);
+
+	assert!(let Ok(_) = bus.write_u32(1, 116, 2000));
+
+	let_assert!(Ok(responses) = bus.sync_read_u32(&[1, 2], 132));
+	assert!(responses.len() == 2);
+	assert!(responses[0].data == 10);
+	assert!(responses[1].data == 20);
+
+	let_assert!(Err(TransferError::ReadError(ReadError::MotorError(e))) = bus.read_u32(3, 132));
+	assert!(e.alert());
+	assert!(e.error_number() == 7);
+
+	let_assert!(Err(TransferError::ReadError(ReadError::Io(e))) = bus.ping(4));
+	assert!(ScriptedSerialPort::is_timeout_error(&e));
+	assert!(bus.serial_port().remaining_expectations() == 0);
+}
+
+#[test]
+#[should_panic(expected = "instruction 2 does not match the expectation")]
+fn test_scripted_mismatch() {
+	let mut bus = bus(ScriptedSerialPort::new(57600)
+		.expect(1, Instructions::Ping)
+		.reply(1, [0x06, 0x04, 0x26])
+		.expect(1, Instructions::Read { address: 132, length: 4 }));
+
+	let _ = bus.ping(1);
+	let _ = bus.read_u32(1, 128);
+}
+
+#[test]
+#[should_panic(expected = "unexpected instruction after all 1 expectations were consumed")]
+fn test_scripted_unexpected() {
+	let mut bus = bus(ScriptedSerialPort::new(57600)
+		.expect(1, Instructions::Ping)
+		.reply(1, [0x06, 0x04, 0x26]));
+	let _ = bus.ping(1);
+	let _ = bus.ping(1);
+}
+
+#[test]
+#[should_panic(expected = "dropped with 1 unconsumed expectation(s)")]
+fn test_scripted_unconsumed() {
+	let mut bus = bus(ScriptedSerialPort::new(57600)
+		.expect(1, Instructions::Ping)
+		.reply(1, [0x06, 0x04, 0x26])
+		.expect(1, Instructions::Reboot));
+	let _ = bus.ping(1);
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "serde")]
+
+use assert2::{assert, let_assert};
+use dynamixel2::instructions::{BulkReadData, BulkWriteData, FactoryResetKind, Ping, SyncWriteData};
+use dynamixel2::{
+	ExpectedCount, FactoryReset, Instruction, Instructions, InvalidMessage, InvalidParameterCount, IoError, MotorError, ReadError,
+	Response, TransferError, WriteError,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Check that a value serializes to the given JSON, and deserializes back to the same value.
+///
+/// The values are compared by their debug representation, since not all types implement `PartialEq`.
+#[track_caller]
+fn check<T>(value: T, json: &str)
+where
+	T: Serialize + DeserializeOwned + std::fmt::Debug,
+{
+	let_assert!(Ok(serialized) = serde_json::to_string(&value));
+	assert!(serialized == json);
+	let_assert!(Ok(deserialized) = serde_json::from_str::<T>(json));
+	assert!(format!("{deserialized:?}") == format!("{value:?}"));
+}
+
+#[test]
+fn test_responses() {
+	check(
+		Response {
+			motor_id: 1,
+			alert: false,
+			data: 1234u32,
+		},
+		r#"{"motor_id":1,"alert":false,"data":1234}"#,
+	);
+	check(
+		Response {
+			motor_id: 2,
+			alert: true,
+			data: Ping { model: 1060, firmware: 38 },
+		},
+		r#"{"motor_id":2,"alert":true,"data":{"model":1060,"firmware":38}}"#,
+	);
+}
+
+#[test]
+fn test_instruction_data() {
+	check(
+		SyncWriteData {
+			motor_id: 1,
+			data: 2048u32,
+		},
+		r#"{"motor_id":1,"data":2048}"#,
+	);
+	check(
+		BulkWriteData {
+			motor_id: 1,
+			address: 116,
+			data: vec![0u8, 8, 0, 0],
+		},
+		r#"{"motor_id":1,"address":116,"data":[0,8,0,0]}"#,
+	);
+	check(
+		BulkReadData {
+			motor_id: 1,
+			address: 132,
+			count: 4,
+		},
+		r#"{"motor_id":1,"address":132,"count":4}"#,
+	);
+	check(FactoryResetKind::KeepIdAndBaudRate, r#""keep_id_and_baud_rate""#);
+}
+
+#[test]
+fn test_instructions() {
+	check(
+		Instruction {
+			id: 1,
+			instruction: Instructions::<Vec<u8>>::Ping,
+		},
+		r#"{"id":1,"instruction":"ping"}"#,
+	);
+	check(
+		Instructions::<Vec<u8>>::Read { address: 132, length: 4 },
+		r#"{"read":{"address":132,"length":4}}"#,
+	);
+	check(
+		Instructions::SyncWrite {
+			address: 116,
+			length: 1,
+			parameters: vec![1u8, 10, 2, 20],
+		},
+		r#"{"sync_write":{"address":116,"length":1,"parameters":[1,10,2,20]}}"#,
+	);
+	check(
+		Instructions::<Vec<u8>>::FactoryReset(FactoryReset::ExceptId),
+		r#"{"factory_reset":"except_id"}"#,
+	);
+	check(
+		Instructions::Unknown {
+			instruction: 0x99,
+			parameters: Vec::<u8>::new(),
+		},
+		r#"{"unknown":{"instruction":153,"parameters":[]}}"#,
+	);
+}
+
+#[test]
+fn test_errors() {
+	check(MotorError { raw: 0x82 }, r#"{"raw":130}"#);
+	check(
+		ReadError::<i32>::InvalidMessage(InvalidMessage::InvalidParameterCount(InvalidParameterCount {
+			actual: 3,
+			expected: ExpectedCount::Exact(4),
+		})),
+		r#"{"invalid_message":{"invalid_parameter_count":{"actual":3,"expected":{"exact":4}}}}"#,
+	);
+	check(TransferError::<i32>::ReadError(ReadError::Io(5)), r#"{"read_error":{"io":5}}"#);
+}
+
+#[test]
+fn test_io_errors() {
+	// The error type of a bus on a real serial port.
+	let error: TransferError<std::io::Error> = ReadError::Io(std::io::Error::new(std::io::ErrorKind::TimedOut, "read timed out")).into();
+	check(
+		error.map_io(IoError::from),
+		r#"{"read_error":{"io":{"kind":"TimedOut","message":"read timed out"}}}"#,
+	);
+
+	let error: WriteError<std::io::Error> = WriteError::Write(std::io::ErrorKind::BrokenPipe.into());
+	check(
+		error.map_io(IoError::from),
+		r#"{"write":{"kind":"BrokenPipe","message":"broken pipe"}}"#,
+	);
+}
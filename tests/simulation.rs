@@ -0,0 +1,93 @@
+use assert2::{assert, let_assert};
+use dynamixel2::simulation::xm430::{address, hardware_error};
+use dynamixel2::simulation::{SimulatedMotor, SimulationClock};
+use dynamixel2::{Bus, Device, ReadError, SerialPort, TransferError};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use test_log::test;
+
+mod mock_serial_port;
+use crate::mock_serial_port::MockSerialPort;
+
+type ReadBuffer = Vec<u8>;
+type WriteBuffer = Vec<u8>;
+type T = MockSerialPort;
+
+const MOTOR_ID: u8 = 3;
+
+fn run_simulation<F>(test: F)
+where
+	F: FnOnce(&mut Bus<ReadBuffer, WriteBuffer, T>) + Send + 'static,
+{
+	let serial_port = MockSerialPort::new(56700);
+	let device_serial_port = serial_port.device_port();
+	let mut bus = Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+	let device = Device::with_buffers(device_serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+
+	let kill_motor = Arc::new(AtomicBool::new(false));
+	let bus_t = thread::spawn(move || test(&mut bus));
+	let motor_t = thread::spawn({
+		let kill_motor = kill_motor.clone();
+		move || {
+			let mut motor = SimulatedMotor::new(device, MOTOR_ID);
+			motor.set_clock(SimulationClock::FixedStep(Duration::from_millis(10)));
+			while !kill_motor.load(Relaxed) {
+				match motor.serve_one(Duration::from_millis(5)) {
+					Err(TransferError::ReadError(ReadError::Io(e))) if T::is_timeout_error(&e) => continue,
+					x => assert!(let Ok(()) = x),
+				}
+			}
+		}
+	});
+	let result = bus_t.join();
+	kill_motor.store(true, Relaxed);
+	motor_t.join().unwrap();
+	result.unwrap();
+}
+
+#[test]
+fn test_simulated_motor_moves_to_goal() {
+	run_simulation(|bus| {
+		let_assert!(Ok(response) = bus.ping(MOTOR_ID));
+		assert!(response.data.model == 1020);
+
+		assert!(let Ok(_) = bus.write_u32(MOTOR_ID, address::PROFILE_VELOCITY, 200));
+		assert!(let Ok(_) = bus.write_u8(MOTOR_ID, address::TORQUE_ENABLE, 1));
+		assert!(let Ok(_) = bus.write_u32(MOTOR_ID, address::GOAL_POSITION, 1000));
+
+		let mut position = 0;
+		for _ in 0..200 {
+			let_assert!(Ok(response) = bus.read_u32(MOTOR_ID, address::PRESENT_POSITION));
+			position = response.data as i32;
+			if (position - 1000).abs() <= 10 {
+				break;
+			}
+		}
+		assert!((position - 1000).abs() <= 10);
+	});
+}
+
+#[test]
+fn test_simulated_motor_errors() {
+	run_simulation(|bus| {
+		let_assert!(Err(e) = bus.write_u8(MOTOR_ID, address::OPERATING_MODE, 2));
+		let_assert!(TransferError::ReadError(ReadError::MotorError(e)) = e);
+		assert!(e.error_number() == 6);
+		assert!(!e.alert());
+
+		// Raise the minimum voltage limit above the supply voltage to trigger an input voltage error.
+		assert!(let Ok(_) = bus.write_u16(MOTOR_ID, address::MIN_VOLTAGE_LIMIT, 130));
+		let_assert!(Ok(response) = bus.read_u8(MOTOR_ID, address::HARDWARE_ERROR_STATUS));
+		assert!(response.data == hardware_error::INPUT_VOLTAGE);
+		assert!(response.alert);
+
+		assert!(let Ok(_) = bus.write_u16(MOTOR_ID, address::MIN_VOLTAGE_LIMIT, 95));
+		assert!(let Ok(_) = bus.reboot(MOTOR_ID));
+		let_assert!(Ok(response) = bus.read_u8(MOTOR_ID, address::HARDWARE_ERROR_STATUS));
+		assert!(response.data == 0);
+		assert!(!response.alert);
+	});
+}
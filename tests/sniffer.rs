@@ -0,0 +1,101 @@
+use assert2::{assert, let_assert};
+use dynamixel2::instructions::packet_id::BROADCAST;
+use dynamixel2::simulation::xm430::address;
+use dynamixel2::simulation::SimulatedMotor;
+use dynamixel2::{Bus, Device, Instructions, ReadError, SerialPort, SniffedContent, Sniffer, TransferError, VirtualBus, VirtualSerialPort};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use test_log::test;
+
+const BAUD_RATE: u32 = 1_000_000;
+
+#[test]
+fn test_sniffer() {
+	let virtual_bus = VirtualBus::new();
+	let mut bus = Bus::with_buffers(virtual_bus.connect(BAUD_RATE), vec![0; 1024], vec![0; 1024]).unwrap();
+	let mut sniffer = Sniffer::with_buffer(virtual_bus.connect(BAUD_RATE), vec![0; 1024]);
+
+	let stop = Arc::new(AtomicBool::new(false));
+	let motors: Vec<_> = [1, 2]
+		.into_iter()
+		.map(|id| {
+			let device = Device::with_buffers(virtual_bus.connect(BAUD_RATE), vec![0; 1024], vec![0; 1024]).unwrap();
+			let stop = stop.clone();
+			thread::spawn(move || {
+				let mut motor = SimulatedMotor::new(device, id);
+				while !stop.load(Relaxed) {
+					match motor.serve_one(Duration::from_millis(5)) {
+						Err(TransferError::ReadError(ReadError::Io(e))) if VirtualSerialPort::is_timeout_error(&e) => continue,
+						x => assert!(let Ok(()) = x),
+					}
+				}
+			})
+		})
+		.collect();
+
+	assert!(let Ok(_) = bus.ping(1));
+	assert!(let Ok(_) = bus.write_u32(BROADCAST, address::GOAL_POSITION, 100));
+	assert!(let Ok(_) = bus.sync_read_u32(&[1, 2], address::GOAL_POSITION));
+	assert!(let Err(_) = bus.ping(3));
+
+	stop.store(true, Relaxed);
+	for motor in motors {
+		motor.join().unwrap();
+	}
+
+	let mut packets = Vec::new();
+	while let Some(packet) = sniffer.next_packet(Duration::from_millis(20)).unwrap() {
+		packets.push(packet);
+	}
+	assert!(packets.len() == 7);
+	assert!(packets.iter().enumerate().all(|(i, packet)| packet.sequence == i as u64));
+	assert!(packets.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+
+	let_assert!(SniffedContent::Instruction(instruction) = &packets[0].content);
+	assert!(instruction.id == 1);
+	assert!(instruction.instruction == Instructions::Ping);
+	let_assert!(SniffedContent::Status(status) = &packets[1].content);
+	assert!(status.motor_id == 1);
+	assert!(status.reply_to == Some(0));
+
+	let_assert!(SniffedContent::Instruction(instruction) = &packets[2].content);
+	assert!(instruction.id == BROADCAST);
+	assert!(instruction.instruction == Instructions::Write {
+		address: address::GOAL_POSITION,
+		parameters: 100u32.to_le_bytes().to_vec(),
+	});
+
+	let_assert!(SniffedContent::Instruction(instruction) = &packets[3].content);
+	assert!(let Instructions::SyncRead { .. } = instruction.instruction);
+	for (packet, motor_id) in packets[4..6].iter().zip([1, 2]) {
+		let_assert!(SniffedContent::Status(status) = &packet.content);
+		assert!(status.motor_id == motor_id);
+		assert!(status.parameters == 100u32.to_le_bytes());
+		assert!(status.reply_to == Some(3));
+	}
+
+	let_assert!(SniffedContent::Instruction(instruction) = &packets[6].content);
+	assert!(instruction.id == 3);
+	assert!(virtual_bus.collision_count() == 0);
+}
+
+#[test]
+fn test_sniffer_oversized_packet() {
+	let virtual_bus = VirtualBus::new();
+	let mut port = virtual_bus.connect(BAUD_RATE);
+	let mut sniffer = Sniffer::with_buffer(virtual_bus.connect(BAUD_RATE), vec![0; 32]);
+
+	// The header of a packet that can never fit in the buffer, followed by a valid ping response.
+	let ping_response = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5D];
+	assert!(let Ok(()) = port.write_all(&[0xFF, 0xFF, 0xFD, 0x00, 0x01, 0xFF, 0xFF, 0x55, 0x00]));
+	assert!(let Ok(()) = port.write_all(&ping_response));
+
+	assert!(let Err(ReadError::BufferFull(_)) = sniffer.next_packet(Duration::from_millis(20)));
+	let_assert!(Ok(Some(packet)) = sniffer.next_packet(Duration::from_millis(20)));
+	let_assert!(SniffedContent::Status(status) = &packet.content);
+	assert!(status.motor_id == 1);
+	assert!(status.parameters == [0x06, 0x04, 0x26]);
+}
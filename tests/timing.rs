@@ -0,0 +1,70 @@
+use assert2::{assert, let_assert};
+use dynamixel2::capture::{CapturedPacket, Direction};
+use dynamixel2::instructions::instruction_id;
+use dynamixel2::{encode_instruction, Bus, ReplaySerialPort};
+use std::time::{Duration, SystemTime};
+use test_log::test;
+
+/// Encode a packet as stored in a capture.
+fn packet(timestamp: SystemTime, packet_id: u8, instruction_id: u8, parameters: &[u8]) -> CapturedPacket {
+	let mut buffer = vec![0; 64];
+	let len = encode_instruction(&mut buffer, packet_id, instruction_id, parameters.len(), |buffer| {
+		buffer.copy_from_slice(parameters)
+	})
+	.unwrap();
+	buffer.truncate(len);
+	CapturedPacket {
+		timestamp,
+		direction: Direction::Unknown,
+		data: buffer,
+	}
+}
+
+#[test]
+fn test_transaction_timing() {
+	// A read of 4 bytes from motor 1, answered 20 milliseconds later.
+	let start = SystemTime::now();
+	let packets = vec![
+		packet(start, 1, instruction_id::READ, &[132, 0, 4, 0]),
+		packet(start + Duration::from_millis(20), 1, instruction_id::STATUS, &[0, 0xD2, 0x04, 0, 0]),
+	];
+
+	let serial_port = ReplaySerialPort::for_bus(packets, 57600).with_pacing(true);
+	let mut bus = Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+	bus.set_return_delay_time(1, Some(Duration::from_micros(500)));
+	assert!(let Ok(()) = bus.set_instruction_timeout(instruction_id::READ, Some(Duration::from_millis(100))));
+	let_assert!(Ok(response) = bus.read_u32(1, 132));
+	assert!(response.data == 1234);
+
+	let_assert!(Some(timing) = bus.last_transaction_timing());
+	assert!(timing.instruction_id == instruction_id::READ);
+	assert!(timing.motor_id == Some(1));
+	assert!(timing.bytes_sent == 14);
+	assert!(timing.bytes_received == 15);
+	assert!(timing.responses == 1);
+	assert!(timing.transmit_time == Duration::from_nanos(2_430_556));
+	assert!(timing.return_delay == Some(Duration::from_micros(500)));
+	let_assert!(Some(first_byte) = timing.first_byte);
+	let_assert!(Some(duration) = timing.duration);
+	assert!(first_byte >= Duration::from_millis(20));
+	assert!(duration >= first_byte);
+	assert!(let Some(_) = timing.response_delay());
+
+	let summary = bus.timing_summary();
+	assert!(summary.transactions == 1);
+	assert!(summary.responses == 1);
+	assert!(summary.measured_transactions == 1);
+	assert!(summary.total_time == duration);
+	let_assert!(Some(utilization) = summary.utilization());
+	assert!(utilization < 0.5);
+	let_assert!(Some(response_delay) = summary.average_response_delay());
+	assert!(response_delay >= Duration::from_millis(15));
+
+	let schedule = bus.schedule().with_response_delay(response_delay).read(4);
+	assert!(schedule.cycle_time() <= duration + Duration::from_micros(1));
+	assert!(schedule.max_loop_rate() < 50.0);
+
+	bus.reset_timing_summary();
+	assert!(bus.last_transaction_timing() == None);
+	assert!(bus.timing_summary().transactions == 0);
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "tracing")]
+
+use assert2::{assert, let_assert};
+use dynamixel2::{Bus, Instructions, ScriptedSerialPort};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+type Fields = BTreeMap<String, String>;
+
+/// A span or event recorded by the [`Recorder`].
+#[derive(Debug, Default)]
+struct Recorded {
+	name: String,
+	fields: Fields,
+	events: Vec<Fields>,
+}
+
+/// A subscriber that records all spans and their events.
+#[derive(Clone, Default)]
+struct Recorder {
+	spans: Arc<Mutex<Vec<Recorded>>>,
+}
+
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldVisitor<'_> {
+	fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+		self.0.insert(field.name().to_owned(), format!("{value:?}"));
+	}
+
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.0.insert(field.name().to_owned(), value.to_owned());
+	}
+}
+
+impl Subscriber for Recorder {
+	fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+		true
+	}
+
+	fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+		let mut span = Recorded {
+			name: attributes.metadata().name().to_owned(),
+			..Default::default()
+		};
+		attributes.record(&mut FieldVisitor(&mut span.fields));
+		let mut spans = self.spans.lock().unwrap();
+		spans.push(span);
+		Id::from_u64(spans.len() as u64)
+	}
+
+	fn record(&self, span: &Id, values: &Record<'_>) {
+		let mut spans = self.spans.lock().unwrap();
+		values.record(&mut FieldVisitor(&mut spans[span.into_u64() as usize - 1].fields));
+	}
+
+	fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+	fn event(&self, event: &Event<'_>) {
+		let mut fields = Fields::new();
+		event.record(&mut FieldVisitor(&mut fields));
+		if let Some(parent) = event.parent() {
+			self.spans.lock().unwrap()[parent.into_u64() as usize - 1].events.push(fields);
+		}
+	}
+
+	fn enter(&self, _span: &Id) {}
+
+	fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn test_transaction_spans() {
+	let serial_port = ScriptedSerialPort::new(57600)
+		.expect(1, Instructions::Read { address: 132, length: 4 })
+		.reply(1, 1234u32.to_le_bytes())
+		.expect(
+			2,
+			Instructions::Write {
+				address: 116,
+				parameters: vec![0, 8, 0, 0],
+			},
+		)
+		.reply_error(2, 0x02, []);
+
+	let recorder = Recorder::default();
+	tracing::subscriber::with_default(recorder.clone(), || {
+		let mut bus = Bus::with_buffers(serial_port, vec![0; 1024], vec![0; 1024]).unwrap();
+		let_assert!(Ok(response) = bus.read_u32(1, 132));
+		assert!(response.data == 1234);
+		assert!(let Err(_) = bus.write_u32(2, 116, 2048));
+	});
+
+	let spans = recorder.spans.lock().unwrap();
+	let spans: Vec<_> = spans.iter().filter(|span| span.name == "dynamixel2::transaction").collect();
+	assert!(spans.len() == 2);
+
+	let read = &spans[0].fields;
+	assert!(read["instruction"] == "read");
+	assert!(read["motor_id"] == "1");
+	assert!(read["address"] == "132");
+	assert!(read["length"] == "4");
+	assert!(read["bytes_sent"] == "14");
+	assert!(read["bytes_received"] == "15");
+	assert!(read["responses"] == "1");
+	assert!(!read.contains_key("error"));
+	assert!(spans[0].events.len() == 1);
+	assert!(spans[0].events[0]["motor_id"] == "1");
+
+	let write = &spans[1].fields;
+	assert!(write["instruction"] == "write");
+	assert!(write["motor_id"] == "2");
+	assert!(write["address"] == "116");
+	assert!(write["length"] == "4");
+	assert!(write["error"] == "motor_error");
+	assert!(spans[1].events.len() == 2);
+	assert!(spans[1].events[1]["error"] == "motor_error");
+}
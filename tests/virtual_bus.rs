@@ -0,0 +1,125 @@
+use assert2::{assert, let_assert};
+use dynamixel2::simulation::xm430::address;
+use dynamixel2::simulation::SimulatedMotor;
+use dynamixel2::{Bus, Device, ReadError, SerialPort, TransferError, VirtualBus, VirtualSerialPort};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+use test_log::test;
+
+const BAUD_RATE: u32 = 1_000_000;
+
+fn run_motors<F>(virtual_bus: &VirtualBus, motor_ids: &[u8], test: F)
+where
+	F: FnOnce(),
+{
+	let stop = Arc::new(AtomicBool::new(false));
+	let motors: Vec<_> = motor_ids
+		.iter()
+		.map(|&id| {
+			let device = Device::with_buffers(virtual_bus.connect(BAUD_RATE), vec![0; 1024], vec![0; 1024]).unwrap();
+			let stop = stop.clone();
+			thread::spawn(move || {
+				let mut motor = SimulatedMotor::new(device, id);
+				while !stop.load(Relaxed) {
+					match motor.serve_one(Duration::from_millis(5)) {
+						Err(TransferError::ReadError(ReadError::Io(e))) if VirtualSerialPort::is_timeout_error(&e) => continue,
+						x => assert!(let Ok(()) = x),
+					}
+				}
+			})
+		})
+		.collect();
+	test();
+	stop.store(true, Relaxed);
+	for motor in motors {
+		motor.join().unwrap();
+	}
+}
+
+#[test]
+fn test_virtual_bus_multiple_devices() {
+	let virtual_bus = VirtualBus::new();
+	let mut bus = Bus::with_buffers(virtual_bus.connect(BAUD_RATE), vec![0; 1024], vec![0; 1024]).unwrap();
+	run_motors(&virtual_bus, &[1, 2], || {
+		let_assert!(Ok(response) = bus.ping(1));
+		assert!(response.motor_id == 1);
+		let_assert!(Ok(response) = bus.ping(2));
+		assert!(response.motor_id == 2);
+		assert!(let Err(TransferError::ReadError(ReadError::Io(_))) = bus.ping(3));
+
+		assert!(let Ok(_) = bus.write_u32(2, address::GOAL_POSITION, 1234));
+		let_assert!(Ok(responses) = bus.sync_read_u32(&[1, 2], address::GOAL_POSITION));
+		assert!(responses.len() == 2);
+		assert!(responses[0].motor_id == 1);
+		assert!(responses[0].data == 0);
+		assert!(responses[1].motor_id == 2);
+		assert!(responses[1].data == 1234);
+	});
+	assert!(virtual_bus.collision_count() == 0);
+}
+
+#[test]
+fn test_virtual_bus_transfer_time() {
+	let virtual_bus = VirtualBus::new();
+	let mut a = virtual_bus.connect(9600);
+	let mut b = virtual_bus.connect(9600);
+
+	// 10 bits per byte at 9600 baud.
+	let start = Instant::now();
+	assert!(let Ok(()) = a.write_all(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]));
+	assert!(start.elapsed() >= Duration::from_micros(10_416));
+
+	let mut buffer = [0; 16];
+	let deadline = b.make_deadline(Duration::from_millis(10));
+	assert!(let Ok(10) = b.read(&mut buffer, &deadline));
+	assert!(buffer[..10] == [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+	let_assert!(Err(e) = b.read(&mut buffer, &deadline));
+	assert!(VirtualSerialPort::is_timeout_error(&e));
+}
+
+#[test]
+fn test_virtual_bus_baud_rate_mismatch() {
+	let virtual_bus = VirtualBus::new();
+	let mut a = virtual_bus.connect(1_000_000);
+	let mut b = virtual_bus.connect(57600);
+
+	assert!(let Ok(()) = a.write_all(&[1, 2, 3]));
+	let mut buffer = [0; 3];
+	let deadline = b.make_deadline(Duration::from_millis(10));
+	assert!(let Ok(3) = b.read(&mut buffer, &deadline));
+	assert!(buffer != [1, 2, 3]);
+}
+
+#[test]
+fn test_virtual_bus_collision() {
+	let virtual_bus = VirtualBus::new();
+	let mut receiver = virtual_bus.connect(9600);
+	let barrier = Arc::new(Barrier::new(2));
+	let writers: Vec<_> = (0..2)
+		.map(|_| {
+			let mut port = virtual_bus.connect(9600);
+			let barrier = barrier.clone();
+			thread::spawn(move || {
+				barrier.wait();
+				port.write_all(&[0x55; 20]).unwrap();
+			})
+		})
+		.collect();
+	for writer in writers {
+		writer.join().unwrap();
+	}
+	assert!(virtual_bus.collision_count() == 1);
+
+	let mut received = Vec::new();
+	let deadline = receiver.make_deadline(Duration::from_millis(10));
+	let mut buffer = [0; 64];
+	while let Ok(read) = receiver.read(&mut buffer, &deadline) {
+		received.extend_from_slice(&buffer[..read]);
+	}
+	assert!(received.len() == 40);
+	assert!(received.iter().any(|&byte| byte != 0x55));
+}
@@ -0,0 +1,75 @@
+-- Wireshark dissector for Dynamixel Protocol 2.0 captures written by `dynamixel2::capture`.
+--
+-- Copy this file to your Wireshark plugin directory (see Help -> About Wireshark -> Folders -> Personal Lua Plugins).
+-- Captures use link type 147 (USER0), which is registered below.
+
+local dxl = Proto("dynamixel2", "Dynamixel Protocol 2.0")
+
+local instructions = {
+	[0x01] = "Ping",
+	[0x02] = "Read",
+	[0x03] = "Write",
+	[0x04] = "Reg Write",
+	[0x05] = "Action",
+	[0x06] = "Factory Reset",
+	[0x08] = "Reboot",
+	[0x10] = "Clear",
+	[0x20] = "Control Table Backup",
+	[0x55] = "Status",
+	[0x82] = "Sync Read",
+	[0x83] = "Sync Write",
+	[0x8A] = "Fast Sync Read",
+	[0x92] = "Bulk Read",
+	[0x93] = "Bulk Write",
+	[0x9A] = "Fast Bulk Read",
+}
+
+local f_header = ProtoField.bytes("dynamixel2.header", "Header")
+local f_id = ProtoField.uint8("dynamixel2.id", "Packet ID", base.DEC)
+local f_length = ProtoField.uint16("dynamixel2.length", "Length", base.DEC)
+local f_instruction = ProtoField.uint8("dynamixel2.instruction", "Instruction", base.HEX, instructions)
+local f_error = ProtoField.uint8("dynamixel2.error", "Error", base.HEX)
+local f_alert = ProtoField.bool("dynamixel2.error.alert", "Alert", 8, nil, 0x80)
+local f_parameters = ProtoField.bytes("dynamixel2.parameters", "Parameters")
+local f_crc = ProtoField.uint16("dynamixel2.crc", "CRC", base.HEX)
+
+dxl.fields = { f_header, f_id, f_length, f_instruction, f_error, f_alert, f_parameters, f_crc }
+
+function dxl.dissector(buffer, pinfo, tree)
+	if buffer:len() < 10 then
+		return 0
+	end
+	pinfo.cols.protocol = "DXL2"
+
+	local id = buffer(4, 1):uint()
+	local instruction = buffer(7, 1):uint()
+	local subtree = tree:add(dxl, buffer(), "Dynamixel Protocol 2.0")
+	subtree:add(f_header, buffer(0, 4))
+	subtree:add(f_id, buffer(4, 1))
+	subtree:add_le(f_length, buffer(5, 2))
+	subtree:add(f_instruction, buffer(7, 1))
+
+	local parameters = 8
+	if instruction == 0x55 then
+		local error = subtree:add(f_error, buffer(8, 1))
+		error:add(f_alert, buffer(8, 1))
+		parameters = 9
+	end
+	local parameter_length = buffer:len() - parameters - 2
+	if parameter_length > 0 then
+		subtree:add(f_parameters, buffer(parameters, parameter_length))
+	end
+	subtree:add_le(f_crc, buffer(buffer:len() - 2, 2))
+
+	local name = instructions[instruction] or string.format("Unknown (0x%02X)", instruction)
+	if instruction == 0x55 then
+		pinfo.cols.info = string.format("Status from ID %d", id)
+	elseif id == 0xFE then
+		pinfo.cols.info = string.format("%s to broadcast ID", name)
+	else
+		pinfo.cols.info = string.format("%s to ID %d", name, id)
+	end
+	return buffer:len()
+end
+
+DissectorTable.get("wtap_encap"):add(wtap.USER0, dxl)